@@ -0,0 +1,123 @@
+use crate::{Bounds, Point};
+
+// A 2D affine transformation, stored like the `matrix(a, b, c, d, e, f)` transforms of CSS and
+// canvas APIs. It maps a point to `(a * x + c * y + e, b * x + d * y + f)`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Affine2 {
+    pub a: f32,
+    pub b: f32,
+    pub c: f32,
+    pub d: f32,
+    pub e: f32,
+    pub f: f32,
+}
+
+impl Default for Affine2 {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Affine2 {
+    pub const IDENTITY: Self = Affine2 {
+        a: 1.0,
+        b: 0.0,
+        c: 0.0,
+        d: 1.0,
+        e: 0.0,
+        f: 0.0,
+    };
+
+    pub fn translation(offset: Point) -> Self {
+        Affine2 {
+            e: offset.x,
+            f: offset.y,
+            ..Self::IDENTITY
+        }
+    }
+
+    pub fn scale(x: f32, y: f32) -> Self {
+        Affine2 {
+            a: x,
+            d: y,
+            ..Self::IDENTITY
+        }
+    }
+
+    // A rotation around the origin, which is clockwise on screens where y points down.
+    pub fn rotation(radians: f32) -> Self {
+        let (sin, cos) = radians.sin_cos();
+        Affine2 {
+            a: cos,
+            b: sin,
+            c: -sin,
+            d: cos,
+            ..Self::IDENTITY
+        }
+    }
+
+    // The transformation that applies `self` and then `next`.
+    pub fn then(self, next: Affine2) -> Self {
+        Affine2 {
+            a: next.a * self.a + next.c * self.b,
+            b: next.b * self.a + next.d * self.b,
+            c: next.a * self.c + next.c * self.d,
+            d: next.b * self.c + next.d * self.d,
+            e: next.a * self.e + next.c * self.f + next.e,
+            f: next.b * self.e + next.d * self.f + next.f,
+        }
+    }
+
+    pub fn transform_point(self, point: Point) -> Point {
+        Point {
+            x: self.a * point.x + self.c * point.y + self.e,
+            y: self.b * point.x + self.d * point.y + self.f,
+        }
+    }
+}
+
+impl Bounds {
+    // The smallest bounds containing the transformed rectangle, e.g. to insert a rotated
+    // element. Empty bounds stay empty.
+    pub fn transform(self, transform: &Affine2) -> Bounds {
+        if self.is_empty() {
+            return Bounds::EMPTY;
+        }
+        Bounds::from_points(
+            self.corners()
+                .map(|corner| transform.transform_point(corner)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::FRAC_PI_2;
+
+    #[test]
+    fn test_transform() {
+        let bounds = Bounds::from_xywh(0.0, 0.0, 20.0, 10.0);
+        let transform =
+            Affine2::scale(2.0, 3.0).then(Affine2::translation(Point { x: 5.0, y: 1.0 }));
+        assert_eq!(
+            bounds.transform(&transform),
+            Bounds::from_xywh(5.0, 1.0, 40.0, 30.0)
+        );
+        assert_eq!(bounds.transform(&Affine2::IDENTITY), bounds);
+
+        let rotated = bounds.transform(&Affine2::rotation(FRAC_PI_2));
+        let expected = Bounds::from_xywh(-10.0, 0.0, 10.0, 20.0);
+        for (actual, expected) in rotated.corners().iter().zip(expected.corners()) {
+            assert!((actual.x - expected.x).abs() < 1e-5 && (actual.y - expected.y).abs() < 1e-5);
+        }
+
+        // Rotating by 45 degrees grows the bounds to fit the diagonal.
+        let square = Bounds::from_center_half_extents(Point::default(), Point { x: 1.0, y: 1.0 });
+        let rotated = square.transform(&Affine2::rotation(FRAC_PI_2 / 2.0));
+        assert!((rotated.width() - 2.0 * 2f32.sqrt()).abs() < 1e-5);
+
+        assert!(Bounds::EMPTY.transform(&transform).is_empty());
+    }
+}
@@ -0,0 +1,68 @@
+use crate::{Bounds, Point, Scalar};
+use arbitrary::{Arbitrary, Result, Unstructured};
+
+impl<'a, S: Arbitrary<'a>> Arbitrary<'a> for Point<S> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Point {
+            x: S::arbitrary(u)?,
+            y: S::arbitrary(u)?,
+        })
+    }
+}
+
+// Generated bounds are always valid, so `min` never exceeds `max` along either axis and no
+// coordinate is NaN. They may still be empty, since both corners can coincide along an axis.
+impl<'a, S: Scalar + Arbitrary<'a>> Arbitrary<'a> for Bounds<S> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        // Corners with NaN coordinates are drawn again. Exhausted input yields zeros, which
+        // always make valid bounds, so this terminates.
+        loop {
+            let a = Point::<S>::arbitrary(u)?;
+            let b = Point::<S>::arbitrary(u)?;
+            let bounds = Bounds {
+                min: Point {
+                    x: a.x.min(b.x),
+                    y: a.y.min(b.y),
+                },
+                max: Point {
+                    x: a.x.max(b.x),
+                    y: a.y.max(b.y),
+                },
+            };
+            if bounds.is_valid() {
+                return Ok(bounds);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_arbitrary_bounds_are_ordered() {
+        let bytes = (0..=255).cycle().take(4096).collect::<Vec<u8>>();
+        let mut u = Unstructured::new(&bytes);
+        for _ in 0..100 {
+            let bounds = Bounds::<i32>::arbitrary(&mut u).unwrap();
+            assert!(bounds.min.x <= bounds.max.x && bounds.min.y <= bounds.max.y);
+        }
+    }
+
+    #[test]
+    fn test_arbitrary_float_bounds_are_valid() {
+        // All ones is a NaN, so the first corners have to be drawn again.
+        let bytes = [0xff; 16]
+            .into_iter()
+            .chain((0..=255).cycle().take(4096))
+            .collect::<Vec<u8>>();
+        let mut u = Unstructured::new(&bytes);
+        for _ in 0..100 {
+            let bounds = Bounds::<f32>::arbitrary(&mut u).unwrap();
+            assert!(bounds.is_valid());
+        }
+        let bounds = Bounds::<f32>::arbitrary(&mut Unstructured::new(&[0xff; 16])).unwrap();
+        assert!(bounds.is_valid());
+    }
+}
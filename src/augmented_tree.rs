@@ -0,0 +1,665 @@
+use crate::{BoundingBox, Bounds, InsertionCost};
+use std::{fmt, iter, mem};
+
+// A summary maintained for every subtree of an `AugmentedTree`, such as the number of
+// leaves or the maximum of some per-leaf value. `BoundsTree` is built on the same core and
+// uses the maximum order within each subtree to prune its searches.
+pub trait Augmentation<T, B> {
+    type Summary: Copy + fmt::Debug;
+
+    fn summarize(bounds: B, data: &T) -> Self::Summary;
+
+    // Combines the summaries of two sibling subtrees. Must be associative, since the shape of
+    // the tree (and thus the grouping of summaries) depends on insertion order.
+    fn combine(left: Self::Summary, right: Self::Summary) -> Self::Summary;
+}
+
+// A bounding volume hierarchy whose internal nodes carry a user-defined summary of their
+// subtree, which is recomputed whenever the subtree changes.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "T: serde::Serialize, B: serde::Serialize, A::Summary: serde::Serialize",
+        deserialize = "T: serde::Deserialize<'de>, B: serde::Deserialize<'de>, A::Summary: serde::Deserialize<'de>"
+    ))
+)]
+pub struct AugmentedTree<T, A: Augmentation<T, B>, B = Bounds> {
+    pub(crate) root: Option<usize>,
+    pub(crate) nodes: Vec<Node<T, B, A::Summary>>,
+    // Custom costs are functions, which can't be serialized, see `set_insertion_cost`.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) insertion_cost: InsertionCost<B>,
+    // The parent of every node, indexed by node, so removals can find a leaf's ancestors
+    // without searching for it. Trees whose nodes were built elsewhere, e.g. by
+    // deserialization, start out without it and have it rebuilt on their first removal.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    parents: Vec<Option<usize>>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    stack: Vec<usize>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    siblings: Vec<usize>,
+}
+
+// The scratch buffers only matter during insertion, so clones start out without them.
+impl<T: Clone, A: Augmentation<T, B>, B: Clone> Clone for AugmentedTree<T, A, B> {
+    fn clone(&self) -> Self {
+        AugmentedTree {
+            root: self.root,
+            nodes: self.nodes.clone(),
+            insertion_cost: self.insertion_cost,
+            parents: self.parents.clone(),
+            stack: Vec::new(),
+            siblings: Vec::new(),
+        }
+    }
+}
+
+impl<T, A: Augmentation<T, B>, B: BoundingBox> Default for AugmentedTree<T, A, B> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, A: Augmentation<T, B>, B: BoundingBox> AugmentedTree<T, A, B> {
+    pub fn new() -> Self {
+        AugmentedTree {
+            root: None,
+            nodes: Vec::new(),
+            insertion_cost: InsertionCost::default(),
+            parents: Vec::new(),
+            stack: Vec::new(),
+            siblings: Vec::new(),
+        }
+    }
+
+    // Preallocates room for `capacity` leaves along with their internal nodes.
+    pub fn with_capacity(capacity: usize) -> Self {
+        AugmentedTree {
+            root: None,
+            nodes: Vec::with_capacity((2 * capacity).saturating_sub(1)),
+            insertion_cost: InsertionCost::default(),
+            parents: Vec::with_capacity((2 * capacity).saturating_sub(1)),
+            stack: Vec::new(),
+            siblings: Vec::new(),
+        }
+    }
+
+    pub fn with_insertion_cost(insertion_cost: InsertionCost<B>) -> Self {
+        AugmentedTree {
+            insertion_cost,
+            ..Self::new()
+        }
+    }
+
+    // Changes how leaves inserted from now on are placed, see
+    // `BoundsTree::set_insertion_cost`.
+    pub fn set_insertion_cost(&mut self, insertion_cost: InsertionCost<B>) {
+        self.insertion_cost = insertion_cost;
+    }
+
+    // Removes all leaves, keeping the allocated memory for reuse.
+    pub fn clear(&mut self) {
+        self.root = None;
+        self.nodes.clear();
+        self.parents.clear();
+    }
+
+    pub fn insert(&mut self, new_bounds: B, data: T) {
+        let summary = A::summarize(new_bounds, &data);
+        self.insert_with(new_bounds, data, |_, _| summary);
+    }
+
+    // The summary of the whole tree, or `None` if it's empty.
+    pub fn summary(&self) -> Option<A::Summary> {
+        self.root.map(|root| self.node(root).summary())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (B, &T)> {
+        self.search(|_, _| true)
+    }
+
+    // Yields the leaves for which `descend` returns true, along with all of their ancestors.
+    // Returning false for an internal node skips its entire subtree.
+    pub fn search<'a>(
+        &'a self,
+        mut descend: impl FnMut(B, A::Summary) -> bool + 'a,
+    ) -> impl Iterator<Item = (B, &'a T)> + 'a {
+        let mut stack = Vec::new();
+        stack.extend(self.root);
+        #[cfg(any(feature = "metrics", feature = "tracing"))]
+        let mut visited = 0;
+        iter::from_fn(move || {
+            while let Some(index) = stack.pop() {
+                #[cfg(any(feature = "metrics", feature = "tracing"))]
+                {
+                    visited += 1;
+                }
+                let node = self.node(index);
+                if !descend(node.bounds(), node.summary()) {
+                    continue;
+                }
+                match node {
+                    Node::Leaf { bounds, data, .. } => return Some((*bounds, data)),
+                    Node::Internal { left, right, .. } => {
+                        stack.push(*right);
+                        stack.push(*left);
+                    }
+                }
+            }
+            #[cfg(feature = "tracing")]
+            tracing::trace!(visited, "search finished");
+            #[cfg(feature = "metrics")]
+            metrics::histogram!("aabb.query.nodes_visited").record(visited as f64);
+            None
+        })
+    }
+
+    // Inserts a new leaf and returns its node index. Before the leaf is attached, `summarize`
+    // is called with the tree and the indices of the subtrees hanging off the insertion path
+    // (the siblings of the path, plus the leaf the new leaf will be paired with), which
+    // together cover every existing leaf. It returns the summary of the new leaf.
+    pub(crate) fn insert_with(
+        &mut self,
+        new_bounds: B,
+        data: T,
+        summarize: impl FnOnce(&Self, &[usize]) -> A::Summary,
+    ) -> usize {
+        let new_node = self.attach_leaf(new_bounds, data, summarize);
+        while let Some(node_index) = self.stack.pop() {
+            self.refit_summary(node_index);
+        }
+        new_node
+    }
+
+    // Like `insert_with`, but leaves the summaries of the new leaf's ancestors as they were,
+    // which saves refitting the same nodes over and over when inserting many leaves in a row.
+    // `refit_all_summaries` must be called before summaries are relied on again.
+    pub(crate) fn insert_with_stale_summaries(
+        &mut self,
+        new_bounds: B,
+        data: T,
+        summarize: impl FnOnce(&Self, &[usize]) -> A::Summary,
+    ) -> usize {
+        let new_node = self.attach_leaf(new_bounds, data, summarize);
+        self.stack.clear();
+        new_node
+    }
+
+    // Inserts a new leaf, leaving the path to its parent on `stack`.
+    fn attach_leaf(
+        &mut self,
+        new_bounds: B,
+        data: T,
+        summarize: impl FnOnce(&Self, &[usize]) -> A::Summary,
+    ) -> usize {
+        self.ensure_parents();
+
+        // If the tree is empty, make the root the new leaf.
+        let Some(mut index) = self.root else {
+            let summary = summarize(self, &[]);
+            let new_node = self.push_leaf(new_bounds, data, summary);
+            self.root = Some(new_node);
+            return new_node;
+        };
+
+        // Search for the best place to add the new leaf based on heuristics.
+        let mut siblings = mem::take(&mut self.siblings);
+        while let Node::Internal {
+            left,
+            right,
+            bounds: node_bounds,
+            ..
+        } = self.node_mut(index)
+        {
+            let left = *left;
+            let right = *right;
+            *node_bounds = node_bounds.merge(new_bounds);
+            self.stack.push(index);
+
+            // Descend to the best-fit child, based on which one would increase
+            // the surface area the least. This attempts to keep the tree balanced
+            // in terms of surface area.
+            //
+            // Ties go to the right child. Built-in costs only involve IEEE arithmetic and
+            // comparisons, which Rust never fuses or widens, so the shape of the tree (and
+            // every query result) is bit-identical across platforms, including wasm.
+            let left_merged = new_bounds.merge(self.node(left).bounds());
+            let right_merged = new_bounds.merge(self.node(right).bounds());
+            if self.insertion_cost.is_cheaper(left_merged, right_merged) {
+                siblings.push(right);
+                index = left;
+            } else {
+                siblings.push(left);
+                index = right;
+            }
+        }
+
+        // We've found a leaf ('index' now refers to a leaf node).
+        // We'll insert a new parent node above the leaf and attach our new leaf to it.
+        let sibling = index;
+        siblings.push(sibling);
+        let summary = summarize(self, &siblings);
+        siblings.clear();
+        self.siblings = siblings;
+
+        let new_node = self.push_leaf(new_bounds, data, summary);
+        let new_parent = self.push_internal(sibling, new_node);
+
+        // If there was an old parent, we need to update its children indices.
+        if let Some(old_parent) = self.stack.last().copied() {
+            self.parents[new_parent] = Some(old_parent);
+            let Node::Internal { left, right, .. } = self.node_mut(old_parent) else {
+                unreachable!();
+            };
+
+            if *left == sibling {
+                *left = new_parent;
+            } else {
+                *right = new_parent;
+            }
+        } else {
+            // If the old parent was the root, the new parent is the new root.
+            self.root = Some(new_parent);
+        }
+
+        new_node
+    }
+
+    // Detaches a leaf, replaces its parent with the leaf's sibling, and refits the bounds and
+    // summaries of the remaining ancestors. Nodes are kept contiguous by moving the last nodes
+    // into the vacated slots, and `moved` is called with the old and new index of every node
+    // that was moved. Returns `None` if the index doesn't refer to a leaf within the tree.
+    pub(crate) fn remove_leaf(
+        &mut self,
+        leaf: usize,
+        mut moved: impl FnMut(usize, usize),
+    ) -> Option<(B, T, A::Summary)> {
+        let Node::Leaf { .. } = self.nodes.get(leaf)? else {
+            return None;
+        };
+        self.ensure_parents();
+        let parent = self.parents[leaf];
+        if let Some(parent) = parent {
+            let Node::Internal { left, right, .. } = *self.node(parent) else {
+                unreachable!()
+            };
+            let sibling = if left == leaf { right } else { left };
+            let grandparent = self.parents[parent];
+            self.parents[sibling] = grandparent;
+            match grandparent {
+                Some(grandparent) => self.replace_child(grandparent, parent, sibling),
+                None => self.root = Some(sibling),
+            }
+            let mut ancestor = grandparent;
+            while let Some(index) = ancestor {
+                self.refit(index);
+                ancestor = self.parents[index];
+            }
+        } else {
+            self.root = None;
+        }
+
+        // Remove the higher index first, so the lower one stays valid.
+        let mut removed = None;
+        let mut indices = [Some(leaf), parent];
+        indices.sort_by(|a, b| b.cmp(a));
+        for index in indices.into_iter().flatten() {
+            let node = self.swap_remove_node(index, &mut moved);
+            if index == leaf {
+                removed = Some(node);
+            }
+        }
+        match removed {
+            Some(Node::Leaf {
+                bounds,
+                data,
+                summary,
+            }) => Some((bounds, data, summary)),
+            _ => unreachable!(),
+        }
+    }
+
+    fn ensure_parents(&mut self) {
+        if self.parents.len() != self.nodes.len() {
+            self.rebuild_parents();
+        }
+    }
+
+    // Recomputes the parent of every node from the child indices of the internal nodes.
+    fn rebuild_parents(&mut self) {
+        self.parents.clear();
+        self.parents.resize(self.nodes.len(), None);
+        for (index, node) in self.nodes.iter().enumerate() {
+            if let Node::Internal { left, right, .. } = *node {
+                self.parents[left] = Some(index);
+                self.parents[right] = Some(index);
+            }
+        }
+    }
+
+    // Removes a detached node, moving the last node into its slot.
+    fn swap_remove_node(
+        &mut self,
+        index: usize,
+        moved: &mut impl FnMut(usize, usize),
+    ) -> Node<T, B, A::Summary> {
+        let last = self.nodes.len() - 1;
+        if index != last {
+            match self.parents[last] {
+                Some(parent) => self.replace_child(parent, last, index),
+                None => self.root = Some(index),
+            }
+            if let Node::Internal { left, right, .. } = *self.node(last) {
+                self.parents[left] = Some(index);
+                self.parents[right] = Some(index);
+            }
+            moved(last, index);
+        }
+        self.parents.swap_remove(index);
+        self.nodes.swap_remove(index)
+    }
+
+    fn replace_child(&mut self, parent: usize, old_child: usize, new_child: usize) {
+        let Node::Internal { left, right, .. } = self.node_mut(parent) else {
+            unreachable!()
+        };
+        if *left == old_child {
+            *left = new_child;
+        } else {
+            *right = new_child;
+        }
+    }
+
+    // Recomputes the bounds and summary of an internal node from its children.
+    fn refit(&mut self, index: usize) {
+        let Node::Internal { left, right, .. } = *self.node(index) else {
+            unreachable!()
+        };
+        let new_bounds = self.node(left).bounds().merge(self.node(right).bounds());
+        let Node::Internal { bounds, .. } = self.node_mut(index) else {
+            unreachable!()
+        };
+        *bounds = new_bounds;
+        self.refit_summary(index);
+    }
+
+    // Combines independently built trees into one by joining their roots under new internal
+    // nodes, returning the offset that was applied to each shard's node indices.
+    pub(crate) fn from_shards(shards: Vec<Self>) -> (Self, Vec<usize>) {
+        let mut tree = AugmentedTree::new();
+        if let Some(shard) = shards.first() {
+            tree.insertion_cost = shard.insertion_cost;
+        }
+        let mut offsets = Vec::with_capacity(shards.len());
+        let mut roots = Vec::with_capacity(shards.len());
+        for shard in shards {
+            let offset = tree.nodes.len();
+            tree.nodes
+                .extend(shard.nodes.into_iter().map(|node| match node {
+                    Node::Internal {
+                        left,
+                        right,
+                        bounds,
+                        summary,
+                    } => Node::Internal {
+                        left: left + offset,
+                        right: right + offset,
+                        bounds,
+                        summary,
+                    },
+                    leaf => leaf,
+                }));
+            offsets.push(offset);
+            roots.extend(shard.root.map(|root| root + offset));
+        }
+
+        tree.rebuild_parents();
+
+        // Join the shard roots pairwise so the top of the hierarchy stays balanced.
+        while roots.len() > 1 {
+            roots = roots
+                .chunks(2)
+                .map(|pair| match *pair {
+                    [left, right] => tree.push_internal(left, right),
+                    [root] => root,
+                    _ => unreachable!(),
+                })
+                .collect();
+        }
+        tree.root = roots.pop();
+        (tree, offsets)
+    }
+
+    // Keeps the shape of the tree but switches it to another augmentation, giving every node
+    // the same summary. The caller is responsible for computing the actual summaries.
+    pub(crate) fn with_summaries<A2: Augmentation<T, B>>(
+        self,
+        summary: A2::Summary,
+    ) -> AugmentedTree<T, A2, B> {
+        AugmentedTree {
+            root: self.root,
+            nodes: self
+                .nodes
+                .into_iter()
+                .map(|node| match node {
+                    Node::Leaf { bounds, data, .. } => Node::Leaf {
+                        bounds,
+                        data,
+                        summary,
+                    },
+                    Node::Internal {
+                        left,
+                        right,
+                        bounds,
+                        ..
+                    } => Node::Internal {
+                        left,
+                        right,
+                        bounds,
+                        summary,
+                    },
+                })
+                .collect(),
+            insertion_cost: self.insertion_cost,
+            parents: self.parents,
+            stack: self.stack,
+            siblings: self.siblings,
+        }
+    }
+
+    // Recomputes the summaries of all internal nodes after leaf summaries were changed. Nodes
+    // are refitted in reverse preorder, which visits children before their parents.
+    pub(crate) fn refit_all_summaries(&mut self) {
+        let mut stack = Vec::new();
+        let mut internal_nodes = Vec::new();
+        stack.extend(self.root);
+        while let Some(index) = stack.pop() {
+            if let Node::Internal { left, right, .. } = *self.node(index) {
+                internal_nodes.push(index);
+                stack.push(left);
+                stack.push(right);
+            }
+        }
+        for index in internal_nodes.into_iter().rev() {
+            self.refit_summary(index);
+        }
+    }
+
+    // Recomputes the summary of an internal node from its children.
+    pub(crate) fn refit_summary(&mut self, index: usize) {
+        let Node::Internal { left, right, .. } = *self.node(index) else {
+            unreachable!()
+        };
+        let new_summary = A::combine(self.node(left).summary(), self.node(right).summary());
+        let Node::Internal { summary, .. } = self.node_mut(index) else {
+            unreachable!()
+        };
+        *summary = new_summary;
+    }
+
+    fn push_leaf(&mut self, bounds: B, data: T, summary: A::Summary) -> usize {
+        self.nodes.push(Node::Leaf {
+            bounds,
+            data,
+            summary,
+        });
+        self.parents.push(None);
+        self.nodes.len() - 1
+    }
+
+    fn push_internal(&mut self, left: usize, right: usize) -> usize {
+        let left_node = self.node(left);
+        let right_node = self.node(right);
+        let new_bounds = left_node.bounds().merge(right_node.bounds());
+        let summary = A::combine(left_node.summary(), right_node.summary());
+        self.nodes.push(Node::Internal {
+            bounds: new_bounds,
+            left,
+            right,
+            summary,
+        });
+        let index = self.nodes.len() - 1;
+        self.parents.push(None);
+        self.parents[left] = Some(index);
+        self.parents[right] = Some(index);
+        index
+    }
+
+    #[inline(always)]
+    pub(crate) fn node(&self, index: usize) -> &Node<T, B, A::Summary> {
+        &self.nodes[index]
+    }
+
+    #[inline(always)]
+    pub(crate) fn node_mut(&mut self, index: usize) -> &mut Node<T, B, A::Summary> {
+        &mut self.nodes[index]
+    }
+}
+
+impl<T: fmt::Debug, A: Augmentation<T, B>, B: fmt::Debug> fmt::Debug for AugmentedTree<T, A, B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AugmentedTree")
+            .field("root", &self.root)
+            .field("nodes", &self.nodes)
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) enum Node<T, B, S> {
+    Leaf {
+        bounds: B,
+        data: T,
+        summary: S,
+    },
+    Internal {
+        left: usize,
+        right: usize,
+        bounds: B,
+        summary: S,
+    },
+}
+
+impl<T, B: Copy, S: Copy> Node<T, B, S> {
+    pub(crate) fn bounds(&self) -> B {
+        match self {
+            Node::Leaf { bounds, .. } => *bounds,
+            Node::Internal { bounds, .. } => *bounds,
+        }
+    }
+
+    pub(crate) fn summary(&self) -> S {
+        match self {
+            Node::Leaf { summary, .. } => *summary,
+            Node::Internal { summary, .. } => *summary,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Point;
+
+    // Tracks how many leaves each subtree contains along with their total weight.
+    struct CountAndWeight;
+
+    impl<B> Augmentation<f32, B> for CountAndWeight {
+        type Summary = (usize, f32);
+
+        fn summarize(_: B, weight: &f32) -> (usize, f32) {
+            (1, *weight)
+        }
+
+        fn combine(left: (usize, f32), right: (usize, f32)) -> (usize, f32) {
+            (left.0 + right.0, left.1 + right.1)
+        }
+    }
+
+    // Tracks the heaviest leaf within each subtree.
+    struct MaxWeight;
+
+    impl<B> Augmentation<f32, B> for MaxWeight {
+        type Summary = f32;
+
+        fn summarize(_: B, weight: &f32) -> f32 {
+            *weight
+        }
+
+        fn combine(left: f32, right: f32) -> f32 {
+            left.max(right)
+        }
+    }
+
+    #[test]
+    fn test_summaries() {
+        let mut tree = AugmentedTree::<f32, CountAndWeight>::new();
+        assert_eq!(tree.summary(), None);
+        for ix in 0..10 {
+            let x = ix as f32 * 2.0;
+            let bounds = Bounds {
+                min: Point { x, y: 0.0 },
+                max: Point { x: x + 1.0, y: 1.0 },
+            };
+            tree.insert(bounds, ix as f32);
+        }
+        assert_eq!(tree.summary(), Some((10, 45.0)));
+        assert_eq!(tree.iter().count(), 10);
+    }
+
+    #[test]
+    fn test_pruned_search() {
+        use rand::{seq::SliceRandom, SeedableRng};
+
+        // Heavy leaves are clustered in one corner, so whole subtrees can be skipped.
+        let mut cells = (0..400).collect::<Vec<_>>();
+        cells.shuffle(&mut rand::rngs::StdRng::seed_from_u64(1));
+        let mut tree = AugmentedTree::<f32, MaxWeight>::new();
+        for cell in cells {
+            let (x, y) = ((cell % 20) as f32, (cell / 20) as f32);
+            let weight = if x < 3.0 && y < 3.0 { 9.0 } else { 1.0 };
+            let bounds = Bounds {
+                min: Point { x, y },
+                max: Point {
+                    x: x + 1.0,
+                    y: y + 1.0,
+                },
+            };
+            tree.insert(bounds, weight);
+        }
+
+        let mut visited = 0;
+        let heavy = tree
+            .search(|_, max_weight| {
+                visited += 1;
+                max_weight >= 9.0
+            })
+            .map(|(bounds, _)| (bounds.min.x, bounds.min.y))
+            .collect::<Vec<_>>();
+        assert_eq!(heavy.len(), 9);
+        assert!(heavy.iter().all(|(x, y)| *x < 3.0 && *y < 3.0));
+        assert!(visited < tree.nodes.len() / 2);
+    }
+}
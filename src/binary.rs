@@ -0,0 +1,201 @@
+use crate::{bounds_tree::Node, Bounds, BoundsTree, Error, Point};
+use std::io::{self, Read, Write};
+
+// A compact binary encoding of a `BoundsTree`, independent of serde. It consists of:
+//
+// - The magic bytes `AABB` followed by a `u32` format version.
+// - The root index (`u32::MAX` for an empty tree) and the number of nodes, as `u32`s.
+// - Every node in storage order: a tag byte (0 for leaves, 1 for internal nodes), the bounds
+//   as four `f32`s, the order, then either the leaf's data or the indices of both children.
+//
+// All numbers are little-endian. Leaf data is encoded by the caller.
+const MAGIC: [u8; 4] = *b"AABB";
+const VERSION: u32 = 1;
+const NONE: u32 = u32::MAX;
+const LEAF: u8 = 0;
+const INTERNAL: u8 = 1;
+
+impl<T: Clone> BoundsTree<T> {
+    pub fn write_to<W: Write>(
+        &self,
+        writer: &mut W,
+        mut write_data: impl FnMut(&mut W, &T) -> io::Result<()>,
+    ) -> io::Result<()> {
+        // Indices are stored as `u32`s, with `NONE` reserved for the missing root.
+        if self.tree.nodes.len() >= NONE as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                Error::CapacityExceeded {
+                    limit: NONE as usize - 1,
+                    requested: self.tree.nodes.len(),
+                },
+            ));
+        }
+        writer.write_all(&MAGIC)?;
+        write_u32(writer, VERSION)?;
+        write_u32(writer, self.tree.root.map_or(NONE, |root| root as u32))?;
+        write_u32(writer, self.tree.nodes.len() as u32)?;
+        for node in &self.tree.nodes {
+            let tag = match node {
+                Node::Leaf { .. } => LEAF,
+                Node::Internal { .. } => INTERNAL,
+            };
+            writer.write_all(&[tag])?;
+            let bounds = node.bounds();
+            for value in [bounds.min.x, bounds.min.y, bounds.max.x, bounds.max.y] {
+                writer.write_all(&value.to_le_bytes())?;
+            }
+            write_u32(writer, node.summary())?;
+            match node {
+                Node::Leaf { data, .. } => write_data(writer, data)?,
+                Node::Internal { left, right, .. } => {
+                    write_u32(writer, *left as u32)?;
+                    write_u32(writer, *right as u32)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Reads a tree written by `write_to`. Malformed input is reported as an error of kind
+    // `InvalidData`, wrapping an `Error` that describes the problem.
+    pub fn read_from<R: Read>(
+        reader: &mut R,
+        mut read_data: impl FnMut(&mut R) -> io::Result<T>,
+    ) -> io::Result<Self> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            return Err(invalid_data(Error::DeserializationMismatch {
+                field: "magic",
+                expected: format!("{MAGIC:?}"),
+                found: format!("{magic:?}"),
+            }));
+        }
+        let version = read_u32(reader)?;
+        if version != VERSION {
+            return Err(invalid_data(Error::DeserializationMismatch {
+                field: "version",
+                expected: VERSION.to_string(),
+                found: version.to_string(),
+            }));
+        }
+
+        let root = read_u32(reader)?;
+        let node_count = read_u32(reader)? as usize;
+        let mut tree = BoundsTree::new();
+        for _ in 0..node_count {
+            let mut tag = [0];
+            reader.read_exact(&mut tag)?;
+            let mut values = [0.0; 4];
+            for value in &mut values {
+                let mut bytes = [0; 4];
+                reader.read_exact(&mut bytes)?;
+                *value = f32::from_le_bytes(bytes);
+            }
+            let bounds = Bounds {
+                min: Point {
+                    x: values[0],
+                    y: values[1],
+                },
+                max: Point {
+                    x: values[2],
+                    y: values[3],
+                },
+            };
+            let summary = read_u32(reader)?;
+            let node = match tag[0] {
+                LEAF => Node::Leaf {
+                    bounds,
+                    data: read_data(reader)?,
+                    summary,
+                },
+                INTERNAL => Node::Internal {
+                    left: read_u32(reader)? as usize,
+                    right: read_u32(reader)? as usize,
+                    bounds,
+                    summary,
+                },
+                tag => {
+                    return Err(invalid_data(Error::DeserializationMismatch {
+                        field: "node tag",
+                        expected: format!("{LEAF} or {INTERNAL}"),
+                        found: tag.to_string(),
+                    }))
+                }
+            };
+            tree.tree.nodes.push(node);
+        }
+
+        tree.tree.root = (root != NONE).then_some(root as usize);
+        tree.validate()
+            .map_err(|error| invalid_data(Error::InvalidTree(error)))?;
+        Ok(tree)
+    }
+}
+
+fn write_u32(writer: &mut impl Write, value: u32) -> io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn invalid_data(error: Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_binary_round_trip() {
+        let mut tree = BoundsTree::new();
+        for (ix, x) in [0.0, 5.0, 20.0, 22.0].into_iter().enumerate() {
+            tree.insert(
+                Bounds {
+                    min: Point { x, y: 0.0 },
+                    max: Point {
+                        x: x + 10.0,
+                        y: 10.0,
+                    },
+                },
+                ix as u16,
+            );
+        }
+
+        let mut bytes = Vec::new();
+        tree.write_to(&mut bytes, |writer, data| {
+            writer.write_all(&data.to_le_bytes())
+        })
+        .unwrap();
+        let read_data = |reader: &mut &[u8]| {
+            let mut bytes = [0; 2];
+            reader.read_exact(&mut bytes)?;
+            Ok(u16::from_le_bytes(bytes))
+        };
+        let loaded = BoundsTree::read_from(&mut bytes.as_slice(), read_data).unwrap();
+        assert_eq!(
+            loaded.iter().collect::<Vec<_>>(),
+            tree.iter().collect::<Vec<_>>()
+        );
+
+        let mut corrupted = bytes.clone();
+        corrupted[4] = 2;
+        let error = BoundsTree::read_from(&mut corrupted.as_slice(), read_data).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(
+            error.into_inner().unwrap().downcast_ref::<Error>(),
+            Some(&Error::DeserializationMismatch {
+                field: "version",
+                expected: "1".into(),
+                found: "2".into()
+            })
+        );
+        assert!(BoundsTree::read_from(&mut &bytes[..bytes.len() - 1], read_data).is_err());
+    }
+}
@@ -0,0 +1,217 @@
+use crate::{Bounds, Bounds3, BoundsN, Scalar};
+use std::fmt;
+
+// The kind of bounds stored in a `BoundsTree`, which lets the same insertion and query
+// machinery serve both 2D and 3D trees.
+//
+// Implement it for your own rectangle type to store it in the tree directly instead of
+// converting to `Bounds` at every call site. `merge` must return bounds that contain both
+// inputs, and `contains` must hold for bounds produced by `merge`.
+pub trait BoundingBox: Copy + fmt::Debug + PartialEq {
+    // When inserting, the tree descends into the child whose bounds would have the lowest
+    // cost after being merged with the new bounds.
+    type Cost: PartialOrd;
+
+    fn merge(self, other: Self) -> Self;
+    fn intersects(self, other: Self) -> bool;
+    fn contains(self, other: Self) -> bool;
+    fn cost(self) -> Self::Cost;
+
+    // Whether the bounds can be stored in a tree, which `BoundsTree::try_insert` checks
+    // before inserting. Bounds with NaN coordinates or a minimum above the maximum break the
+    // invariants that merging and intersection tests rely on.
+    fn is_valid(self) -> bool {
+        true
+    }
+}
+
+// How insertion measures bounds when deciding where a new leaf goes. At every internal node,
+// the tree descends into the child whose bounds would be cheapest after being merged with the
+// new bounds, so the cost shapes the tree. Area suits trees queried with many small regions,
+// while the half perimeter avoids long, thin nodes.
+#[derive(Debug, Default)]
+pub enum InsertionCost<B> {
+    // `BoundingBox::cost`, which is the half perimeter of `Bounds` and its counterpart for
+    // other bounds, e.g. the sum of the extents of `Bounds3`.
+    #[default]
+    HalfPerimeter,
+    Custom(fn(B) -> f64),
+}
+
+impl<B> Clone for InsertionCost<B> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<B> Copy for InsertionCost<B> {}
+
+impl<B: BoundingBox> InsertionCost<B> {
+    // Whether `left` is strictly cheaper than `right`.
+    pub(crate) fn is_cheaper(&self, left: B, right: B) -> bool {
+        match self {
+            InsertionCost::HalfPerimeter => left.cost() < right.cost(),
+            InsertionCost::Custom(cost) => cost(left) < cost(right),
+        }
+    }
+}
+
+impl InsertionCost<Bounds> {
+    pub const AREA: Self = InsertionCost::Custom(|bounds| {
+        (bounds.max.x - bounds.min.x) as f64 * (bounds.max.y - bounds.min.y) as f64
+    });
+}
+
+impl InsertionCost<Bounds3> {
+    pub const VOLUME: Self = InsertionCost::Custom(|bounds| {
+        (bounds.max.x - bounds.min.x) as f64
+            * (bounds.max.y - bounds.min.y) as f64
+            * (bounds.max.z - bounds.min.z) as f64
+    });
+}
+
+impl<S: Scalar> BoundingBox for Bounds<S> {
+    type Cost = S;
+
+    fn merge(self, other: Self) -> Self {
+        Bounds::merge(self, other)
+    }
+
+    fn intersects(self, other: Self) -> bool {
+        Bounds::intersects(self, other)
+    }
+
+    fn contains(self, other: Self) -> bool {
+        Bounds::contains(self, other)
+    }
+
+    fn cost(self) -> S {
+        self.half_perimeter()
+    }
+
+    fn is_valid(self) -> bool {
+        Bounds::is_valid(self)
+    }
+}
+
+impl<S: Scalar> BoundingBox for Bounds3<S> {
+    type Cost = S;
+
+    fn merge(self, other: Self) -> Self {
+        Bounds3::merge(self, other)
+    }
+
+    fn intersects(self, other: Self) -> bool {
+        Bounds3::intersects(self, other)
+    }
+
+    fn contains(self, other: Self) -> bool {
+        Bounds3::contains(self, other)
+    }
+
+    fn cost(self) -> S {
+        self.half_extent_sum()
+    }
+
+    fn is_valid(self) -> bool {
+        Bounds3::is_valid(self)
+    }
+}
+
+impl<S: Scalar, const D: usize> BoundingBox for BoundsN<S, D> {
+    type Cost = S;
+
+    fn merge(self, other: Self) -> Self {
+        BoundsN::merge(self, other)
+    }
+
+    fn intersects(self, other: Self) -> bool {
+        BoundsN::intersects(self, other)
+    }
+
+    fn contains(self, other: Self) -> bool {
+        BoundsN::contains(self, other)
+    }
+
+    fn cost(self) -> S {
+        self.half_extent_sum()
+    }
+
+    fn is_valid(self) -> bool {
+        BoundsN::is_valid(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BoundsTree;
+
+    // A rectangle stored as origin and size, like the ones used by most UI frameworks.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    struct Rect {
+        origin: (f32, f32),
+        size: (f32, f32),
+    }
+
+    impl Rect {
+        fn right(&self) -> f32 {
+            self.origin.0 + self.size.0
+        }
+
+        fn bottom(&self) -> f32 {
+            self.origin.1 + self.size.1
+        }
+    }
+
+    impl BoundingBox for Rect {
+        type Cost = f32;
+
+        fn merge(self, other: Self) -> Self {
+            let origin = (
+                self.origin.0.min(other.origin.0),
+                self.origin.1.min(other.origin.1),
+            );
+            Rect {
+                origin,
+                size: (
+                    self.right().max(other.right()) - origin.0,
+                    self.bottom().max(other.bottom()) - origin.1,
+                ),
+            }
+        }
+
+        fn intersects(self, other: Self) -> bool {
+            self.origin.0 < other.right()
+                && other.origin.0 < self.right()
+                && self.origin.1 < other.bottom()
+                && other.origin.1 < self.bottom()
+        }
+
+        fn contains(self, other: Self) -> bool {
+            self.origin.0 <= other.origin.0
+                && self.origin.1 <= other.origin.1
+                && self.right() >= other.right()
+                && self.bottom() >= other.bottom()
+        }
+
+        fn cost(self) -> f32 {
+            self.size.0 + self.size.1
+        }
+    }
+
+    #[test]
+    fn test_custom_bounding_box() {
+        let rect = |x, y, width, height| Rect {
+            origin: (x, y),
+            size: (width, height),
+        };
+
+        let mut tree = BoundsTree::new();
+        assert_eq!(tree.insert(rect(0.0, 0.0, 10.0, 10.0), "a"), 1);
+        assert_eq!(tree.insert(rect(10.0, 0.0, 10.0, 10.0), "b"), 1);
+        assert_eq!(tree.insert(rect(5.0, 5.0, 10.0, 10.0), "c"), 2);
+        assert_eq!(tree.insert(rect(0.0, 0.0, 1.0, 1.0), "d"), 2);
+        assert_eq!(tree.iter().count(), 4);
+    }
+}
@@ -0,0 +1,115 @@
+use crate::{BoundsTree, Scalar};
+use std::fmt;
+
+// A `BoundsTree` over 3D bounds, e.g. for physics broad phases or voxel engines.
+pub type BoundsTree3<T, S = f32> = BoundsTree<T, Bounds3<S>>;
+
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Bounds3<S = f32> {
+    pub min: Point3<S>,
+    pub max: Point3<S>,
+}
+
+impl<S: Scalar> Bounds3<S> {
+    pub(crate) fn merge(self, other: Bounds3<S>) -> Bounds3<S> {
+        Bounds3 {
+            min: Point3 {
+                x: self.min.x.min(other.min.x),
+                y: self.min.y.min(other.min.y),
+                z: self.min.z.min(other.min.z),
+            },
+            max: Point3 {
+                x: self.max.x.max(other.max.x),
+                y: self.max.y.max(other.max.y),
+                z: self.max.z.max(other.max.z),
+            },
+        }
+    }
+
+    pub(crate) fn intersects(self, other: Bounds3<S>) -> bool {
+        !(self.min.x >= other.max.x
+            || self.max.x <= other.min.x
+            || self.min.y >= other.max.y
+            || self.max.y <= other.min.y
+            || self.min.z >= other.max.z
+            || self.max.z <= other.min.z)
+    }
+
+    pub(crate) fn contains(self, other: Bounds3<S>) -> bool {
+        self.min.x <= other.min.x
+            && self.min.y <= other.min.y
+            && self.min.z <= other.min.z
+            && self.max.x >= other.max.x
+            && self.max.y >= other.max.y
+            && self.max.z >= other.max.z
+    }
+
+    pub(crate) fn is_valid(self) -> bool {
+        self.min.x <= self.max.x && self.min.y <= self.max.y && self.min.z <= self.max.z
+    }
+
+    // The 3D counterpart of `Bounds::half_perimeter`, i.e. a quarter of the sum of all edges.
+    pub(crate) fn half_extent_sum(self) -> S {
+        let width = self.max.x - self.min.x;
+        let height = self.max.y - self.min.y;
+        let depth = self.max.z - self.min.z;
+        width + height + depth
+    }
+}
+
+#[derive(Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Point3<S = f32> {
+    pub x: S,
+    pub y: S,
+    pub z: S,
+}
+
+impl<S: fmt::Debug> fmt::Debug for Point3<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "(x: {:.2?}, y: {:.2?}, z: {:.2?})",
+            self.x, self.y, self.z
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cube(x: f32, y: f32, z: f32, size: f32) -> Bounds3 {
+        Bounds3 {
+            min: Point3 { x, y, z },
+            max: Point3 {
+                x: x + size,
+                y: y + size,
+                z: z + size,
+            },
+        }
+    }
+
+    #[test]
+    fn test_3d_insertion() {
+        let mut tree = BoundsTree3::new();
+        assert_eq!(tree.insert(cube(0.0, 0.0, 0.0, 10.0), "a"), 1);
+
+        // Overlaps "a" when projected onto the XY plane, but not in 3D.
+        assert_eq!(tree.insert(cube(0.0, 0.0, 10.0, 10.0), "b"), 1);
+        assert_eq!(tree.insert(cube(5.0, 5.0, 5.0, 10.0), "c"), 2);
+        assert_eq!(tree.insert(cube(0.0, 0.0, 0.0, 20.0), "d"), 3);
+        assert_eq!(tree.iter().count(), 4);
+
+        let mut cursor = tree.query_cursor(cube(0.0, 0.0, 12.0, 1.0));
+        let mut results = Vec::new();
+        assert!(cursor.poll(usize::MAX, &mut results));
+        let mut results = results
+            .into_iter()
+            .map(|primitive| *primitive.data)
+            .collect::<Vec<_>>();
+        results.sort();
+        assert_eq!(results, ["b", "d"]);
+    }
+}
@@ -0,0 +1,169 @@
+use crate::{Bounds, Bounds3, BoundsTree, Point, Point3, Scalar};
+use std::array;
+
+// A `BoundsTree` over bounds with an arbitrary number of dimensions, e.g. for indexing
+// feature vectors. `Bounds` and `Bounds3` convert to and from the 2D and 3D versions.
+pub type BoundsTreeN<T, S, const D: usize> = BoundsTree<T, BoundsN<S, D>>;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundsN<S, const D: usize> {
+    pub min: PointN<S, D>,
+    pub max: PointN<S, D>,
+}
+
+impl<S: Scalar, const D: usize> Default for BoundsN<S, D> {
+    fn default() -> Self {
+        BoundsN {
+            min: PointN::default(),
+            max: PointN::default(),
+        }
+    }
+}
+
+impl<S: Scalar, const D: usize> BoundsN<S, D> {
+    pub(crate) fn merge(self, other: BoundsN<S, D>) -> BoundsN<S, D> {
+        BoundsN {
+            min: PointN(array::from_fn(|axis| {
+                self.min.0[axis].min(other.min.0[axis])
+            })),
+            max: PointN(array::from_fn(|axis| {
+                self.max.0[axis].max(other.max.0[axis])
+            })),
+        }
+    }
+
+    pub(crate) fn intersects(self, other: BoundsN<S, D>) -> bool {
+        (0..D).all(|axis| {
+            self.min.0[axis] < other.max.0[axis] && self.max.0[axis] > other.min.0[axis]
+        })
+    }
+
+    pub(crate) fn contains(self, other: BoundsN<S, D>) -> bool {
+        (0..D).all(|axis| {
+            self.min.0[axis] <= other.min.0[axis] && self.max.0[axis] >= other.max.0[axis]
+        })
+    }
+
+    pub(crate) fn is_valid(self) -> bool {
+        (0..D).all(|axis| self.min.0[axis] <= self.max.0[axis])
+    }
+
+    // The sum of the extents along every axis, generalizing `Bounds::half_perimeter`.
+    pub(crate) fn half_extent_sum(self) -> S {
+        (0..D).fold(S::default(), |sum, axis| {
+            sum + (self.max.0[axis] - self.min.0[axis])
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PointN<S, const D: usize>(pub [S; D]);
+
+impl<S: Scalar, const D: usize> Default for PointN<S, D> {
+    fn default() -> Self {
+        PointN([S::default(); D])
+    }
+}
+
+impl<S> From<Point<S>> for PointN<S, 2> {
+    fn from(point: Point<S>) -> Self {
+        PointN([point.x, point.y])
+    }
+}
+
+impl<S: Copy> From<PointN<S, 2>> for Point<S> {
+    fn from(point: PointN<S, 2>) -> Self {
+        Point {
+            x: point.0[0],
+            y: point.0[1],
+        }
+    }
+}
+
+impl<S> From<Point3<S>> for PointN<S, 3> {
+    fn from(point: Point3<S>) -> Self {
+        PointN([point.x, point.y, point.z])
+    }
+}
+
+impl<S: Copy> From<PointN<S, 3>> for Point3<S> {
+    fn from(point: PointN<S, 3>) -> Self {
+        Point3 {
+            x: point.0[0],
+            y: point.0[1],
+            z: point.0[2],
+        }
+    }
+}
+
+impl<S> From<Bounds<S>> for BoundsN<S, 2> {
+    fn from(bounds: Bounds<S>) -> Self {
+        BoundsN {
+            min: bounds.min.into(),
+            max: bounds.max.into(),
+        }
+    }
+}
+
+impl<S: Copy> From<BoundsN<S, 2>> for Bounds<S> {
+    fn from(bounds: BoundsN<S, 2>) -> Self {
+        Bounds {
+            min: bounds.min.into(),
+            max: bounds.max.into(),
+        }
+    }
+}
+
+impl<S> From<Bounds3<S>> for BoundsN<S, 3> {
+    fn from(bounds: Bounds3<S>) -> Self {
+        BoundsN {
+            min: bounds.min.into(),
+            max: bounds.max.into(),
+        }
+    }
+}
+
+impl<S: Copy> From<BoundsN<S, 3>> for Bounds3<S> {
+    fn from(bounds: BoundsN<S, 3>) -> Self {
+        Bounds3 {
+            min: bounds.min.into(),
+            max: bounds.max.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::random_bounds;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_2d_bounds_n_matches_bounds() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(5);
+        let mut tree = BoundsTree::new();
+        let mut tree_n = BoundsTreeN::<_, f32, 2>::new();
+        for id in 0..200 {
+            let bounds = random_bounds(&mut rng, 50.0);
+            let bounds_n = BoundsN::from(bounds);
+            assert_eq!(Bounds::from(bounds_n), bounds);
+            assert_eq!(tree_n.insert(bounds_n, id), tree.insert(bounds, id));
+        }
+    }
+
+    #[test]
+    fn test_4d_bounds() {
+        let bounds = |min: [f64; 4], max: [f64; 4]| BoundsN {
+            min: PointN(min),
+            max: PointN(max),
+        };
+
+        let mut tree = BoundsTreeN::new();
+        assert_eq!(tree.insert(bounds([0.0; 4], [1.0; 4]), "a"), 1);
+        assert_eq!(
+            tree.insert(bounds([0.0, 0.0, 0.0, 1.0], [1.0, 1.0, 1.0, 2.0]), "b"),
+            1
+        );
+        assert_eq!(tree.insert(bounds([0.5; 4], [1.5; 4]), "c"), 2);
+    }
+}
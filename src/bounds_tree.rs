@@ -1,119 +1,426 @@
-use std::{cmp, fmt, iter};
+use crate::{
+    augmented_tree, primitive_handle::HandleTable, Augmentation, AugmentedTree, BoundingBox, Error,
+    InsertionCost, Scalar,
+};
+use std::{
+    cmp,
+    collections::HashSet,
+    fmt, iter, mem,
+    ops::{Add, BitAnd, BitOr, Div, Mul, Range, Sub},
+};
 
+pub(crate) type Node<T, B = Bounds> = augmented_tree::Node<T, B, u32>;
+
+type OnIntersection<'a, T, B> = dyn FnMut(Primitive<&T, B>) + 'a;
+
+// Deserialized trees are validated, so malformed input results in an error instead of a tree
+// that panics once it's queried. Clones keep the handles of the original.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(
+        try_from = "UncheckedBoundsTree<T, B>",
+        bound(
+            deserialize = "T: Clone + serde::Deserialize<'de>, B: BoundingBox + serde::Deserialize<'de>"
+        )
+    )
+)]
+pub struct BoundsTree<T, B = Bounds> {
+    pub(crate) tree: AugmentedTree<T, MaxOrdering, B>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) handles: HandleTable,
+    // Reused by the searches for the maximum order of the leaves intersecting new bounds. The
+    // tree's own stack can't be used since it holds the insertion path during those searches.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    search_stack: Vec<usize>,
+}
+
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct UncheckedBoundsTree<T, B> {
+    tree: AugmentedTree<T, MaxOrdering, B>,
+}
+
+#[cfg(feature = "serde")]
+impl<T: Clone, B: BoundingBox> TryFrom<UncheckedBoundsTree<T, B>> for BoundsTree<T, B> {
+    type Error = crate::ValidationError;
+
+    fn try_from(unchecked: UncheckedBoundsTree<T, B>) -> Result<Self, Self::Error> {
+        let tree = BoundsTree {
+            tree: unchecked.tree,
+            handles: HandleTable::default(),
+            search_stack: Vec::new(),
+        };
+        tree.validate()?;
+        Ok(tree)
+    }
+}
+
+// The augmentation used by `BoundsTree`, where the summary of a leaf is its order and the
+// summary of an internal node is the maximum order within its subtree.
 #[derive(Debug)]
-pub struct BoundsTree<T> {
-    root: Option<usize>,
-    nodes: Vec<Node<T>>,
-    stack: Vec<usize>,
+pub(crate) struct MaxOrdering;
+
+impl<T, B> Augmentation<T, B> for MaxOrdering {
+    type Summary = u32;
+
+    // Orders are assigned by `BoundsTree::insert`, which always provides them explicitly.
+    fn summarize(_: B, _: &T) -> u32 {
+        0
+    }
+
+    fn combine(left: u32, right: u32) -> u32 {
+        cmp::max(left, right)
+    }
+}
+
+// Turns a tree that was built for its geometry alone into a `BoundsTree`, e.g. once paint
+// order is needed. The hierarchy is reused as is, and the primitives are ordered as if they
+// had been inserted into a `BoundsTree` in the same sequence as into the original tree.
+impl<T: Clone, A: Augmentation<T, B>, B: BoundingBox> From<AugmentedTree<T, A, B>>
+    for BoundsTree<T, B>
+{
+    fn from(tree: AugmentedTree<T, A, B>) -> Self {
+        let mut tree = BoundsTree {
+            tree: tree.with_summaries(0),
+            handles: HandleTable::default(),
+            search_stack: Vec::new(),
+        };
+        // Leaves are pushed as they're inserted and never move, since `AugmentedTree`
+        // doesn't support removal.
+        let leaves = tree
+            .tree
+            .nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| matches!(node, Node::Leaf { .. }))
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+        tree.recompute_orders(&leaves);
+        tree
+    }
+}
+
+impl<T: Clone, A: Augmentation<T, B>, B: BoundingBox> AugmentedTree<T, A, B> {
+    pub fn into_bounds_tree(self) -> BoundsTree<T, B> {
+        self.into()
+    }
 }
 
-impl<T: Clone> BoundsTree<T> {
+impl<T: Clone, B: BoundingBox> Default for BoundsTree<T, B> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone, B: BoundingBox> BoundsTree<T, B> {
     pub fn new() -> Self {
         BoundsTree {
-            root: None,
-            nodes: Vec::new(),
-            stack: Vec::new(),
-        }
-    }
-
-    pub fn insert(&mut self, new_bounds: Bounds, data: T) -> u32 {
-        // If the tree is empty, make the root the new leaf.
-        if self.root.is_none() {
-            let new_node = self.push_leaf(new_bounds, data, 1);
-            self.root = Some(new_node);
-            return 1;
-        }
-
-        // Search for the best place to add the new leaf based on heuristics.
-        let mut max_intersecting_ordering = 0;
-        let mut index = self.root.unwrap();
-        while let Node::Internal {
-            left,
-            right,
-            bounds: node_bounds,
-            ..
-        } = self.node_mut(index)
-        {
-            let left = *left;
-            let right = *right;
-            *node_bounds = node_bounds.merge(new_bounds);
-            self.stack.push(index);
-
-            // Descend to the best-fit child, based on which one would increase
-            // the surface area the least. This attempts to keep the tree balanced
-            // in terms of surface area. If there is an intersection with the other child,
-            // add its keys to the intersections vector.
-            let left_cost = new_bounds.merge(self.node(left).bounds()).half_perimeter();
-            let right_cost = new_bounds.merge(self.node(right).bounds()).half_perimeter();
-            if left_cost < right_cost {
-                max_intersecting_ordering =
-                    self.collect_max_ordering(right, new_bounds, max_intersecting_ordering);
-                index = left;
-            } else {
-                max_intersecting_ordering =
-                    self.collect_max_ordering(left, new_bounds, max_intersecting_ordering);
-                index = right;
-            }
+            tree: AugmentedTree::new(),
+            handles: HandleTable::default(),
+            search_stack: Vec::new(),
         }
+    }
 
-        // We've found a leaf ('index' now refers to a leaf node).
-        // We'll insert a new parent node above the leaf and attach our new leaf to it.
-        let sibling = index;
+    // Preallocates room for `capacity` primitives.
+    pub fn with_capacity(capacity: usize) -> Self {
+        BoundsTree {
+            tree: AugmentedTree::with_capacity(capacity),
+            handles: HandleTable::default(),
+            search_stack: Vec::new(),
+        }
+    }
 
-        // Check for collision with the located leaf node
-        let Node::Leaf {
-            bounds: sibling_bounds,
-            order: sibling_ordering,
-            ..
-        } = self.node(index)
-        else {
-            unreachable!();
-        };
-        if sibling_bounds.intersects(new_bounds) {
-            max_intersecting_ordering = cmp::max(max_intersecting_ordering, *sibling_ordering);
+    // Creates a tree that measures bounds with `insertion_cost` when choosing where to insert
+    // primitives. This only affects the shape of the tree, not the orders.
+    //
+    // The cost isn't serialized, since custom costs are plain functions, so deserialized trees
+    // measure bounds with `InsertionCost::HalfPerimeter` until `set_insertion_cost` is called.
+    pub fn with_insertion_cost(insertion_cost: InsertionCost<B>) -> Self {
+        BoundsTree {
+            tree: AugmentedTree::with_insertion_cost(insertion_cost),
+            handles: HandleTable::default(),
+            search_stack: Vec::new(),
         }
+    }
 
-        let ordering = max_intersecting_ordering + 1;
-        let new_node = self.push_leaf(new_bounds, data, ordering);
-        let new_parent = self.push_internal(sibling, new_node);
+    // Changes how primitives inserted from now on are placed, e.g. to restore the cost of a
+    // deserialized tree. Existing primitives stay where they are.
+    pub fn set_insertion_cost(&mut self, insertion_cost: InsertionCost<B>) {
+        self.tree.set_insertion_cost(insertion_cost);
+    }
 
-        // If there was an old parent, we need to update its children indices.
-        if let Some(old_parent) = self.stack.last().copied() {
-            let Node::Internal { left, right, .. } = self.node_mut(old_parent) else {
-                unreachable!();
-            };
+    // Removes all primitives while keeping the allocated memory, so a tree that's rebuilt
+    // every frame doesn't need to allocate once it has grown to its steady-state size.
+    // Outstanding handles are invalidated.
+    pub fn clear(&mut self) {
+        self.tree.clear();
+        self.handles.clear();
+    }
+
+    pub fn insert(&mut self, new_bounds: B, data: T) -> u32 {
+        self.insert_leaf(new_bounds, data, 1).1
+    }
+
+    // The number of primitives in the tree.
+    pub fn len(&self) -> usize {
+        self.tree.nodes.len().div_ceil(2)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tree.root.is_none()
+    }
+
+    // The highest order of any primitive, or 0 if the tree is empty.
+    pub fn max_order(&self) -> u32 {
+        self.tree.summary().unwrap_or(0)
+    }
 
-            if *left == sibling {
-                *left = new_parent;
-            } else {
-                *right = new_parent;
+    // The bounds of all primitives combined, or `None` if the tree is empty.
+    pub fn bounds(&self) -> Option<B> {
+        self.root_bounds()
+    }
+
+    // Like `insert`, but the primitive's order is at least `min_order`, e.g. to keep overlays
+    // above all content. Primitives inserted later are still ordered above it if they
+    // intersect it.
+    pub fn insert_with_min_order(&mut self, new_bounds: B, data: T, min_order: u32) -> u32 {
+        self.insert_leaf(new_bounds, data, min_order).1
+    }
+
+    // Inserts a group of primitives that occupies a contiguous band of orders above everything
+    // the group intersects, so it can be rendered into an offscreen layer and composited as a
+    // whole, e.g. with group opacity. Within the band, members are ordered among themselves
+    // like regular insertions. Returns the band, which is empty for an empty group.
+    //
+    // Primitives inserted later are only ordered above the members they intersect. To keep
+    // them from interleaving with the group, insert them with a minimum order of `band.end`.
+    pub fn insert_group(&mut self, primitives: impl IntoIterator<Item = (B, T)>) -> Range<u32> {
+        let primitives = primitives.into_iter().collect::<Vec<_>>();
+        let base_order = primitives
+            .iter()
+            .map(|(bounds, _)| self.max_order_in(*bounds))
+            .max()
+            .unwrap_or(0);
+
+        // Members only intersect earlier members with lower orders within the group, and
+        // everything else they intersect is at or below the base order, so inserting them
+        // with their order within the group as an offset reproduces exactly that offset.
+        let mut group = BoundsTree::<(), B>::new();
+        let mut max_order = base_order;
+        for (bounds, data) in primitives {
+            let order = base_order + group.insert(bounds, ());
+            max_order = cmp::max(max_order, self.insert_leaf(bounds, data, order).1);
+        }
+        base_order + 1..max_order + 1
+    }
+
+    // The maximum order among the primitives intersecting `bounds`, or 0 if there are none.
+    // Inserting `bounds` would assign them this order plus one.
+    pub fn max_order_in(&self, bounds: B) -> u32 {
+        Self::collect_max_ordering(&self.tree, self.tree.root, bounds, &mut Vec::new())
+    }
+
+    // Compresses the orders of all primitives to the dense range starting at 1 while preserving
+    // their relative order, and returns the new maximum order. Orders only ever grow while
+    // primitives are updated and removed, so long-running sessions should call this once in a
+    // while to stay clear of `u32::MAX`.
+    pub fn renormalize_orders(&mut self) -> u32 {
+        let mut orders = self
+            .tree
+            .nodes
+            .iter()
+            .filter_map(|node| match node {
+                Node::Leaf { summary: order, .. } => Some(*order),
+                Node::Internal { .. } => None,
+            })
+            .collect::<Vec<_>>();
+        orders.sort_unstable();
+        orders.dedup();
+        for node in &mut self.tree.nodes {
+            if let Node::Leaf { summary: order, .. } = node {
+                *order = orders.partition_point(|other| other < order) as u32 + 1;
             }
-        } else {
-            // If the old parent was the root, the new parent is the new root.
-            self.root = Some(new_parent);
         }
+        self.tree.refit_all_summaries();
+        orders.len() as u32
+    }
 
-        for node_index in self.stack.drain(..) {
-            let Node::Internal { max_ordering, .. } = &mut self.nodes[node_index] else {
-                unreachable!()
-            };
-            *max_ordering = cmp::max(*max_ordering, ordering);
+    // Reassigns every primitive the lowest order that keeps it above the intersecting
+    // primitives it's currently above, and returns the new maximum order. This undoes the
+    // gaps left behind by removals, updates, and minimum orders, resulting in as few distinct
+    // orders (and thus draw-order switches) as possible. Orders requested explicitly with
+    // `insert_with_min_order` or `insert_above` aren't preserved.
+    pub fn compact_orders(&mut self) -> u32 {
+        let mut leaves = self
+            .tree
+            .nodes
+            .iter()
+            .enumerate()
+            .filter_map(|(index, node)| match node {
+                Node::Leaf { summary: order, .. } => Some((*order, index)),
+                Node::Internal { .. } => None,
+            })
+            .collect::<Vec<_>>();
+        leaves.sort_by_key(|(order, _)| *order);
+        let leaves = leaves
+            .into_iter()
+            .map(|(_, index)| index)
+            .collect::<Vec<_>>();
+        self.recompute_orders(&leaves);
+        self.tree.summary().unwrap_or(0)
+    }
+
+    // Like `insert`, but rejects bounds that would corrupt the tree instead of inserting them.
+    pub fn try_insert(&mut self, new_bounds: B, data: T) -> Result<u32, Error> {
+        if !new_bounds.is_valid() {
+            return Err(Error::InvalidBounds {
+                bounds: format!("{new_bounds:?}"),
+            });
         }
+        Ok(self.insert(new_bounds, data))
+    }
+
+    // Like `insert`, but also calls `on_intersection` with every existing primitive the new
+    // bounds intersect, e.g. to invalidate them or for hit testing. This visits all of them,
+    // so it's slower than `insert`, which only needs to find the topmost one.
+    pub fn insert_with_intersections(
+        &mut self,
+        new_bounds: B,
+        data: T,
+        mut on_intersection: impl FnMut(Primitive<&T, B>),
+    ) -> u32 {
+        self.insert_leaf_reporting(new_bounds, data, 1, Some(&mut on_intersection), false)
+            .1
+    }
+
+    // Inserts a batch of primitives, e.g. a frame's display list, returning their orders. The
+    // maximum orders of the internal nodes are brought up to date once at the end instead of
+    // after every insertion. Until then they can't be used to prune, so every intersecting
+    // leaf is visited, which pays off when primitives mostly intersect few others.
+    pub fn insert_batch(&mut self, primitives: impl IntoIterator<Item = (B, T)>) -> Vec<u32> {
+        let mut ignore_intersection = |_: Primitive<&T, B>| {};
+        let orders = primitives
+            .into_iter()
+            .map(|(bounds, data)| {
+                self.insert_leaf_reporting(bounds, data, 1, Some(&mut ignore_intersection), true)
+                    .1
+            })
+            .collect();
+        self.tree.refit_all_summaries();
+        orders
+    }
+
+    // Inserts a new leaf, returning its node index along with its order.
+    pub(crate) fn insert_leaf(&mut self, new_bounds: B, data: T, min_order: u32) -> (usize, u32) {
+        self.insert_leaf_reporting(new_bounds, data, min_order, None, false)
+    }
+
+    fn insert_leaf_reporting(
+        &mut self,
+        new_bounds: B,
+        data: T,
+        min_order: u32,
+        on_intersection: Option<&mut OnIntersection<'_, T, B>>,
+        stale_summaries: bool,
+    ) -> (usize, u32) {
+        // The new leaf is ordered above every leaf it intersects. Those can only live in the
+        // subtrees hanging off the insertion path, so we search each of them.
+        let mut ordering = 0;
+        let search_stack = &mut self.search_stack;
+        let summarize = |tree: &AugmentedTree<T, MaxOrdering, B>, siblings: &[usize]| {
+            let max_intersecting_ordering = match on_intersection {
+                Some(on_intersection) => {
+                    let mut max_ordering = 0;
+                    let mut stack = siblings.to_vec();
+                    while let Some(index) = stack.pop() {
+                        let node = tree.node(index);
+                        if !node.bounds().intersects(new_bounds) {
+                            continue;
+                        }
+                        match node {
+                            Node::Leaf {
+                                bounds,
+                                data,
+                                summary: order,
+                            } => {
+                                max_ordering = cmp::max(max_ordering, *order);
+                                on_intersection(Primitive {
+                                    bounds: *bounds,
+                                    data,
+                                    order: *order,
+                                });
+                            }
+                            Node::Internal { left, right, .. } => {
+                                stack.push(*right);
+                                stack.push(*left);
+                            }
+                        }
+                    }
+                    max_ordering
+                }
+                None => Self::collect_max_ordering(
+                    tree,
+                    siblings.iter().copied(),
+                    new_bounds,
+                    search_stack,
+                ),
+            };
+            ordering = cmp::max(max_intersecting_ordering + 1, min_order);
+            ordering
+        };
+        let new_node = if stale_summaries {
+            self.tree
+                .insert_with_stale_summaries(new_bounds, data, summarize)
+        } else {
+            self.tree.insert_with(new_bounds, data, summarize)
+        };
+        #[cfg(feature = "metrics")]
+        metrics::counter!("aabb.inserts").increment(1);
+        #[cfg(feature = "tracing")]
+        tracing::trace!(
+            order = ordering,
+            nodes = self.tree.nodes.len(),
+            "inserted primitive"
+        );
+        (new_node, ordering)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Primitive<&T, B>> {
+        self.search(|_| true)
+    }
 
-        ordering
+    // Yields every primitive intersecting the viewport, skipping entire subtrees outside of
+    // it, so content that's scrolled out of view costs next to nothing.
+    pub fn iter_intersecting(&self, viewport: B) -> impl Iterator<Item = Primitive<&T, B>> {
+        self.search(move |bounds| bounds.intersects(viewport))
     }
 
-    pub fn iter(&self) -> impl Iterator<Item = Primitive<&T>> {
+    // Like `iter_intersecting`, but in painter's order like `iter_in_order`.
+    pub fn iter_intersecting_in_order(
+        &self,
+        viewport: B,
+    ) -> impl Iterator<Item = Primitive<&T, B>> {
+        let mut primitives = self.iter_intersecting(viewport).collect::<Vec<_>>();
+        primitives.sort_by_key(|primitive| primitive.order);
+        primitives.into_iter()
+    }
+
+    fn search(&self, mut descend: impl FnMut(B) -> bool) -> impl Iterator<Item = Primitive<&T, B>> {
         let mut stack = Vec::new();
-        stack.extend(self.root);
+        stack.extend(self.tree.root);
         iter::from_fn(move || {
             while let Some(node_ix) = stack.pop() {
-                match self.node(node_ix) {
+                let node = self.node(node_ix);
+                if !descend(node.bounds()) {
+                    continue;
+                }
+                match node {
                     Node::Leaf {
                         bounds,
                         data,
-                        order,
-                        ..
+                        summary: order,
                     } => {
                         return Some(Primitive {
                             bounds: *bounds,
@@ -131,87 +438,361 @@ impl<T: Clone> BoundsTree<T> {
         })
     }
 
-    fn collect_max_ordering(&self, index: usize, bounds: Bounds, max_ordering: u32) -> u32 {
-        match self.node(index) {
-            Node::Leaf {
-                bounds: node_bounds,
-                order: ordering,
-                ..
-            } => {
-                if bounds.intersects(*node_bounds) {
-                    cmp::max(*ordering, max_ordering)
-                } else {
-                    max_ordering
+    // Yields every primitive in the order it should be painted in, i.e. by ascending order.
+    // Primitives with equal orders don't intersect, and are yielded in the same relative order
+    // as `iter`.
+    pub fn iter_in_order(&self) -> impl Iterator<Item = Primitive<&T, B>> {
+        let mut primitives = self.iter().collect::<Vec<_>>();
+        primitives.sort_by_key(|primitive| primitive.order);
+        primitives.into_iter()
+    }
+
+    // Groups primitives into as few batches as possible for drawing, where batches have to be
+    // drawn in sequence and primitives within a batch can be drawn in any order. Unlike grouping
+    // by order, primitives that don't intersect anything between them share a batch even if
+    // their orders differ, e.g. after removals or explicit minimum orders.
+    pub fn draw_batches(&self) -> Vec<Vec<Primitive<&T, B>>> {
+        let mut leaves = self
+            .tree
+            .nodes
+            .iter()
+            .enumerate()
+            .filter_map(|(index, node)| match node {
+                Node::Leaf { summary: order, .. } => Some((*order, index)),
+                Node::Internal { .. } => None,
+            })
+            .collect::<Vec<_>>();
+        leaves.sort_by_key(|(order, _)| *order);
+
+        // Each primitive goes into the batch after the latest one containing a primitive it
+        // intersects and is ordered above. Zero means the leaf wasn't assigned a batch yet.
+        let mut batch_ixs = vec![0; self.tree.nodes.len()];
+        let mut batches = Vec::<Vec<Primitive<&T, B>>>::new();
+        let mut stack = Vec::new();
+        for (order, leaf) in leaves {
+            let Node::Leaf { bounds, data, .. } = self.node(leaf) else {
+                unreachable!()
+            };
+            let mut batch_ix = 0;
+            stack.extend(self.tree.root);
+            while let Some(node_ix) = stack.pop() {
+                match self.node(node_ix) {
+                    Node::Leaf {
+                        bounds: other_bounds,
+                        summary: other_order,
+                        ..
+                    } => {
+                        if *other_order < order && bounds.intersects(*other_bounds) {
+                            batch_ix = cmp::max(batch_ix, batch_ixs[node_ix]);
+                        }
+                    }
+                    Node::Internal {
+                        bounds: node_bounds,
+                        left,
+                        right,
+                        ..
+                    } => {
+                        if bounds.intersects(*node_bounds) {
+                            stack.push(*left);
+                            stack.push(*right);
+                        }
+                    }
                 }
             }
-            Node::Internal {
-                left,
-                right,
-                bounds: node_bounds,
-                max_ordering: node_max_ordering,
-                ..
-            } => {
-                if bounds.intersects(*node_bounds) && max_ordering < *node_max_ordering {
-                    let left_max_ordering = self.collect_max_ordering(*left, bounds, max_ordering);
-                    let right_max_ordering =
-                        self.collect_max_ordering(*right, bounds, max_ordering);
-                    cmp::max(left_max_ordering, right_max_ordering)
-                } else {
-                    max_ordering
+
+            batch_ixs[leaf] = batch_ix + 1;
+            if batches.len() <= batch_ix {
+                batches.push(Vec::new());
+            }
+            batches[batch_ix].push(Primitive {
+                data,
+                bounds: *bounds,
+                order,
+            });
+        }
+        batches
+    }
+
+    // Combines independently built trees into one by joining their roots under new internal
+    // nodes, returning the offset that was applied to each shard's node indices. Orders are
+    // left untouched, so they're only meaningful if no two shards contain intersecting leaves.
+    pub(crate) fn from_shards(shards: Vec<BoundsTree<T, B>>) -> (Self, Vec<usize>) {
+        let (tree, offsets) =
+            AugmentedTree::from_shards(shards.into_iter().map(|shard| shard.tree).collect());
+        (
+            BoundsTree {
+                tree,
+                handles: HandleTable::default(),
+                search_stack: Vec::new(),
+            },
+            offsets,
+        )
+    }
+
+    // Reassigns every order from scratch, as if the given leaves had been inserted in sequence
+    // into a tree with the current shape.
+    pub(crate) fn recompute_orders(&mut self, leaves: &[usize]) {
+        let mut parents = vec![None; self.tree.nodes.len()];
+        for (index, node) in self.tree.nodes.iter_mut().enumerate() {
+            match node {
+                Node::Leaf { summary, .. } => *summary = 0,
+                Node::Internal {
+                    left,
+                    right,
+                    summary,
+                    ..
+                } => {
+                    parents[*left] = Some(index);
+                    parents[*right] = Some(index);
+                    *summary = 0;
                 }
             }
         }
+
+        let mut search_stack = mem::take(&mut self.search_stack);
+        for &leaf in leaves {
+            // Leaves that haven't been visited yet still have an order of zero, so they never
+            // contribute to the maximum.
+            let bounds = self.node(leaf).bounds();
+            let ordering =
+                Self::collect_max_ordering(&self.tree, self.tree.root, bounds, &mut search_stack)
+                    + 1;
+            let Node::Leaf { summary: order, .. } = self.node_mut(leaf) else {
+                unreachable!()
+            };
+            *order = ordering;
+
+            let mut parent = parents[leaf];
+            while let Some(index) = parent {
+                let Node::Internal {
+                    summary: max_ordering,
+                    ..
+                } = self.node_mut(index)
+                else {
+                    unreachable!()
+                };
+                if *max_ordering >= ordering {
+                    break;
+                }
+                *max_ordering = ordering;
+                parent = parents[index];
+            }
+        }
+        self.search_stack = search_stack;
     }
 
-    fn push_leaf(&mut self, bounds: Bounds, data: T, order: u32) -> usize {
-        self.nodes.push(Node::Leaf {
-            bounds,
-            data,
-            order,
-        });
-        self.nodes.len() - 1
-    }
-
-    fn push_internal(&mut self, left: usize, right: usize) -> usize {
-        let left_node = self.node(left);
-        let right_node = self.node(right);
-        let new_bounds = left_node.bounds().merge(right_node.bounds());
-        let max_ordering = cmp::max(left_node.max_ordering(), right_node.max_ordering());
-        self.nodes.push(Node::Internal {
-            bounds: new_bounds,
-            left,
-            right,
-            max_ordering,
-        });
-        self.nodes.len() - 1
+    pub(crate) fn root_bounds(&self) -> Option<B> {
+        self.tree.root.map(|root| self.node(root).bounds())
+    }
+
+    // The maximum order among the leaves in the given subtrees that intersect `bounds`. Subtrees
+    // whose maximum order doesn't exceed the maximum found so far are skipped.
+    fn collect_max_ordering(
+        tree: &AugmentedTree<T, MaxOrdering, B>,
+        roots: impl IntoIterator<Item = usize>,
+        bounds: B,
+        stack: &mut Vec<usize>,
+    ) -> u32 {
+        let mut max_ordering = 0;
+        stack.clear();
+        stack.extend(roots);
+        while let Some(index) = stack.pop() {
+            let node = tree.node(index);
+            if node.summary() <= max_ordering || !bounds.intersects(node.bounds()) {
+                continue;
+            }
+            match node {
+                Node::Leaf {
+                    summary: ordering, ..
+                } => max_ordering = *ordering,
+                Node::Internal { left, right, .. } => {
+                    stack.push(*left);
+                    stack.push(*right);
+                }
+            }
+        }
+        max_ordering
     }
 
     #[inline(always)]
-    fn node(&self, index: usize) -> &Node<T> {
-        &self.nodes[index]
+    pub(crate) fn node(&self, index: usize) -> &Node<T, B> {
+        self.tree.node(index)
     }
 
     #[inline(always)]
-    fn node_mut(&mut self, index: usize) -> &mut Node<T> {
-        &mut self.nodes[index]
+    pub(crate) fn node_mut(&mut self, index: usize) -> &mut Node<T, B> {
+        self.tree.node_mut(index)
     }
 }
 
+// Trees are equal when they contain the same `(bounds, data)` leaves, regardless of how those
+// leaves are arranged internally, so independently built trees can be compared.
+impl<T: Clone + PartialEq, B: BoundingBox> PartialEq for BoundsTree<T, B> {
+    fn eq(&self, other: &Self) -> bool {
+        if self.tree.iter().count() != other.tree.iter().count() {
+            return false;
+        }
+        // Each leaf is looked up spatially in the other tree, and every match is consumed so
+        // that duplicate leaves have to occur equally often in both trees.
+        let mut matched = HashSet::new();
+        self.tree.iter().all(|(bounds, data)| {
+            other
+                .tree
+                .search(|node_bounds, _| node_bounds.contains(bounds))
+                .any(|(other_bounds, other_data)| {
+                    other_bounds == bounds
+                        && other_data == data
+                        && matched.insert(other_data as *const T)
+                })
+        })
+    }
+}
+
+impl<T: Clone + Eq, B: BoundingBox + Eq> Eq for BoundsTree<T, B> {}
+
 #[derive(Default, Debug, Clone, PartialEq)]
-pub struct Primitive<T> {
-    data: T,
-    bounds: Bounds,
-    order: u32,
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Primitive<T, B = Bounds> {
+    pub(crate) data: T,
+    pub(crate) bounds: B,
+    pub(crate) order: u32,
 }
 
-#[derive(Default, Debug, Clone, Copy, PartialEq)]
-pub struct Bounds {
-    pub min: Point,
-    pub max: Point,
+impl<T, B: Copy> Primitive<T, B> {
+    pub fn data(&self) -> &T {
+        &self.data
+    }
+
+    pub fn into_data(self) -> T {
+        self.data
+    }
+
+    pub fn bounds(&self) -> B {
+        self.bounds
+    }
+
+    pub fn order(&self) -> u32 {
+        self.order
+    }
 }
 
-impl Bounds {
-    fn merge(self, other: Bounds) -> Bounds {
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Bounds<S = f32> {
+    pub min: Point<S>,
+    pub max: Point<S>,
+}
+
+impl<S: Scalar> Bounds<S> {
+    // Inverted bounds that contain nothing and intersect nothing. Merging them with any other
+    // bounds yields those bounds, so they're the starting point for folds over collections of
+    // bounds that might be empty.
+    pub const EMPTY: Self = Bounds {
+        min: Point {
+            x: S::HIGHEST,
+            y: S::HIGHEST,
+        },
+        max: Point {
+            x: S::LOWEST,
+            y: S::LOWEST,
+        },
+    };
+
+    pub fn new(min: Point<S>, max: Point<S>) -> Self {
+        Bounds { min, max }
+    }
+
+    pub fn from_center_half_extents(center: Point<S>, half_extents: Point<S>) -> Self {
+        Bounds {
+            min: Point {
+                x: center.x - half_extents.x,
+                y: center.y - half_extents.y,
+            },
+            max: Point {
+                x: center.x + half_extents.x,
+                y: center.y + half_extents.y,
+            },
+        }
+    }
+
+    // Bounds given by their minimum corner and size, like the rectangles of most UI frameworks.
+    pub fn from_xywh(x: S, y: S, width: S, height: S) -> Self {
+        Bounds {
+            min: Point { x, y },
+            max: Point {
+                x: x + width,
+                y: y + height,
+            },
+        }
+    }
+
+    // The smallest bounds containing all of the points, or `Bounds::EMPTY` if there are none.
+    pub fn from_points(points: impl IntoIterator<Item = Point<S>>) -> Self {
+        points.into_iter().fold(Self::EMPTY, |bounds, point| {
+            bounds.merge(Bounds::new(point, point))
+        })
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y
+    }
+
+    // The bounds spanned by two opposite corners given in any order, e.g. the start and end
+    // of a drag selection.
+    pub fn from_corners(a: Point<S>, b: Point<S>) -> Self {
+        Bounds {
+            min: Point {
+                x: a.x.min(b.x),
+                y: a.y.min(b.y),
+            },
+            max: Point {
+                x: a.x.max(b.x),
+                y: a.y.max(b.y),
+            },
+        }
+    }
+
+    // Empty bounds have a width and height of zero, rather than the negative span between
+    // their inverted corners, which would overflow for integer coordinates.
+    pub fn width(self) -> S {
+        if self.is_empty() {
+            return S::default();
+        }
+        self.max.x - self.min.x
+    }
+
+    pub fn height(self) -> S {
+        if self.is_empty() {
+            return S::default();
+        }
+        self.max.y - self.min.y
+    }
+
+    pub fn size(self) -> Point<S> {
+        Point {
+            x: self.width(),
+            y: self.height(),
+        }
+    }
+
+    // The corners in clockwise order on screens where y points down, starting at `min`.
+    pub fn corners(self) -> [Point<S>; 4] {
+        [
+            self.min,
+            Point {
+                x: self.max.x,
+                y: self.min.y,
+            },
+            self.max,
+            Point {
+                x: self.min.x,
+                y: self.max.y,
+            },
+        ]
+    }
+
+    // The smallest bounds containing both.
+    pub fn merge(self, other: Bounds<S>) -> Bounds<S> {
         Bounds {
             min: Point {
                 x: self.min.x.min(other.min.x),
@@ -224,72 +805,347 @@ impl Bounds {
         }
     }
 
-    fn intersects(self, other: Bounds) -> bool {
+    // Bounds that merely touch don't intersect.
+    pub fn intersects(self, other: Bounds<S>) -> bool {
         !(self.min.x >= other.max.x
             || self.max.x <= other.min.x
             || self.min.y >= other.max.y
             || self.max.y <= other.min.y)
     }
 
-    fn half_perimeter(self) -> f32 {
-        let width = self.max.x - self.min.x;
-        let height = self.max.y - self.min.y;
-        width + height
+    // The overlap of two bounds, or `None` if they don't intersect.
+    pub fn intersection(self, other: Bounds<S>) -> Option<Bounds<S>> {
+        self.intersects(other).then(|| Bounds {
+            min: Point {
+                x: self.min.x.max(other.min.x),
+                y: self.min.y.max(other.min.y),
+            },
+            max: Point {
+                x: self.max.x.min(other.max.x),
+                y: self.max.y.min(other.max.y),
+            },
+        })
+    }
+
+    pub fn contains(self, other: Bounds<S>) -> bool {
+        self.min.x <= other.min.x
+            && self.min.y <= other.min.y
+            && self.max.x >= other.max.x
+            && self.max.y >= other.max.y
+    }
+
+    // Bounds include their minimum edges but not their maximum ones, so a point on the edge
+    // shared by two adjacent bounds is only contained by one of them.
+    pub fn contains_point(self, point: Point<S>) -> bool {
+        self.min.x <= point.x
+            && point.x < self.max.x
+            && self.min.y <= point.y
+            && point.y < self.max.y
+    }
+
+    // Comparisons involving NaN are false, so this also rejects NaN coordinates.
+    pub(crate) fn is_valid(self) -> bool {
+        self.min.x <= self.max.x && self.min.y <= self.max.y
+    }
+
+    pub(crate) fn half_perimeter(self) -> S {
+        self.width() + self.height()
     }
 }
 
-#[derive(Debug)]
-enum Node<T> {
-    Leaf {
-        bounds: Bounds,
-        data: T,
-        order: u32,
-    },
-    Internal {
-        left: usize,
-        right: usize,
-        bounds: Bounds,
-        max_ordering: u32,
-    },
+// `a | b` is the union of two bounds, like `merge`.
+impl<S: Scalar> BitOr for Bounds<S> {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        self.merge(other)
+    }
+}
+
+// `a & b` is the intersection of two bounds, or `Bounds::EMPTY` if they don't intersect.
+impl<S: Scalar> BitAnd for Bounds<S> {
+    type Output = Self;
+
+    fn bitand(self, other: Self) -> Self {
+        self.intersection(other).unwrap_or(Self::EMPTY)
+    }
+}
+
+// The bounds enclosing all of the given bounds, or `Bounds::EMPTY` if there are none.
+impl<S: Scalar> iter::Sum for Bounds<S> {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::EMPTY, Self::merge)
+    }
 }
 
-impl<T> Node<T> {
-    fn bounds(&self) -> Bounds {
-        match self {
-            Node::Leaf { bounds, .. } => *bounds,
-            Node::Internal { bounds, .. } => *bounds,
+impl<S: Scalar> FromIterator<Bounds<S>> for Bounds<S> {
+    fn from_iter<I: IntoIterator<Item = Bounds<S>>>(iter: I) -> Self {
+        iter.into_iter().sum()
+    }
+}
+
+// `Scalar` doesn't require multiplication or division, so these are only available for
+// coordinates that support them.
+impl<S: Scalar + Mul<Output = S>> Bounds<S> {
+    pub fn area(self) -> S {
+        self.width() * self.height()
+    }
+
+    // The squared distance from the point to the closest point within the bounds, which is
+    // zero for points inside them. Cheaper than `distance_to_point` and ordered the same way.
+    // Coordinates are compared before subtracting, so unsigned integers don't underflow, but
+    // the squared distance itself must fit in the coordinate type.
+    pub fn distance_squared_to_point(self, point: Point<S>) -> S {
+        let dx = Self::gap(point.x, point.x, self.min.x, self.max.x);
+        let dy = Self::gap(point.y, point.y, self.min.y, self.max.y);
+        dx * dx + dy * dy
+    }
+
+    // The squared length of the shortest gap between two bounds, which is zero if they touch
+    // or intersect.
+    pub fn distance_squared_between(a: Bounds<S>, b: Bounds<S>) -> S {
+        let dx = Self::gap(a.min.x, a.max.x, b.min.x, b.max.x);
+        let dy = Self::gap(a.min.y, a.max.y, b.min.y, b.max.y);
+        dx * dx + dy * dy
+    }
+
+    // The gap between two ranges along one axis, or zero if they overlap.
+    fn gap(a_min: S, a_max: S, b_min: S, b_max: S) -> S {
+        if a_max < b_min {
+            b_min - a_max
+        } else if b_max < a_min {
+            a_min - b_max
+        } else {
+            S::default()
         }
     }
+}
+
+// Square roots aren't available for every `Scalar`, so the Euclidean distances themselves are
+// only provided for the default coordinates. Implementing them for `f64` as well would make
+// calls on bounds built from unsuffixed float literals ambiguous. Use the squared variants
+// for other coordinate types.
+impl Bounds {
+    pub fn distance_to_point(self, point: Point) -> f32 {
+        self.distance_squared_to_point(point).sqrt()
+    }
 
-    fn max_ordering(&self) -> u32 {
-        match self {
-            Node::Leaf {
-                order: ordering, ..
-            } => *ordering,
-            Node::Internal { max_ordering, .. } => *max_ordering,
+    // The length of the shortest gap between two bounds, which is zero if they touch or
+    // intersect.
+    pub fn distance_between(a: Bounds, b: Bounds) -> f32 {
+        Self::distance_squared_between(a, b).sqrt()
+    }
+}
+
+impl<S: Scalar + Div<Output = S> + From<u8>> Bounds<S> {
+    pub fn center(self) -> Point<S> {
+        let two = S::from(2);
+        Point {
+            x: self.min.x + self.width() / two,
+            y: self.min.y + self.height() / two,
         }
     }
 }
 
-#[derive(Default, Clone, Copy, PartialEq)]
-pub struct Point {
-    pub x: f32,
-    pub y: f32,
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Point<S = f32> {
+    pub x: S,
+    pub y: S,
 }
 
-impl fmt::Debug for Point {
+impl<S: fmt::Debug> fmt::Debug for Point<S> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "(x: {:.2}, y: {:.2})", self.x, self.y)
+        write!(f, "(x: {:.2?}, y: {:.2?})", self.x, self.y)
+    }
+}
+
+impl<S: Add<Output = S>> Add for Point<S> {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Point {
+            x: self.x + other.x,
+            y: self.y + other.y,
+        }
+    }
+}
+
+impl<S: Sub<Output = S>> Sub for Point<S> {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Point {
+            x: self.x - other.x,
+            y: self.y - other.y,
+        }
+    }
+}
+
+impl<S: Mul<Output = S> + Copy> Mul<S> for Point<S> {
+    type Output = Self;
+
+    fn mul(self, factor: S) -> Self {
+        Point {
+            x: self.x * factor,
+            y: self.y * factor,
+        }
+    }
+}
+
+impl<S> From<(S, S)> for Point<S> {
+    fn from((x, y): (S, S)) -> Self {
+        Point { x, y }
+    }
+}
+
+impl<S> From<[S; 2]> for Point<S> {
+    fn from([x, y]: [S; 2]) -> Self {
+        Point { x, y }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::test_fixtures::{random_bounds, strip};
     use std::{fs, path::Path};
 
     use super::*;
     use rand::{Rng, SeedableRng};
 
+    #[test]
+    fn test_bounds_constructors() {
+        let bounds = Bounds::new(Point { x: 1.0, y: 2.0 }, Point { x: 5.0, y: 8.0 });
+        assert_eq!(Bounds::from_xywh(1.0, 2.0, 4.0, 6.0), bounds);
+        assert_eq!(
+            Bounds::from_center_half_extents(Point { x: 3.0, y: 5.0 }, Point { x: 2.0, y: 3.0 }),
+            bounds
+        );
+        assert_eq!(
+            Bounds::from_points([
+                Point { x: 5.0, y: 2.0 },
+                Point { x: 1.0, y: 4.0 },
+                Point { x: 3.0, y: 8.0 },
+            ]),
+            bounds
+        );
+        assert!(Bounds::<f32>::from_points([]).is_empty());
+    }
+
+    #[test]
+    fn test_bounds_accessors() {
+        let bounds = Bounds::from_xywh(1.0, 2.0, 4.0, 6.0);
+        assert_eq!((bounds.width(), bounds.height()), (4.0, 6.0));
+        assert_eq!(bounds.size(), Point { x: 4.0, y: 6.0 });
+        assert_eq!(bounds.area(), 24.0);
+        assert_eq!(bounds.center(), Point { x: 3.0, y: 5.0 });
+        assert_eq!(
+            bounds.corners(),
+            [
+                Point { x: 1.0, y: 2.0 },
+                Point { x: 5.0, y: 2.0 },
+                Point { x: 5.0, y: 8.0 },
+                Point { x: 1.0, y: 8.0 },
+            ]
+        );
+        // Integer centers round towards the minimum.
+        assert_eq!(Bounds::from_xywh(0, 0, 3, 5).center(), Point { x: 1, y: 2 });
+    }
+
+    #[test]
+    fn test_empty_integer_bounds() {
+        fn check<S: Scalar + Mul<Output = S> + From<u8>>() {
+            let empty = Bounds::<S>::EMPTY;
+            let zero = S::default();
+            assert_eq!((empty.width(), empty.height()), (zero, zero));
+            assert_eq!((empty.area(), empty.half_perimeter()), (zero, zero));
+
+            let bounds = Bounds::from_xywh(S::from(1), S::from(2), S::from(3), S::from(4));
+            assert_eq!(bounds.merge(empty), bounds);
+            assert_eq!(empty.merge(bounds), bounds);
+            assert_eq!(std::iter::empty().sum::<Bounds<S>>(), empty);
+            assert!(!empty.intersects(bounds));
+        }
+
+        check::<i32>();
+        check::<i64>();
+    }
+
+    #[test]
+    fn test_bounds_set_operations() {
+        let a = Bounds::from_xywh(0.0, 0.0, 10.0, 10.0);
+        let b = Bounds::from_xywh(5.0, 5.0, 10.0, 10.0);
+        assert_eq!(a.merge(b), Bounds::from_xywh(0.0, 0.0, 15.0, 15.0));
+        assert_eq!(
+            a.intersection(b),
+            Some(Bounds::from_xywh(5.0, 5.0, 5.0, 5.0))
+        );
+        assert_eq!(a.intersection(Bounds::from_xywh(10.0, 0.0, 5.0, 5.0)), None);
+        assert!(a.contains(Bounds::from_xywh(0.0, 2.0, 10.0, 5.0)));
+        assert!(!a.contains(b));
+        assert!(a.contains_point(Point { x: 0.0, y: 5.0 }));
+        assert!(!a.contains_point(Point { x: 10.0, y: 5.0 }));
+
+        assert_eq!(a | b, a.merge(b));
+        assert_eq!(a & b, a.intersection(b).unwrap());
+        assert!((a & Bounds::from_xywh(20.0, 0.0, 5.0, 5.0)).is_empty());
+        assert_eq!(a | Bounds::EMPTY, a);
+
+        assert_eq!([a, b].into_iter().sum::<Bounds>(), a | b);
+        assert_eq!([b, a].into_iter().collect::<Bounds>(), a | b);
+        assert!(iter::empty::<Bounds>().sum::<Bounds>().is_empty());
+    }
+
+    #[test]
+    fn test_bounds_distances() {
+        let a = Bounds::from_xywh(0.0, 0.0, 10.0, 10.0);
+        assert_eq!(a.distance_to_point(Point { x: 5.0, y: 5.0 }), 0.0);
+        assert_eq!(a.distance_to_point(Point { x: 13.0, y: 14.0 }), 5.0);
+        assert_eq!(a.distance_squared_to_point(Point { x: -3.0, y: 5.0 }), 9.0);
+        assert_eq!(
+            Bounds::from_xywh(0, 0, 10, 10).distance_squared_to_point(Point { x: 13, y: 14 }),
+            25
+        );
+
+        assert_eq!(
+            Bounds::distance_between(a, Bounds::from_xywh(13.0, 14.0, 5.0, 5.0)),
+            5.0
+        );
+        assert_eq!(
+            Bounds::distance_between(a, Bounds::from_xywh(10.0, 0.0, 5.0, 5.0)),
+            0.0
+        );
+        assert_eq!(
+            Bounds::distance_between(a, Bounds::from_xywh(2.0, 20.0, 5.0, 5.0)),
+            10.0
+        );
+
+        // Integer coordinates don't overflow as long as the squared distance fits, even for
+        // bounds reaching the largest coordinate.
+        let tiles = Bounds::<i32>::from_xywh(10, 10, 5, 5);
+        assert_eq!(tiles.distance_squared_to_point(Point { x: 7, y: 6 }), 25);
+        assert_eq!(tiles.distance_squared_to_point(Point { x: 12, y: 21 }), 36);
+        let wide = Bounds::<i32>::new(Point { x: 0, y: 0 }, Point { x: i32::MAX, y: 1 });
+        assert_eq!(wide.distance_squared_to_point(Point { x: -5, y: 0 }), 25);
+        assert_eq!(
+            Bounds::distance_squared_between(tiles, Bounds::from_xywh(0, 0, 7, 6)),
+            25
+        );
+    }
+
+    #[test]
+    fn test_point_arithmetic() {
+        let a = Point::from((1.0, 2.0));
+        let b = Point::from([3.0, 5.0]);
+        assert_eq!(a + b, Point { x: 4.0, y: 7.0 });
+        assert_eq!(b - a, Point { x: 2.0, y: 3.0 });
+        assert_eq!(a * 2.0, Point { x: 2.0, y: 4.0 });
+        assert_eq!(
+            Point::from((1, 2)) + Point::from([3, 4]),
+            Point { x: 4, y: 6 }
+        );
+    }
+
     #[test]
     fn test_bounds_insertion_with_two_bounds() {
         let mut tree = BoundsTree::new();
@@ -328,6 +1184,563 @@ mod tests {
         assert_eq!(tree.insert(bounds2, "bounds2"), 1);
     }
 
+    #[test]
+    fn test_max_order_in() {
+        let mut tree = BoundsTree::new();
+        assert_eq!(tree.max_order_in(strip(0.0, 10.0)), 0);
+        tree.insert(strip(0.0, 10.0), 'a');
+        tree.insert(strip(5.0, 10.0), 'b');
+        tree.insert(strip(30.0, 10.0), 'c');
+
+        assert_eq!(tree.max_order_in(strip(8.0, 10.0)), 2);
+        assert_eq!(tree.max_order_in(strip(-8.0, 10.0)), 1);
+        assert_eq!(tree.max_order_in(strip(50.0, 10.0)), 0);
+        // Querying doesn't insert anything.
+        assert_eq!(tree.iter().count(), 3);
+        assert_eq!(tree.insert(strip(8.0, 10.0), 'd'), 3);
+    }
+
+    #[test]
+    fn test_insert_with_min_order() {
+        let mut tree = BoundsTree::new();
+        assert_eq!(tree.insert(strip(0.0, 10.0), 'a'), 1);
+        assert_eq!(tree.insert_with_min_order(strip(50.0, 10.0), 'b', 10), 10);
+        // Overlaps can still push a primitive above its minimum.
+        assert_eq!(tree.insert_with_min_order(strip(5.0, 10.0), 'c', 2), 2);
+        assert_eq!(tree.insert_with_min_order(strip(8.0, 10.0), 'd', 2), 3);
+        assert_eq!(tree.insert(strip(55.0, 10.0), 'e'), 11);
+        assert_eq!(tree.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_insert_with_intersections() {
+        let mut tree = BoundsTree::new();
+        for (x, data) in [(0.0, 'a'), (5.0, 'b'), (20.0, 'c'), (8.0, 'd')] {
+            tree.insert(strip(x, 10.0), data);
+        }
+
+        let mut intersections = Vec::new();
+        let order = tree.insert_with_intersections(strip(2.0, 10.0), 'e', |primitive| {
+            intersections.push((*primitive.data, primitive.order))
+        });
+        assert_eq!(order, 4);
+        intersections.sort();
+        assert_eq!(intersections, [('a', 1), ('b', 2), ('d', 3)]);
+    }
+
+    #[test]
+    fn test_renormalize_orders() {
+        let mut tree = BoundsTree::new();
+        tree.insert(strip(0.0, 10.0), 'a');
+        tree.insert_with_min_order(strip(5.0, 10.0), 'b', 1000);
+        tree.insert_with_min_order(strip(30.0, 10.0), 'c', 50);
+        tree.insert(strip(8.0, 10.0), 'd');
+        assert_eq!(tree.max_order_in(strip(0.0, 10.0)), 1001);
+
+        assert_eq!(tree.renormalize_orders(), 4);
+        let mut orders = tree
+            .iter()
+            .map(|primitive| (*primitive.data, primitive.order))
+            .collect::<Vec<_>>();
+        orders.sort();
+        assert_eq!(orders, [('a', 1), ('b', 3), ('c', 2), ('d', 4)]);
+        assert_eq!(tree.validate(), Ok(()));
+        assert_eq!(tree.insert(strip(2.0, 10.0), 'e'), 5);
+    }
+
+    #[test]
+    fn test_iter_in_order() {
+        let mut tree = BoundsTree::new();
+        for (x, id) in [(0.0, 'a'), (5.0, 'b'), (30.0, 'c'), (8.0, 'd'), (32.0, 'e')] {
+            tree.insert(strip(x, 10.0), id);
+        }
+        let ordered = tree
+            .iter_in_order()
+            .map(|primitive| (primitive.order, *primitive.data))
+            .collect::<Vec<_>>();
+        let mut expected = tree
+            .iter()
+            .map(|primitive| (primitive.order, *primitive.data))
+            .collect::<Vec<_>>();
+        expected.sort_by_key(|(order, _)| *order);
+        assert_eq!(ordered, expected);
+        assert_eq!(ordered.last(), Some(&(3, 'd')));
+    }
+
+    #[test]
+    fn test_iter_intersecting() {
+        let mut tree = BoundsTree::new();
+        for (x, id) in [
+            (0.0, 'a'),
+            (5.0, 'b'),
+            (30.0, 'c'),
+            (8.0, 'd'),
+            (100.0, 'e'),
+        ] {
+            tree.insert(strip(x, 10.0), id);
+        }
+        let viewport = Bounds {
+            min: Point { x: 6.0, y: 0.0 },
+            max: Point { x: 35.0, y: 5.0 },
+        };
+        let visible = tree
+            .iter_intersecting_in_order(viewport)
+            .map(|primitive| (primitive.order, *primitive.data))
+            .collect::<Vec<_>>();
+        assert_eq!(visible[3], (3, 'd'));
+        let mut ids = visible.iter().map(|(_, id)| *id).collect::<Vec<_>>();
+        ids.sort();
+        assert_eq!(ids, ['a', 'b', 'c', 'd']);
+        assert_eq!(tree.iter_intersecting(strip(200.0, 10.0)).count(), 0);
+    }
+
+    #[test]
+    fn test_accessors() {
+        let mut tree = BoundsTree::new();
+        assert!(tree.is_empty());
+        assert_eq!((tree.len(), tree.max_order(), tree.bounds()), (0, 0, None));
+
+        tree.insert(strip(0.0, 10.0), 'a');
+        tree.insert(strip(5.0, 10.0), 'b');
+        tree.insert(strip(30.0, 10.0), 'c');
+        assert!(!tree.is_empty());
+        assert_eq!(tree.len(), 3);
+        assert_eq!(tree.max_order(), 2);
+        assert_eq!(
+            tree.bounds(),
+            Some(Bounds {
+                min: Point { x: 0.0, y: 0.0 },
+                max: Point { x: 40.0, y: 10.0 },
+            })
+        );
+
+        let top = tree.iter_in_order().last().unwrap();
+        assert_eq!(
+            (**top.data(), top.bounds(), top.order()),
+            ('b', strip(5.0, 10.0), 2)
+        );
+        assert_eq!(top.into_data(), &'b');
+    }
+
+    #[test]
+    fn test_insert_group() {
+        let mut tree = BoundsTree::new();
+        tree.insert(strip(0.0, 100.0), "background");
+        tree.insert(strip(10.0, 10.0), "button");
+
+        let band = tree.insert_group([
+            (strip(50.0, 20.0), "dialog"),
+            (strip(55.0, 5.0), "title"),
+            (strip(15.0, 10.0), "shadow"),
+            (strip(80.0, 5.0), "close"),
+        ]);
+        assert_eq!(band, 3..5);
+        let mut orders = tree
+            .iter()
+            .map(|primitive| (*primitive.data, primitive.order))
+            .collect::<Vec<_>>();
+        orders.sort();
+        assert_eq!(
+            orders,
+            [
+                ("background", 1),
+                ("button", 2),
+                ("close", 3),
+                ("dialog", 3),
+                ("shadow", 3),
+                ("title", 4)
+            ]
+        );
+        assert!(tree.insert_group([]).is_empty());
+    }
+
+    #[test]
+    fn test_coincident_primitives() {
+        // Coincident primitives all end up on one long path, which used to be searched
+        // recursively.
+        let bounds = Bounds {
+            min: Point { x: 0.0, y: 0.0 },
+            max: Point { x: 1.0, y: 1.0 },
+        };
+        let mut tree = BoundsTree::new();
+        for id in 1..=3000 {
+            assert_eq!(tree.insert(bounds, id), id);
+        }
+        assert_eq!(tree.max_order_in(bounds), 3000);
+        assert_eq!(tree.compact_orders(), 3000);
+    }
+
+    #[test]
+    fn test_insert_batch() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(5);
+        let primitives = (0..200)
+            .map(|id| {
+                let bounds = random_bounds(&mut rng, 50.0);
+                (bounds, id)
+            })
+            .collect::<Vec<_>>();
+
+        let mut expected = BoundsTree::new();
+        let expected_orders = primitives
+            .iter()
+            .map(|(bounds, id)| expected.insert(*bounds, *id))
+            .collect::<Vec<_>>();
+        let mut tree = BoundsTree::new();
+        tree.insert(primitives[0].0, primitives[0].1);
+        let orders = tree.insert_batch(primitives[1..].iter().copied());
+        assert_eq!(orders, expected_orders[1..]);
+        assert_eq!(tree.flatten(), expected.flatten());
+        assert_eq!(tree.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_draw_batches() {
+        let mut tree = BoundsTree::new();
+        tree.insert(strip(0.0, 10.0), 'a');
+        tree.insert(strip(5.0, 10.0), 'b');
+        tree.insert_with_min_order(strip(50.0, 10.0), 'c', 10);
+        tree.insert(strip(55.0, 10.0), 'd');
+        tree.insert(strip(12.0, 10.0), 'e');
+
+        let batches = tree
+            .draw_batches()
+            .into_iter()
+            .map(|batch| {
+                let mut ids = batch
+                    .into_iter()
+                    .map(|primitive| *primitive.data)
+                    .collect::<Vec<_>>();
+                ids.sort();
+                ids
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(batches, [vec!['a', 'c'], vec!['b', 'd'], vec!['e']]);
+        assert!(BoundsTree::<char>::new().draw_batches().is_empty());
+    }
+
+    #[test]
+    fn test_compact_orders() {
+        let mut tree = BoundsTree::new();
+        tree.insert(strip(0.0, 10.0), 'a');
+        let (b, _) = tree.insert_with_handle(strip(5.0, 10.0), 'b');
+        assert_eq!(tree.insert(strip(12.0, 10.0), 'c'), 3);
+        assert_eq!(tree.insert(strip(14.0, 10.0), 'd'), 4);
+        tree.insert_with_min_order(strip(50.0, 10.0), 'e', 10);
+        tree.remove(b);
+
+        assert_eq!(tree.compact_orders(), 2);
+        let mut orders = tree
+            .iter()
+            .map(|primitive| (*primitive.data, primitive.order))
+            .collect::<Vec<_>>();
+        orders.sort();
+        assert_eq!(orders, [('a', 1), ('c', 1), ('d', 2), ('e', 1)]);
+        assert_eq!(tree.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_from_augmented_tree() {
+        struct Count;
+
+        impl<T, B> Augmentation<T, B> for Count {
+            type Summary = usize;
+
+            fn summarize(_: B, _: &T) -> usize {
+                1
+            }
+
+            fn combine(left: usize, right: usize) -> usize {
+                left + right
+            }
+        }
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+        let mut augmented_tree = AugmentedTree::<usize, Count>::new();
+        let mut expected = BoundsTree::new();
+        let mut expected_orders = Vec::new();
+        for id in 0..200 {
+            let bounds = strip(rng.gen_range(0.0..500.0), 10.0);
+            augmented_tree.insert(bounds, id);
+            expected_orders.push((id, expected.insert(bounds, id)));
+        }
+
+        let tree = augmented_tree.into_bounds_tree();
+        let mut orders = tree
+            .iter()
+            .map(|primitive| (*primitive.data, primitive.order))
+            .collect::<Vec<_>>();
+        orders.sort();
+        assert_eq!(orders, expected_orders);
+        assert_eq!(tree.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_insertion_cost() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(11);
+        let primitives = (0..500)
+            .map(|id| {
+                let min = Point {
+                    x: rng.gen_range(0.0..1000.0),
+                    y: rng.gen_range(0.0..1000.0),
+                };
+                let max = Point {
+                    x: min.x + rng.gen_range(0.0..300.0),
+                    y: min.y + rng.gen_range(0.0..3.0),
+                };
+                (Bounds { min, max }, id)
+            })
+            .collect::<Vec<_>>();
+        let build = |insertion_cost| {
+            let mut tree = BoundsTree::with_insertion_cost(insertion_cost);
+            for (bounds, id) in &primitives {
+                tree.insert(*bounds, *id);
+            }
+            tree
+        };
+
+        // The shape changes, but the orders don't.
+        let half_perimeter = build(InsertionCost::HalfPerimeter);
+        let area = build(InsertionCost::AREA);
+        assert_ne!(half_perimeter.stats(), area.stats());
+        let mut orders = area
+            .iter()
+            .map(|primitive| (*primitive.data, primitive.order))
+            .collect::<Vec<_>>();
+        orders.sort();
+        let mut expected_orders = half_perimeter
+            .iter()
+            .map(|primitive| (*primitive.data, primitive.order))
+            .collect::<Vec<_>>();
+        expected_orders.sort();
+        assert_eq!(orders, expected_orders);
+
+        // A constant cost always descends to the right, degenerating into a list.
+        let list = build(InsertionCost::Custom(|_| 0.0));
+        assert_eq!(list.stats().height, primitives.len());
+        assert_eq!(list.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_try_insert_rejects_invalid_bounds() {
+        let mut tree = BoundsTree::new();
+        let valid = Bounds {
+            min: Point { x: 0.0, y: 0.0 },
+            max: Point { x: 10.0, y: 10.0 },
+        };
+        let inverted = Bounds {
+            min: Point { x: 10.0, y: 0.0 },
+            max: Point { x: 0.0, y: 10.0 },
+        };
+        let nan = Bounds {
+            min: Point {
+                x: 0.0,
+                y: f32::NAN,
+            },
+            max: Point { x: 10.0, y: 10.0 },
+        };
+        assert_eq!(tree.try_insert(valid, 'a'), Ok(1));
+        assert_eq!(
+            tree.try_insert(inverted, 'b'),
+            Err(Error::InvalidBounds {
+                bounds: format!("{inverted:?}")
+            })
+        );
+        assert!(tree.try_insert(nan, 'c').is_err());
+        // Degenerate bounds are fine.
+        assert_eq!(tree.try_insert(Bounds::default(), 'd'), Ok(1));
+        assert_eq!(tree.iter().count(), 2);
+    }
+
+    #[test]
+    fn test_bounds_from_corners() {
+        let expected = Bounds {
+            min: Point { x: 0.0, y: 0.0 },
+            max: Point { x: 10.0, y: 5.0 },
+        };
+        for (a, b) in [
+            ((0.0, 0.0), (10.0, 5.0)),
+            ((10.0, 5.0), (0.0, 0.0)),
+            ((0.0, 5.0), (10.0, 0.0)),
+            ((10.0, 0.0), (0.0, 5.0)),
+        ] {
+            let a = Point { x: a.0, y: a.1 };
+            let b = Point { x: b.0, y: b.1 };
+            assert_eq!(Bounds::from_corners(a, b), expected);
+        }
+    }
+
+    #[test]
+    fn test_empty_bounds() {
+        let a = Bounds {
+            min: Point { x: 0.0, y: 0.0 },
+            max: Point { x: 10.0, y: 10.0 },
+        };
+        let b = Bounds {
+            min: Point { x: -5.0, y: 20.0 },
+            max: Point { x: 5.0, y: 30.0 },
+        };
+        assert!(Bounds::<f32>::EMPTY.is_empty());
+        assert!(!a.is_empty());
+        assert_eq!(Bounds::EMPTY.merge(a), a);
+        assert_eq!(a.merge(Bounds::EMPTY), a);
+        assert!(!Bounds::EMPTY.intersects(a));
+        assert!(!a.intersects(Bounds::EMPTY));
+        assert!(a.contains(Bounds::EMPTY));
+        assert_eq!(
+            [a, b].into_iter().fold(Bounds::EMPTY, Bounds::merge),
+            a.merge(b)
+        );
+        assert!([]
+            .into_iter()
+            .fold(Bounds::<i32>::EMPTY, Bounds::merge)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_f64_bounds_far_from_origin() {
+        // At this distance from the origin, `f32` can't represent these bounds and would
+        // collapse both of them to zero width.
+        let offset = 1_000_000_000.0;
+        let mut tree = BoundsTree::<_, Bounds<f64>>::new();
+        let bounds1 = Bounds {
+            min: Point {
+                x: offset,
+                y: offset,
+            },
+            max: Point {
+                x: offset + 10.0,
+                y: offset + 10.0,
+            },
+        };
+        let bounds2 = Bounds {
+            min: Point {
+                x: offset + 5.0,
+                y: offset + 5.0,
+            },
+            max: Point {
+                x: offset + 20.0,
+                y: offset + 20.0,
+            },
+        };
+
+        assert_eq!(tree.insert(bounds1, "bounds1"), 1);
+        assert_eq!(tree.insert(bounds2, "bounds2"), 2);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        let mut tree = BoundsTree::new();
+        tree.insert(strip(0.0, 10.0), 'a');
+        tree.insert(strip(5.0, 10.0), 'b');
+
+        let json = serde_json::to_string(&tree).unwrap();
+        let mut loaded: BoundsTree<char> = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            loaded.iter().collect::<Vec<_>>(),
+            tree.iter().collect::<Vec<_>>()
+        );
+        // The loaded tree keeps its structure and summaries, so it can be inserted into.
+        assert_eq!(loaded.insert(strip(8.0, 10.0), 'c'), 3);
+
+        // The insertion cost isn't serialized, but can be restored after loading.
+        let mut tree = BoundsTree::with_insertion_cost(InsertionCost::AREA);
+        let mut loaded: BoundsTree<char> =
+            serde_json::from_str(&serde_json::to_string(&tree).unwrap()).unwrap();
+        loaded.set_insertion_cost(InsertionCost::AREA);
+        let mut rng = rand::rngs::StdRng::seed_from_u64(11);
+        for _ in 0..200 {
+            let x = rng.gen_range(0.0..1000.0);
+            let y = rng.gen_range(0.0..1000.0);
+            let thin = Bounds::from_xywh(x, y, rng.gen_range(0.0..300.0), 1.0);
+            tree.insert(thin, 'w');
+            loaded.insert(thin, 'w');
+        }
+        assert_eq!(loaded, tree);
+        assert_eq!(loaded.stats(), tree.stats());
+
+        // Malformed trees are rejected instead of panicking when they're used.
+        let corrupted = json.replace("\"root\":2", "\"root\":9");
+        assert_ne!(corrupted, json);
+        assert!(serde_json::from_str::<BoundsTree<char>>(&corrupted).is_err());
+    }
+
+    #[test]
+    fn test_content_equality() {
+        let mut a = BoundsTree::new();
+        let mut b = BoundsTree::new();
+        for x in [0, 5, 20, 40, 5] {
+            a.insert(strip(x as f32, 10.0), x);
+        }
+        for x in [40, 5, 20, 5, 0] {
+            b.insert(strip(x as f32, 10.0), x);
+        }
+        assert_eq!(a, b);
+
+        // Duplicates have to occur equally often.
+        a.insert(strip(0.0, 10.0), 0);
+        b.insert(strip(5.0, 10.0), 5);
+        assert_ne!(a, b);
+
+        let mut c = BoundsTree::new();
+        c.insert(strip(0.0, 10.0), 1);
+        let mut d = BoundsTree::new();
+        d.insert(strip(0.0, 10.0), 2);
+        assert_ne!(c, d);
+        assert_ne!(c, BoundsTree::new());
+    }
+
+    #[test]
+    fn test_deterministic_shape() {
+        // A fixed xorshift generator rather than `rand`, whose output may change between
+        // versions.
+        let mut state = 0x2545_f491_u32;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            (state % 10_000) as f32 / 100.0
+        };
+
+        // Include duplicate bounds to exercise ties between equally good children.
+        let mut tree = BoundsTree::new();
+        for id in 0..500 {
+            let (x, y) = (next(), next());
+            let bounds = Bounds {
+                min: Point { x, y },
+                max: Point {
+                    x: x + next() / 4.0,
+                    y: y + next() / 4.0,
+                },
+            };
+            tree.insert(bounds, id);
+            tree.insert(bounds, id);
+        }
+
+        // Hash the exact layout of every node, so any platform-dependent decision would
+        // change the result.
+        let mut hash = 0xcbf2_9ce4_8422_2325_u64;
+        let mut write = |value: u32| {
+            hash ^= value as u64;
+            hash = hash.wrapping_mul(0x100_0000_01b3);
+        };
+        for node in &tree.tree.nodes {
+            let bounds = node.bounds();
+            for value in [bounds.min.x, bounds.min.y, bounds.max.x, bounds.max.y] {
+                write(value.to_bits());
+            }
+            write(node.summary());
+            match node {
+                Node::Leaf { data, .. } => write(*data),
+                Node::Internal { left, right, .. } => {
+                    write(*left as u32);
+                    write(*right as u32);
+                }
+            }
+        }
+        assert_eq!(hash, 11526800684657424021);
+    }
+
     #[test]
     fn test_random_iterations() {
         let max_bounds = 100;
@@ -390,8 +1803,23 @@ mod tests {
                 insert_time += t0.elapsed();
                 assert_eq!(actual_ordering, expected_ordering);
 
+                #[cfg(feature = "svg")]
                 if debug {
-                    draw_bounds_tree(format!("./svg/bounds_tree_after_{}.svg", quad_id), &tree);
+                    let style = crate::SvgStyle {
+                        view_box: Some(Bounds {
+                            min: Point {
+                                x: -100.0,
+                                y: -100.0,
+                            },
+                            max: Point { x: 100.0, y: 100.0 },
+                        }),
+                        ..Default::default()
+                    };
+                    fs::write(
+                        format!("./svg/bounds_tree_after_{}.svg", quad_id),
+                        tree.render_svg(&style),
+                    )
+                    .unwrap();
                 }
 
                 // Verify the tree contains all the AABBs.
@@ -436,59 +1864,4 @@ mod tests {
         svg_content.push_str("</svg>");
         fs::write(svg_path, &svg_content).unwrap();
     }
-
-    fn draw_bounds_tree(svg_path: impl AsRef<Path>, tree: &BoundsTree<usize>) {
-        let mut svg_content = String::from(
-            r#"<svg xmlns="http://www.w3.org/2000/svg" version="1.1" viewBox="-100 -100 200 200" style="border:1px solid black;">"#,
-        );
-
-        fn draw_node<T: fmt::Debug>(svg_content: &mut String, nodes: &[Node<T>], index: usize) {
-            match &nodes[index] {
-                Node::Internal {
-                    bounds,
-                    left,
-                    right,
-                    ..
-                } => {
-                    svg_content.push_str(&format!(
-                        r#"<rect x="{}" y="{}" width="{}" height="{}" style="fill:rgba({},{},{},0.5);stroke:rgba({},{},{},1);stroke-width:1" />"#,
-                        bounds.min.x,
-                        bounds.min.y,
-                        bounds.max.x - bounds.min.x,
-                        bounds.max.y - bounds.min.y,
-                        (index * 50) % 255, // Red component
-                        (index * 120) % 255, // Green component
-                        (index * 180) % 255, // Blue component
-                        (index * 50) % 255, // Red stroke
-                        (index * 120) % 255, // Green stroke
-                        (index * 180) % 255  // Blue stroke
-                    ));
-                    draw_node(svg_content, nodes, *left);
-                    draw_node(svg_content, nodes, *right);
-                }
-                Node::Leaf { bounds, data, .. } => {
-                    svg_content.push_str(&format!(
-                        r#"<rect x="{}" y="{}" width="{}" height="{}" style="fill:none;stroke:black;stroke-width:1" />"#,
-                        bounds.min.x,
-                        bounds.min.y,
-                        bounds.max.x - bounds.min.x,
-                        bounds.max.y - bounds.min.y
-                    ));
-                    svg_content.push_str(&format!(
-                        r#"<text x="{}" y="{}" font-size="3" text-anchor="middle" alignment-baseline="central">{:?}</text>"#,
-                        (bounds.min.x + bounds.max.x) / 2.0,
-                        (bounds.min.y + bounds.max.y) / 2.0,
-                        data
-                    ));
-                }
-            }
-        }
-
-        if let Some(root) = tree.root {
-            draw_node(&mut svg_content, &tree.nodes, root);
-        }
-
-        svg_content.push_str("</svg>");
-        fs::write(svg_path, &svg_content).unwrap();
-    }
 }
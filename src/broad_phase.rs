@@ -0,0 +1,385 @@
+use crate::{BoundingBox, Bounds, Error, PersistentBoundsTree, Point};
+use std::{collections::HashSet, mem};
+
+// A broad phase for physics engines, modeled after the one in rapier. Every collider is
+// registered as a proxy whose bounds are updated as it moves, and `update` reports the pairs
+// of proxies that started or stopped overlapping since the previous call.
+pub struct BroadPhase<K, B = Bounds> {
+    tree: PersistentBoundsTree<ProxyHandle, B>,
+    // Enlarges the bounds stored in the tree for loose broad phases, see `BroadPhase::loose`.
+    loosen: Option<fn(B, f32) -> B>,
+    looseness: f32,
+    slots: Vec<Slot<K, B>>,
+    free_slots: Vec<usize>,
+    dirty: Vec<ProxyHandle>,
+    pending_events: Vec<PairEvent<K>>,
+}
+
+// Identifies a proxy by its slot along with the generation of that slot. Slots are reused
+// once their proxy is removed, and bumping the generation on removal makes sure that handles
+// to the removed proxy are rejected instead of addressing the slot's new occupant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ProxyHandle {
+    pub(crate) index: usize,
+    pub(crate) generation: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PairEvent<K> {
+    Added(K, K),
+    Removed(K, K),
+}
+
+struct Slot<K, B> {
+    generation: u32,
+    proxy: Option<Proxy<K, B>>,
+}
+
+struct Proxy<K, B> {
+    key: K,
+    bounds: B,
+    // The bounds the proxy is stored with in the tree, which contain `bounds`.
+    tree_bounds: B,
+    overlaps: HashSet<ProxyHandle>,
+}
+
+impl<K: Copy, B: BoundingBox> Default for BroadPhase<K, B> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Copy, B: BoundingBox> BroadPhase<K, B> {
+    pub fn new() -> Self {
+        BroadPhase {
+            tree: PersistentBoundsTree::new(),
+            loosen: None,
+            looseness: 0.0,
+            slots: Vec::new(),
+            free_slots: Vec::new(),
+            dirty: Vec::new(),
+            pending_events: Vec::new(),
+        }
+    }
+
+    // Registers a new proxy for the collider identified by `key`. Its overlaps are reported
+    // by the next call to `update`. Panics if the bounds are invalid, e.g. contain NaN.
+    pub fn create_proxy(&mut self, key: K, bounds: B) -> ProxyHandle {
+        self.try_create_proxy(key, bounds).unwrap()
+    }
+
+    // Registers a new proxy, failing if the bounds are invalid.
+    pub fn try_create_proxy(&mut self, key: K, bounds: B) -> Result<ProxyHandle, Error> {
+        let tree_bounds = self.valid_tree_bounds(bounds)?;
+        let proxy = Proxy {
+            key,
+            bounds,
+            tree_bounds,
+            overlaps: HashSet::new(),
+        };
+        let handle = match self.free_slots.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index];
+                slot.proxy = Some(proxy);
+                ProxyHandle {
+                    index,
+                    generation: slot.generation,
+                }
+            }
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    proxy: Some(proxy),
+                });
+                ProxyHandle {
+                    index: self.slots.len() - 1,
+                    generation: 0,
+                }
+            }
+        };
+        self.tree = self.tree.insert(tree_bounds, handle).0;
+        self.dirty.push(handle);
+        Ok(handle)
+    }
+
+    // Moves a proxy. Panics if the proxy was removed or the bounds are invalid.
+    pub fn set_bounds(&mut self, handle: ProxyHandle, bounds: B) {
+        self.try_set_bounds(handle, bounds).unwrap()
+    }
+
+    // Moves a proxy, failing if the proxy was removed or the bounds are invalid.
+    pub fn try_set_bounds(&mut self, handle: ProxyHandle, bounds: B) -> Result<(), Error> {
+        let tree_bounds = self.valid_tree_bounds(bounds)?;
+        let loose = self.loosen.is_some();
+        let proxy = self.proxy_mut(handle).ok_or(Error::StaleHandle {
+            index: handle.index,
+            generation: handle.generation,
+        })?;
+        let old_bounds = mem::replace(&mut proxy.bounds, bounds);
+        if old_bounds == bounds {
+            return Ok(());
+        }
+        let old_tree_bounds = proxy.tree_bounds;
+        let moved_in_tree = !loose || !old_tree_bounds.contains(bounds);
+        if moved_in_tree {
+            proxy.tree_bounds = tree_bounds;
+        }
+        self.dirty.push(handle);
+        if !moved_in_tree {
+            return Ok(());
+        }
+
+        // Proxies only ever have valid bounds, so they can always be found in the tree.
+        self.tree = self
+            .tree
+            .remove(old_tree_bounds, &handle)
+            .expect("every proxy is in the tree")
+            .insert(tree_bounds, handle)
+            .0;
+        Ok(())
+    }
+
+    // Removes a proxy, reporting all of its pairs as removed on the next call to `update`.
+    pub fn remove_proxy(&mut self, handle: ProxyHandle) -> Option<K> {
+        let slot = self
+            .slots
+            .get_mut(handle.index)
+            .filter(|slot| slot.generation == handle.generation)?;
+        let proxy = slot.proxy.take()?;
+        slot.generation = slot.generation.wrapping_add(1);
+        // Proxies only ever have valid bounds, so they can always be found in the tree.
+        self.tree = self
+            .tree
+            .remove(proxy.tree_bounds, &handle)
+            .expect("every proxy is in the tree");
+        for other in proxy.overlaps {
+            if let Some(other) = self.proxy_mut(other) {
+                other.overlaps.remove(&handle);
+                let other_key = other.key;
+                self.pending_events
+                    .push(PairEvent::Removed(proxy.key, other_key));
+            }
+        }
+        self.free_slots.push(handle.index);
+        Some(proxy.key)
+    }
+
+    pub fn bounds(&self, handle: ProxyHandle) -> Option<B> {
+        Some(self.proxy(handle)?.bounds)
+    }
+
+    // The bounds to store a proxy with in the tree. Bounds that wouldn't be contained by their
+    // ancestors, like those with NaN coordinates, are rejected, since the proxy couldn't be
+    // found again to move or remove it.
+    fn valid_tree_bounds(&self, bounds: B) -> Result<B, Error> {
+        let tree_bounds = match self.loosen {
+            Some(loosen) => loosen(bounds, self.looseness),
+            None => bounds,
+        };
+        if !bounds.is_valid() || !tree_bounds.is_valid() {
+            return Err(Error::InvalidBounds {
+                bounds: format!("{bounds:?}"),
+            });
+        }
+        Ok(tree_bounds)
+    }
+
+    fn proxy(&self, handle: ProxyHandle) -> Option<&Proxy<K, B>> {
+        let slot = self.slots.get(handle.index)?;
+        if slot.generation != handle.generation {
+            return None;
+        }
+        slot.proxy.as_ref()
+    }
+
+    fn proxy_mut(&mut self, handle: ProxyHandle) -> Option<&mut Proxy<K, B>> {
+        let slot = self.slots.get_mut(handle.index)?;
+        if slot.generation != handle.generation {
+            return None;
+        }
+        slot.proxy.as_mut()
+    }
+
+    // Appends an event for every pair that started or stopped overlapping since the last
+    // update. Only proxies that were created or moved in the meantime are queried.
+    pub fn update(&mut self, events: &mut Vec<PairEvent<K>>) {
+        events.append(&mut self.pending_events);
+
+        let mut dirty = mem::take(&mut self.dirty);
+        dirty.sort_unstable();
+        dirty.dedup();
+        for handle in dirty.drain(..) {
+            let Some(proxy) = self.proxy_mut(handle) else {
+                continue;
+            };
+            let key = proxy.key;
+            let bounds = proxy.bounds;
+            let old_overlaps = mem::take(&mut proxy.overlaps);
+            let overlaps = self
+                .tree
+                .query(bounds)
+                .map(|primitive| *primitive.data)
+                .filter(|other| {
+                    *other != handle
+                        && (self.loosen.is_none()
+                            || self
+                                .proxy(*other)
+                                .is_some_and(|other| other.bounds.intersects(bounds)))
+                })
+                .collect::<HashSet<_>>();
+
+            for other in old_overlaps.difference(&overlaps) {
+                if let Some(other) = self.proxy_mut(*other) {
+                    other.overlaps.remove(&handle);
+                    events.push(PairEvent::Removed(key, other.key));
+                }
+            }
+            for other in overlaps.difference(&old_overlaps) {
+                if let Some(other) = self.proxy_mut(*other) {
+                    other.overlaps.insert(handle);
+                    events.push(PairEvent::Added(key, other.key));
+                }
+            }
+            if let Some(proxy) = self.proxy_mut(handle) {
+                proxy.overlaps = overlaps;
+            }
+        }
+        self.dirty = dirty;
+    }
+}
+
+impl<K: Copy> BroadPhase<K> {
+    // Creates a broad phase that stores proxies with bounds enlarged by `looseness` times
+    // their size on every side, like a loose quadtree. Proxies that jitter within their
+    // enlarged bounds don't have to be moved in the tree, at the cost of finding more
+    // candidate pairs that have to be filtered out.
+    pub fn loose(looseness: f32) -> Self {
+        let mut broad_phase = Self::new();
+        broad_phase.looseness = looseness;
+        broad_phase.loosen = Some(|bounds: Bounds, looseness| {
+            let dx = (bounds.max.x - bounds.min.x) * looseness;
+            let dy = (bounds.max.y - bounds.min.y) * looseness;
+            Bounds {
+                min: Point {
+                    x: bounds.min.x - dx,
+                    y: bounds.min.y - dy,
+                },
+                max: Point {
+                    x: bounds.max.x + dx,
+                    y: bounds.max.y + dy,
+                },
+            }
+        });
+        broad_phase
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::strip;
+
+    #[test]
+    fn test_pair_events() {
+        let mut broad_phase = BroadPhase::new();
+        let mut events = Vec::new();
+        let a = broad_phase.create_proxy('a', strip(0.0, 10.0));
+        let b = broad_phase.create_proxy('b', strip(5.0, 10.0));
+        let c = broad_phase.create_proxy('c', strip(30.0, 10.0));
+        broad_phase.update(&mut events);
+        assert_eq!(events, [PairEvent::Added('a', 'b')]);
+
+        // Pairs are only reported once, even when both proxies move.
+        events.clear();
+        broad_phase.set_bounds(b, strip(25.0, 10.0));
+        broad_phase.set_bounds(c, strip(28.0, 10.0));
+        broad_phase.update(&mut events);
+        assert_eq!(
+            events,
+            [PairEvent::Removed('b', 'a'), PairEvent::Added('b', 'c')]
+        );
+
+        events.clear();
+        broad_phase.update(&mut events);
+        assert!(events.is_empty());
+
+        assert_eq!(broad_phase.remove_proxy(c), Some('c'));
+        assert_eq!(broad_phase.remove_proxy(c), None);
+        broad_phase.update(&mut events);
+        assert_eq!(events, [PairEvent::Removed('c', 'b')]);
+        assert_eq!(broad_phase.bounds(a), Some(strip(0.0, 10.0)));
+    }
+
+    #[test]
+    fn test_stale_handles() {
+        let mut broad_phase = BroadPhase::new();
+        let mut events = Vec::new();
+        let a = broad_phase.create_proxy('a', strip(0.0, 10.0));
+        broad_phase.remove_proxy(a);
+
+        // The new proxy reuses the slot of the removed one, but the old handle stays invalid.
+        let b = broad_phase.create_proxy('b', strip(5.0, 10.0));
+        assert_ne!(a, b);
+        assert_eq!(broad_phase.bounds(a), None);
+        assert_eq!(broad_phase.remove_proxy(a), None);
+        assert_eq!(
+            broad_phase.try_set_bounds(a, strip(0.0, 10.0)),
+            Err(Error::StaleHandle {
+                index: 0,
+                generation: 0
+            })
+        );
+        assert_eq!(broad_phase.bounds(b), Some(strip(5.0, 10.0)));
+
+        broad_phase.create_proxy('c', strip(8.0, 10.0));
+        broad_phase.update(&mut events);
+        assert_eq!(events, [PairEvent::Added('b', 'c')]);
+    }
+
+    #[test]
+    fn test_loose() {
+        let mut broad_phase = BroadPhase::loose(0.2);
+        let mut events = Vec::new();
+        let a = broad_phase.create_proxy('a', strip(0.0, 10.0));
+        broad_phase.create_proxy('b', strip(11.0, 10.0));
+        broad_phase.update(&mut events);
+        // The enlarged bounds overlap, but the proxies don't.
+        assert!(events.is_empty());
+
+        // Moving within the enlarged bounds leaves the tree untouched.
+        let tree = broad_phase.tree.clone();
+        broad_phase.set_bounds(a, strip(1.5, 10.0));
+        broad_phase.update(&mut events);
+        assert!(broad_phase.tree.ptr_eq(&tree));
+        assert_eq!(events, [PairEvent::Added('a', 'b')]);
+
+        events.clear();
+        broad_phase.set_bounds(a, strip(-5.0, 10.0));
+        broad_phase.update(&mut events);
+        assert!(!broad_phase.tree.ptr_eq(&tree));
+        assert_eq!(events, [PairEvent::Removed('a', 'b')]);
+    }
+
+    #[test]
+    fn test_invalid_bounds() {
+        let mut broad_phase = BroadPhase::new();
+        let mut events = Vec::new();
+        let nan = Bounds::from_xywh(f32::NAN, 0.0, 10.0, 10.0);
+        assert!(matches!(
+            broad_phase.try_create_proxy('a', nan),
+            Err(Error::InvalidBounds { .. })
+        ));
+
+        // A rejected move leaves the proxy where it was, so it can still be removed.
+        let b = broad_phase.create_proxy('b', strip(0.0, 10.0));
+        assert!(matches!(
+            broad_phase.try_set_bounds(b, nan),
+            Err(Error::InvalidBounds { .. })
+        ));
+        assert_eq!(broad_phase.bounds(b), Some(strip(0.0, 10.0)));
+        broad_phase.create_proxy('c', strip(5.0, 10.0));
+        broad_phase.update(&mut events);
+        assert_eq!(events, [PairEvent::Added('b', 'c')]);
+        assert_eq!(broad_phase.remove_proxy(b), Some('b'));
+    }
+}
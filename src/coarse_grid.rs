@@ -0,0 +1,127 @@
+use crate::{Bounds, BoundsTree, Point, PrimitiveHandle, SpatialIndex};
+use std::collections::HashMap;
+
+// A two-level index for very large worlds, which splits space into coarse square cells that
+// each hold a small `BoundsTree`. Inserting and removing only touches the tree of one cell,
+// and since the trees are independent, they can be processed in parallel, see `cells_mut`.
+//
+// Items are assigned to the cell containing their center, so a cell's tree may extend past
+// the cell. Queries visit every cell whose tree intersects the query, so cells should be
+// much larger than the items.
+#[derive(Debug, Clone)]
+pub struct CoarseGrid<T> {
+    cell_size: f32,
+    cells: HashMap<(i32, i32), BoundsTree<T>>,
+}
+
+// Identifies an item of a `CoarseGrid` by its cell and its handle within that cell's tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CoarseGridHandle {
+    cell: (i32, i32),
+    handle: PrimitiveHandle,
+}
+
+impl<T: Clone> CoarseGrid<T> {
+    pub fn new(cell_size: f32) -> Self {
+        CoarseGrid {
+            cell_size,
+            cells: HashMap::new(),
+        }
+    }
+
+    pub fn cell_size(&self) -> f32 {
+        self.cell_size
+    }
+
+    // The cells that items were inserted into along with their trees, in no particular order.
+    pub fn cells(&self) -> impl Iterator<Item = ((i32, i32), &BoundsTree<T>)> {
+        self.cells.iter().map(|(cell, tree)| (*cell, tree))
+    }
+
+    // Like `cells`, but the trees can be modified independently, e.g. on separate threads.
+    pub fn cells_mut(&mut self) -> impl Iterator<Item = ((i32, i32), &mut BoundsTree<T>)> {
+        self.cells.iter_mut().map(|(cell, tree)| (*cell, tree))
+    }
+
+    fn cell(&self, bounds: Bounds) -> (i32, i32) {
+        (
+            ((bounds.min.x + bounds.max.x) / 2.0 / self.cell_size).floor() as i32,
+            ((bounds.min.y + bounds.max.y) / 2.0 / self.cell_size).floor() as i32,
+        )
+    }
+}
+
+impl<T: Clone> SpatialIndex<T> for CoarseGrid<T> {
+    type Handle = CoarseGridHandle;
+
+    fn insert(&mut self, bounds: Bounds, data: T) -> CoarseGridHandle {
+        let cell = self.cell(bounds);
+        let tree = self.cells.entry(cell).or_default();
+        CoarseGridHandle {
+            cell,
+            handle: tree.insert_with_handle(bounds, data).0,
+        }
+    }
+
+    fn remove(&mut self, handle: CoarseGridHandle) -> Option<T> {
+        let tree = self.cells.get_mut(&handle.cell)?;
+        let data = tree.remove(handle.handle)?;
+        if tree.is_empty() {
+            self.cells.remove(&handle.cell);
+        }
+        Some(data)
+    }
+
+    fn query<'a>(&'a self, bounds: Bounds) -> impl Iterator<Item = (Bounds, &'a T)>
+    where
+        T: 'a,
+    {
+        self.cells
+            .values()
+            .filter(move |tree| {
+                tree.bounds()
+                    .is_some_and(|tree_bounds| tree_bounds.intersects(bounds))
+            })
+            .flat_map(move |tree| tree.query(bounds))
+    }
+
+    // Visits cells from the closest to the farthest tree, until the remaining trees are
+    // farther away than the nearest item found so far.
+    fn nearest(&self, point: Point) -> Option<(Bounds, &T)> {
+        let mut trees = self
+            .cells
+            .values()
+            .filter_map(|tree| Some((tree.bounds()?.distance_squared_to_point(point), tree)))
+            .collect::<Vec<_>>();
+        trees.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut nearest: Option<(Bounds, &T)> = None;
+        for (tree_distance, tree) in trees {
+            if nearest
+                .is_some_and(|(bounds, _)| bounds.distance_squared_to_point(point) <= tree_distance)
+            {
+                break;
+            }
+            if let Some((bounds, data)) = tree.nearest(point) {
+                if nearest.is_none_or(|(nearest_bounds, _)| {
+                    bounds.distance_squared_to_point(point)
+                        < nearest_bounds.distance_squared_to_point(point)
+                }) {
+                    nearest = Some((bounds, data));
+                }
+            }
+        }
+        nearest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spatial_index::tests::check_spatial_index;
+
+    #[test]
+    fn test_coarse_grid() {
+        check_spatial_index(CoarseGrid::new(50.0));
+    }
+}
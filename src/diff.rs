@@ -0,0 +1,255 @@
+use crate::{augmented_tree::Node, BoundingBox, Bounds, BoundsTree, Primitive};
+use std::{collections::HashMap, hash::Hash};
+
+// The changes between two trees, with leaves identified by their data.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TreeDiff<'a, T, B = Bounds> {
+    pub added: Vec<(B, &'a T)>,
+    pub removed: Vec<(B, &'a T)>,
+    pub moved: Vec<MovedLeaf<'a, T, B>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MovedLeaf<'a, T, B = Bounds> {
+    pub data: &'a T,
+    pub from: B,
+    pub to: B,
+}
+
+impl<T, B> TreeDiff<'_, T, B> {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.moved.is_empty()
+    }
+}
+
+impl<T: Clone + Eq + Hash, B: BoundingBox> BoundsTree<T, B> {
+    // Reports the leaves that were added, removed, or moved to get from this tree to `other`.
+    // A leaf counts as moved when its data is present in both trees with different bounds.
+    // When several leaves share the same data, leaves with identical bounds are matched up
+    // first, and the remaining ones are paired up as moves.
+    pub fn diff<'a>(&'a self, other: &'a Self) -> TreeDiff<'a, T, B> {
+        let mut old = HashMap::<&T, Vec<B>>::new();
+        for (bounds, data) in self.tree.iter() {
+            old.entry(data).or_default().push(bounds);
+        }
+
+        let mut unmatched = Vec::new();
+        for (bounds, data) in other.tree.iter() {
+            let unchanged = old.get_mut(data).and_then(|old_bounds| {
+                let ix = old_bounds.iter().position(|old| *old == bounds)?;
+                Some(old_bounds.swap_remove(ix))
+            });
+            if unchanged.is_none() {
+                unmatched.push((bounds, data));
+            }
+        }
+
+        let mut diff = TreeDiff {
+            added: Vec::new(),
+            removed: Vec::new(),
+            moved: Vec::new(),
+        };
+        for (bounds, data) in unmatched {
+            match old.get_mut(data).and_then(|old_bounds| old_bounds.pop()) {
+                Some(from) => diff.moved.push(MovedLeaf {
+                    data,
+                    from,
+                    to: bounds,
+                }),
+                None => diff.added.push((bounds, data)),
+            }
+        }
+        // Walk this tree again rather than draining the map, so the output order is stable.
+        for (bounds, data) in self.tree.iter() {
+            if let Some(old_bounds) = old.get_mut(data) {
+                if let Some(ix) = old_bounds.iter().position(|old| *old == bounds) {
+                    old_bounds.swap_remove(ix);
+                    diff.removed.push((bounds, data));
+                }
+            }
+        }
+        diff
+    }
+}
+
+impl<T: PartialEq, B: BoundingBox> BoundsTree<T, B> {
+    // Returns the regions that need to be repainted to get from the previous frame to this one,
+    // with primitives matched up across frames by `key`, which should be unique within a frame.
+    // Primitives that were added, removed, moved, reordered, or whose data changed damage
+    // their bounds in both frames. Overlapping regions are merged, so no pixel is repainted
+    // twice.
+    pub fn damage<K: Eq + Hash>(&self, previous: &Self, mut key: impl FnMut(&T) -> K) -> Vec<B> {
+        let mut old = HashMap::new();
+        for primitive in previous.primitives() {
+            old.insert(key(primitive.data), primitive);
+        }
+
+        let mut damage = Vec::new();
+        for primitive in self.primitives() {
+            match old.remove(&key(primitive.data)) {
+                Some(old)
+                    if old.bounds == primitive.bounds
+                        && old.order == primitive.order
+                        && old.data == primitive.data => {}
+                Some(old) => {
+                    damage.push(old.bounds);
+                    damage.push(primitive.bounds);
+                }
+                None => damage.push(primitive.bounds),
+            }
+        }
+        // Walk the previous frame again rather than draining the map, so the output is stable.
+        for primitive in previous.primitives() {
+            if old.remove(&key(primitive.data)).is_some() {
+                damage.push(primitive.bounds);
+            }
+        }
+
+        // Regions are kept in a tree of their own, so finding the ones a new region overlaps
+        // doesn't mean checking all of them. Leaves store their index into `handles`, which
+        // is how absorbed regions are removed.
+        let mut regions = BoundsTree::<usize, B>::new();
+        let mut handles = Vec::new();
+        for mut bounds in damage {
+            // Growing a region can make it overlap regions it was disjoint from before, so keep
+            // absorbing them until it's disjoint from all of them.
+            loop {
+                let overlapping = regions
+                    .iter_intersecting(bounds)
+                    .map(|region| (*region.data, region.bounds))
+                    .collect::<Vec<_>>();
+                if overlapping.is_empty() {
+                    break;
+                }
+                for (region, region_bounds) in overlapping {
+                    regions.remove(handles[region]);
+                    bounds = bounds.merge(region_bounds);
+                }
+            }
+            let (handle, _) = regions.insert_with_handle(bounds, handles.len());
+            handles.push(handle);
+        }
+        regions.iter().map(|region| region.bounds).collect()
+    }
+
+    // Yields every primitive in no particular order. Unlike `iter`, this doesn't require the
+    // data to be `Clone`.
+    fn primitives(&self) -> impl Iterator<Item = Primitive<&T, B>> {
+        self.tree.nodes.iter().filter_map(|node| match node {
+            Node::Leaf {
+                bounds,
+                data,
+                summary: order,
+            } => Some(Primitive {
+                data,
+                bounds: *bounds,
+                order: *order,
+            }),
+            Node::Internal { .. } => None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Point;
+
+    #[test]
+    fn test_diff() {
+        let bounds = |x: i32| Bounds {
+            min: Point { x, y: 0 },
+            max: Point { x: x + 10, y: 10 },
+        };
+        let mut a = BoundsTree::new();
+        let mut b = BoundsTree::new();
+        for (x, data) in [(0, 'a'), (20, 'b'), (40, 'c'), (60, 'd'), (80, 'd')] {
+            a.insert(bounds(x), data);
+        }
+        for (x, data) in [(0, 'a'), (25, 'b'), (60, 'd'), (85, 'd'), (100, 'e')] {
+            b.insert(bounds(x), data);
+        }
+        assert!(a.diff(&a).is_empty());
+
+        let diff = a.diff(&b);
+        assert_eq!(diff.added, [(bounds(100), &'e')]);
+        assert_eq!(diff.removed, [(bounds(40), &'c')]);
+        let mut moved = diff
+            .moved
+            .iter()
+            .map(|moved| (*moved.data, moved.from, moved.to))
+            .collect::<Vec<_>>();
+        moved.sort_by_key(|(data, ..)| *data);
+        assert_eq!(
+            moved,
+            [('b', bounds(20), bounds(25)), ('d', bounds(80), bounds(85))]
+        );
+
+        let reverse = b.diff(&a);
+        assert_eq!(reverse.added, diff.removed);
+        assert_eq!(reverse.removed, diff.added);
+        assert_eq!(reverse.moved.len(), 2);
+    }
+
+    #[test]
+    fn test_damage() {
+        let bounds = |x: i32, width: i32| Bounds {
+            min: Point { x, y: 0 },
+            max: Point {
+                x: x + width,
+                y: 10,
+            },
+        };
+        let mut previous = BoundsTree::new();
+        for (x, data) in [
+            (0, ('a', 0)),
+            (20, ('b', 0)),
+            (40, ('c', 0)),
+            (60, ('d', 0)),
+        ] {
+            previous.insert(bounds(x, 10), data);
+        }
+        let mut current = BoundsTree::new();
+        for (x, data) in [
+            (0, ('a', 0)),
+            (25, ('b', 0)),
+            (60, ('d', 1)),
+            (100, ('e', 0)),
+        ] {
+            current.insert(bounds(x, 10), data);
+        }
+        assert!(current.damage(&current, |(id, _)| *id).is_empty());
+
+        let mut damage = current.damage(&previous, |(id, _)| *id);
+        damage.sort_by_key(|bounds| bounds.min.x);
+        assert_eq!(
+            damage,
+            [
+                bounds(20, 15),
+                bounds(40, 10),
+                bounds(60, 10),
+                bounds(100, 10)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_damage_bridging_regions() {
+        let bounds = |x: i32, width: i32| Bounds {
+            min: Point { x, y: 0 },
+            max: Point {
+                x: x + width,
+                y: 10,
+            },
+        };
+        // Data that's only `PartialEq`, like opacities, is fine, since it's matched by key.
+        let previous = BoundsTree::new();
+        let mut current = BoundsTree::new();
+        for (x, width, id) in [(0, 10, 0), (20, 10, 1), (50, 10, 2), (5, 20, 3)] {
+            current.insert(bounds(x, width), (id, 0.5));
+        }
+        let mut damage = current.damage(&previous, |(id, _)| *id);
+        damage.sort_by_key(|bounds| bounds.min.x);
+        assert_eq!(damage, [bounds(0, 30), bounds(50, 10)]);
+    }
+}
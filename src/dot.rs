@@ -0,0 +1,109 @@
+use crate::{bounds_tree::Node, BoundingBox, BoundsTree};
+use std::fmt::Write;
+
+impl<T: Clone, B: BoundingBox> BoundsTree<T, B> {
+    // Renders the tree as a Graphviz DOT graph, with every node labeled by its depth, bounds,
+    // and order (or maximum order within its subtree). Leaves are drawn as boxes.
+    pub fn to_dot(&self) -> String {
+        let mut dot = DotWriter::new();
+        if let Some(root) = self.tree.root {
+            let mut stack = vec![(root, 0, None)];
+            while let Some((index, depth, parent)) = stack.pop() {
+                let node = self.node(index);
+                let id = dot.node(
+                    depth,
+                    node.bounds(),
+                    node.summary(),
+                    matches!(node, Node::Leaf { .. }),
+                    parent,
+                );
+                if let Node::Internal { left, right, .. } = node {
+                    stack.push((*right, depth + 1, Some(id)));
+                    stack.push((*left, depth + 1, Some(id)));
+                }
+            }
+        }
+        dot.finish()
+    }
+}
+
+// Builds DOT output shared by the different tree types, numbering nodes in the order they're
+// added.
+pub(crate) struct DotWriter {
+    output: String,
+    next_id: usize,
+}
+
+impl DotWriter {
+    pub(crate) fn new() -> Self {
+        DotWriter {
+            output: String::from("digraph {\n"),
+            next_id: 0,
+        }
+    }
+
+    // Adds a node, connecting it to its parent if any, and returns its id.
+    pub(crate) fn node(
+        &mut self,
+        depth: usize,
+        bounds: impl std::fmt::Debug,
+        order: u32,
+        is_leaf: bool,
+        parent: Option<usize>,
+    ) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        let (shape, order_label) = if is_leaf {
+            ("box", "order")
+        } else {
+            ("ellipse", "max order")
+        };
+        let label = format!("depth {depth}\n{bounds:?}\n{order_label} {order}");
+        writeln!(
+            self.output,
+            "    n{id} [shape={shape}, label=\"{}\"];",
+            label
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n")
+        )
+        .unwrap();
+        if let Some(parent) = parent {
+            writeln!(self.output, "    n{parent} -> n{id};").unwrap();
+        }
+        id
+    }
+
+    pub(crate) fn finish(mut self) -> String {
+        self.output.push_str("}\n");
+        self.output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Bounds, Point};
+
+    #[test]
+    fn test_to_dot() {
+        let mut tree = BoundsTree::new();
+        assert_eq!(tree.to_dot(), "digraph {\n}\n");
+
+        for x in [0.0, 5.0, 20.0] {
+            let bounds = Bounds {
+                min: Point { x, y: 0.0 },
+                max: Point {
+                    x: x + 10.0,
+                    y: 10.0,
+                },
+            };
+            tree.insert(bounds, ());
+        }
+        let dot = tree.to_dot();
+        assert!(dot.starts_with("digraph {\n    n0 [shape=ellipse, label=\"depth 0\\n"));
+        assert_eq!(dot.matches("shape=box").count(), 3);
+        assert_eq!(dot.matches(" -> ").count(), 4);
+        assert!(dot.contains("max order 2"));
+    }
+}
@@ -0,0 +1,139 @@
+use crate::{BoundingBox, Bounds, BoundsTree, Error, PrimitiveHandle};
+use std::{collections::HashMap, hash::Hash};
+
+// A tree keyed by external ids, such as ECS entities, which takes care of the bookkeeping
+// between those ids and the tree's contents. Leaves store the entity itself, so queries map
+// straight back to entities, while a map from entity to handle locates the leaf to move
+// whenever an entity moves.
+pub struct EntityTree<E, B = Bounds> {
+    tree: BoundsTree<E, B>,
+    entities: HashMap<E, (PrimitiveHandle, B)>,
+}
+
+impl<E: Copy + Eq + Hash, B: BoundingBox> Default for EntityTree<E, B> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E: Copy + Eq + Hash, B: BoundingBox> EntityTree<E, B> {
+    pub fn new() -> Self {
+        EntityTree {
+            tree: BoundsTree::new(),
+            entities: HashMap::new(),
+        }
+    }
+
+    // Inserts the entity, or moves it if it's already present. Returns its order, or an error
+    // if the bounds are invalid, in which case the entity is left untouched.
+    pub fn insert_or_update(&mut self, entity: E, bounds: B) -> Result<u32, Error> {
+        if !bounds.is_valid() {
+            return Err(Error::InvalidBounds {
+                bounds: format!("{bounds:?}"),
+            });
+        }
+        if let Some((handle, old_bounds)) = self.entities.get_mut(&entity) {
+            if let Some(order) = self.tree.update(*handle, bounds) {
+                *old_bounds = bounds;
+                return Ok(order);
+            }
+        }
+        let (handle, order) = self.tree.insert_with_handle(bounds, entity);
+        self.entities.insert(entity, (handle, bounds));
+        Ok(order)
+    }
+
+    // Removes the entity, returning its bounds if it was present.
+    pub fn remove(&mut self, entity: E) -> Option<B> {
+        let (handle, bounds) = self.entities.remove(&entity)?;
+        self.tree.remove(handle);
+        Some(bounds)
+    }
+
+    pub fn bounds(&self, entity: E) -> Option<B> {
+        Some(self.entities.get(&entity)?.1)
+    }
+
+    pub fn contains(&self, entity: E) -> bool {
+        self.entities.contains_key(&entity)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    // Yields every entity intersecting `bounds`.
+    pub fn query(&self, bounds: B) -> impl Iterator<Item = E> + '_ {
+        self.tree
+            .iter_intersecting(bounds)
+            .map(|primitive| *primitive.data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::strip;
+
+    // A generational entity id, as handed out by most ECS crates.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+    struct Entity {
+        index: u32,
+        generation: u32,
+    }
+
+    #[test]
+    fn test_insert_or_update() {
+        let a = Entity {
+            index: 0,
+            generation: 0,
+        };
+        let b = Entity {
+            index: 1,
+            generation: 0,
+        };
+        let reused = Entity {
+            index: 0,
+            generation: 1,
+        };
+
+        let mut tree = EntityTree::new();
+        assert_eq!(tree.insert_or_update(a, strip(0.0, 10.0)), Ok(1));
+        assert_eq!(tree.insert_or_update(b, strip(5.0, 10.0)), Ok(2));
+        assert_eq!(tree.query(strip(2.0, 10.0)).count(), 2);
+
+        // Moving an entity replaces its old bounds rather than adding a second leaf.
+        tree.insert_or_update(b, strip(50.0, 10.0)).unwrap();
+        assert_eq!(tree.len(), 2);
+        assert_eq!(tree.query(strip(2.0, 10.0)).collect::<Vec<_>>(), [a]);
+        assert_eq!(tree.bounds(b), Some(strip(50.0, 10.0)));
+
+        assert_eq!(tree.remove(a), Some(strip(0.0, 10.0)));
+        assert_eq!(tree.remove(a), None);
+        tree.insert_or_update(reused, strip(0.0, 10.0)).unwrap();
+        assert!(!tree.contains(a));
+        assert_eq!(tree.query(strip(2.0, 10.0)).collect::<Vec<_>>(), [reused]);
+    }
+
+    #[test]
+    fn test_invalid_bounds() {
+        let mut tree = EntityTree::new();
+        let nan = Bounds::from_xywh(f32::NAN, 0.0, 10.0, 10.0);
+        assert!(matches!(
+            tree.insert_or_update(1, nan),
+            Err(Error::InvalidBounds { .. })
+        ));
+        assert!(tree.is_empty());
+
+        // Rejected moves keep the entity's previous bounds.
+        tree.insert_or_update(2, strip(0.0, 10.0)).unwrap();
+        assert!(tree.insert_or_update(2, nan).is_err());
+        assert_eq!(tree.bounds(2), Some(strip(0.0, 10.0)));
+        assert_eq!(tree.remove(2), Some(strip(0.0, 10.0)));
+        assert!(tree.is_empty());
+    }
+}
@@ -0,0 +1,64 @@
+use crate::ValidationError;
+use std::{error, fmt};
+
+// The errors returned by the crate's fallible APIs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    // Bounds with NaN coordinates or a minimum above the maximum, formatted with `Debug`.
+    InvalidBounds {
+        bounds: String,
+    },
+    // A handle whose slot was freed, and possibly reused, since the handle was created.
+    StaleHandle {
+        index: usize,
+        generation: u32,
+    },
+    CapacityExceeded {
+        limit: usize,
+        requested: usize,
+    },
+    // Encoded data that doesn't match what the decoder expects, e.g. a different format
+    // version.
+    DeserializationMismatch {
+        field: &'static str,
+        expected: String,
+        found: String,
+    },
+    InvalidTree(ValidationError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidBounds { bounds } => write!(f, "invalid bounds {bounds}"),
+            Error::StaleHandle { index, generation } => write!(
+                f,
+                "handle to slot {index} with generation {generation} is stale"
+            ),
+            Error::CapacityExceeded { limit, requested } => {
+                write!(f, "capacity of {limit} exceeded by request for {requested}")
+            }
+            Error::DeserializationMismatch {
+                field,
+                expected,
+                found,
+            } => write!(f, "expected {field} {expected}, found {found}"),
+            Error::InvalidTree(error) => write!(f, "invalid tree: {error}"),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::InvalidTree(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<ValidationError> for Error {
+    fn from(error: ValidationError) -> Self {
+        Error::InvalidTree(error)
+    }
+}
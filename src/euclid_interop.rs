@@ -0,0 +1,82 @@
+use crate::{Bounds, Bounds3, Point, Point3};
+use euclid::{Box2D, Box3D, Point2D, Point3D};
+
+// Conversions are generic over euclid's unit tag, so tagged geometry such as
+// `Box2D<f32, LayoutPixel>` converts without first being cast to `UnknownUnit`.
+impl<S, U> From<Point2D<S, U>> for Point<S> {
+    fn from(point: Point2D<S, U>) -> Self {
+        Point {
+            x: point.x,
+            y: point.y,
+        }
+    }
+}
+
+impl<S, U> From<Point<S>> for Point2D<S, U> {
+    fn from(point: Point<S>) -> Self {
+        Point2D::new(point.x, point.y)
+    }
+}
+
+impl<S, U> From<Point3D<S, U>> for Point3<S> {
+    fn from(point: Point3D<S, U>) -> Self {
+        Point3 {
+            x: point.x,
+            y: point.y,
+            z: point.z,
+        }
+    }
+}
+
+impl<S, U> From<Point3<S>> for Point3D<S, U> {
+    fn from(point: Point3<S>) -> Self {
+        Point3D::new(point.x, point.y, point.z)
+    }
+}
+
+impl<S, U> From<Box2D<S, U>> for Bounds<S> {
+    fn from(bounds: Box2D<S, U>) -> Self {
+        Bounds {
+            min: bounds.min.into(),
+            max: bounds.max.into(),
+        }
+    }
+}
+
+impl<S, U> From<Bounds<S>> for Box2D<S, U> {
+    fn from(bounds: Bounds<S>) -> Self {
+        Box2D::new(bounds.min.into(), bounds.max.into())
+    }
+}
+
+impl<S, U> From<Box3D<S, U>> for Bounds3<S> {
+    fn from(bounds: Box3D<S, U>) -> Self {
+        Bounds3 {
+            min: bounds.min.into(),
+            max: bounds.max.into(),
+        }
+    }
+}
+
+impl<S, U> From<Bounds3<S>> for Box3D<S, U> {
+    fn from(bounds: Bounds3<S>) -> Self {
+        Box3D::new(bounds.min.into(), bounds.max.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct LayoutPixel;
+
+    #[test]
+    fn test_euclid_round_trip() {
+        let layout_box =
+            Box2D::<f32, LayoutPixel>::new(Point2D::new(1.0, 2.0), Point2D::new(3.0, 4.0));
+        let bounds = Bounds::from(layout_box);
+        assert_eq!(bounds.min, Point { x: 1.0, y: 2.0 });
+        assert_eq!(bounds.max, Point { x: 3.0, y: 4.0 });
+        assert_eq!(Box2D::<f32, LayoutPixel>::from(bounds), layout_box);
+    }
+}
@@ -0,0 +1,358 @@
+use crate::{bounds_tree::Node, Bounds, BoundsTree, Point};
+use std::iter;
+
+// A node of a flattened `BoundsTree`, laid out so a whole buffer of them can be uploaded to
+// a GPU and traversed in a shader. With the `bytemuck` feature enabled, a `&[FlatNode]` can
+// be cast to bytes with `bytemuck::cast_slice`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "bytemuck", derive(bytemuck::Pod, bytemuck::Zeroable))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(as = "Self")
+)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FlatNode {
+    pub min: [f32; 2],
+    pub max: [f32; 2],
+    // The buffer indices of the node's children, or `FlatNode::NONE` for leaves.
+    pub left: u32,
+    pub right: u32,
+    // The index of the leaf's data in the payload list, or `FlatNode::NONE` for internal nodes.
+    pub payload: u32,
+    // The order of a leaf, or the maximum order within the subtree of an internal node.
+    pub order: u32,
+}
+
+impl FlatNode {
+    pub const NONE: u32 = u32::MAX;
+
+    pub fn is_leaf(&self) -> bool {
+        self.payload != Self::NONE
+    }
+}
+
+// A node of a flattened `BoundsTree` that can be traversed without a stack. Nodes are laid
+// out depth-first so the first child of an internal node always directly follows it, and
+// every node stores the index to continue at once its subtree has been visited or skipped.
+// With the `rkyv` feature enabled, nodes are their own archived representation, so an archived
+// buffer can be memory-mapped and passed straight to `StacklessNode::query`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "bytemuck", derive(bytemuck::Pod, bytemuck::Zeroable))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(as = "Self")
+)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StacklessNode {
+    pub min: [f32; 2],
+    pub max: [f32; 2],
+    // The index following this node's subtree, which equals the buffer length for the last
+    // subtree in the buffer.
+    pub escape: u32,
+    // The index of the leaf's data in the payload list, or `FlatNode::NONE` for internal nodes.
+    pub payload: u32,
+    // The order of a leaf, or the maximum order within the subtree of an internal node.
+    pub order: u32,
+}
+
+impl StacklessNode {
+    pub fn is_leaf(&self) -> bool {
+        self.payload != FlatNode::NONE
+    }
+
+    fn bounds(&self) -> Bounds {
+        Bounds {
+            min: Point {
+                x: self.min[0],
+                y: self.min[1],
+            },
+            max: Point {
+                x: self.max[0],
+                y: self.max[1],
+            },
+        }
+    }
+
+    // Reference traversal of a stackless buffer, yielding the leaves intersecting `bounds`.
+    // Shaders can mirror this loop directly.
+    pub fn query(nodes: &[StacklessNode], bounds: Bounds) -> impl Iterator<Item = &StacklessNode> {
+        let mut index = 0;
+        iter::from_fn(move || {
+            while let Some(node) = nodes.get(index) {
+                if !node.bounds().intersects(bounds) {
+                    index = node.escape as usize;
+                } else {
+                    index += 1;
+                    if node.is_leaf() {
+                        return Some(node);
+                    }
+                }
+            }
+            None
+        })
+    }
+}
+
+impl<T: Clone> BoundsTree<T> {
+    // Lays the tree out depth-first, starting with the root at index 0, and returns the
+    // nodes along with the data of every leaf, indexed by `FlatNode::payload`.
+    pub fn flatten(&self) -> (Vec<FlatNode>, Vec<&T>) {
+        let mut nodes = Vec::with_capacity(self.tree.nodes.len());
+        let mut payloads = Vec::with_capacity(self.tree.nodes.len().div_ceil(2));
+        if let Some(root) = self.tree.root {
+            self.flatten_node(root, &mut nodes, &mut payloads);
+        }
+        (nodes, payloads)
+    }
+
+    // Lays the tree out for stackless traversal, see `StacklessNode`. Returns the nodes along
+    // with the data of every leaf, indexed by `StacklessNode::payload`.
+    pub fn flatten_stackless(&self) -> (Vec<StacklessNode>, Vec<&T>) {
+        let mut nodes = Vec::with_capacity(self.tree.nodes.len());
+        let mut payloads = Vec::with_capacity(self.tree.nodes.len().div_ceil(2));
+        if let Some(root) = self.tree.root {
+            self.flatten_stackless_node(root, &mut nodes, &mut payloads);
+        }
+        (nodes, payloads)
+    }
+
+    fn flatten_stackless_node<'a>(
+        &'a self,
+        root: usize,
+        nodes: &mut Vec<StacklessNode>,
+        payloads: &mut Vec<&'a T>,
+    ) {
+        // Degenerate trees can be as deep as they're long, so this walks an explicit stack.
+        // A `Close` entry is popped once a node's subtree has been laid out and patches up
+        // its escape index.
+        enum Step {
+            Visit(usize),
+            Close(usize),
+        }
+
+        let mut stack = vec![Step::Visit(root)];
+        while let Some(step) = stack.pop() {
+            let index = match step {
+                Step::Visit(index) => index,
+                Step::Close(flat_index) => {
+                    nodes[flat_index].escape = nodes.len() as u32;
+                    continue;
+                }
+            };
+
+            let flat_index = nodes.len();
+            let node = &self.tree.nodes[index];
+            let bounds = node.bounds();
+            nodes.push(StacklessNode {
+                min: [bounds.min.x, bounds.min.y],
+                max: [bounds.max.x, bounds.max.y],
+                escape: 0,
+                payload: FlatNode::NONE,
+                order: node.summary(),
+            });
+
+            match node {
+                Node::Leaf { data, .. } => {
+                    nodes[flat_index].payload = payloads.len() as u32;
+                    nodes[flat_index].escape = nodes.len() as u32;
+                    payloads.push(data);
+                }
+                Node::Internal { left, right, .. } => {
+                    stack.extend([
+                        Step::Close(flat_index),
+                        Step::Visit(*right),
+                        Step::Visit(*left),
+                    ]);
+                }
+            }
+        }
+    }
+
+    fn flatten_node<'a>(
+        &'a self,
+        root: usize,
+        nodes: &mut Vec<FlatNode>,
+        payloads: &mut Vec<&'a T>,
+    ) {
+        // Pairs of a node and the flat index of its parent, along with whether it's the left
+        // child, so the parent's child indices can be filled in once the child is laid out.
+        let mut stack: Vec<(usize, Option<(usize, bool)>)> = vec![(root, None)];
+        while let Some((index, parent)) = stack.pop() {
+            let flat_index = nodes.len();
+            let node = &self.tree.nodes[index];
+            let bounds = node.bounds();
+            nodes.push(FlatNode {
+                min: [bounds.min.x, bounds.min.y],
+                max: [bounds.max.x, bounds.max.y],
+                left: FlatNode::NONE,
+                right: FlatNode::NONE,
+                payload: FlatNode::NONE,
+                order: node.summary(),
+            });
+            match parent {
+                Some((parent, true)) => nodes[parent].left = flat_index as u32,
+                Some((parent, false)) => nodes[parent].right = flat_index as u32,
+                None => {}
+            }
+
+            match node {
+                Node::Leaf { data, .. } => {
+                    nodes[flat_index].payload = payloads.len() as u32;
+                    payloads.push(data);
+                }
+                Node::Internal { left, right, .. } => {
+                    stack.extend([
+                        (*right, Some((flat_index, false))),
+                        (*left, Some((flat_index, true))),
+                    ]);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::random_bounds;
+    use crate::{Bounds, Point};
+
+    #[test]
+    fn test_flatten() {
+        let mut tree = BoundsTree::new();
+        assert_eq!(tree.flatten(), (Vec::new(), Vec::new()));
+
+        for (ix, x) in [0.0, 5.0, 20.0, 22.0].into_iter().enumerate() {
+            tree.insert(
+                Bounds {
+                    min: Point { x, y: 0.0 },
+                    max: Point {
+                        x: x + 10.0,
+                        y: 10.0,
+                    },
+                },
+                ix,
+            );
+        }
+
+        let (nodes, payloads) = tree.flatten();
+        assert_eq!(nodes.len(), 7);
+        assert_eq!(payloads.len(), 4);
+        assert_eq!(nodes[0].order, 2);
+        assert_eq!((nodes[0].min, nodes[0].max), ([0.0, 0.0], [32.0, 10.0]));
+
+        // Every leaf is reachable from the root exactly once and carries its original order.
+        let mut stack = vec![0];
+        let mut leaves = Vec::new();
+        while let Some(index) = stack.pop() {
+            let node = nodes[index as usize];
+            if node.is_leaf() {
+                leaves.push((*payloads[node.payload as usize], node.order));
+            } else {
+                stack.extend([node.left, node.right]);
+            }
+        }
+        leaves.sort();
+        assert_eq!(leaves, [(0, 1), (1, 2), (2, 1), (3, 2)]);
+
+        #[cfg(feature = "bytemuck")]
+        assert_eq!(
+            bytemuck::cast_slice::<FlatNode, u8>(&nodes).len(),
+            nodes.len() * 32
+        );
+    }
+
+    #[cfg(feature = "rkyv")]
+    #[test]
+    fn test_query_archived_nodes() {
+        let mut tree = BoundsTree::new();
+        for (ix, x) in [0.0, 5.0, 20.0].into_iter().enumerate() {
+            tree.insert(
+                Bounds {
+                    min: Point { x, y: 0.0 },
+                    max: Point {
+                        x: x + 10.0,
+                        y: 10.0,
+                    },
+                },
+                ix as u32,
+            );
+        }
+        let (nodes, payloads) = tree.flatten_stackless();
+        let payloads = payloads.into_iter().copied().collect::<Vec<_>>();
+        let bytes = rkyv::to_bytes::<_, 256>(&(nodes, payloads)).unwrap();
+
+        // Safety: the bytes were produced by `rkyv::to_bytes` for this exact type.
+        let (nodes, payloads) =
+            unsafe { rkyv::archived_root::<(Vec<StacklessNode>, Vec<u32>)>(&bytes) };
+        let query = Bounds {
+            min: Point { x: 8.0, y: 0.0 },
+            max: Point { x: 9.0, y: 1.0 },
+        };
+        let mut hits = StacklessNode::query(nodes, query)
+            .map(|node| payloads[node.payload as usize])
+            .collect::<Vec<_>>();
+        hits.sort();
+        assert_eq!(hits, [0, 1]);
+    }
+
+    #[test]
+    fn test_flatten_degenerate_tree() {
+        // Coincident primitives make the tree about as deep as it's long, which used to
+        // overflow the stack when flattening recursively.
+        let mut tree = BoundsTree::new();
+        for id in 0..4_000 {
+            tree.insert(Bounds::from_xywh(0.0, 0.0, 1.0, 1.0), id);
+        }
+        std::thread::Builder::new()
+            .stack_size(256 * 1024)
+            .spawn(move || {
+                let (nodes, payloads) = tree.flatten_stackless();
+                assert_eq!(payloads.len(), 4_000);
+                assert_eq!(nodes[0].escape as usize, nodes.len());
+                assert_eq!(
+                    StacklessNode::query(&nodes, tree.bounds().unwrap()).count(),
+                    4_000
+                );
+
+                let (nodes, payloads) = tree.flatten();
+                assert_eq!((nodes.len(), payloads.len()), (7_999, 4_000));
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+    }
+
+    #[test]
+    fn test_stackless_query() {
+        use rand::SeedableRng;
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(1);
+        let mut tree = BoundsTree::new();
+        let mut all_bounds = Vec::new();
+        for id in 0..100 {
+            let bounds = random_bounds(&mut rng, 50.0);
+            tree.insert(bounds, id);
+            all_bounds.push(bounds);
+        }
+
+        let (nodes, payloads) = tree.flatten_stackless();
+        assert_eq!(nodes[0].escape as usize, nodes.len());
+        let query = Bounds {
+            min: Point { x: -20.0, y: -20.0 },
+            max: Point { x: 20.0, y: 20.0 },
+        };
+        let mut actual = StacklessNode::query(&nodes, query)
+            .map(|node| *payloads[node.payload as usize])
+            .collect::<Vec<_>>();
+        actual.sort();
+        let expected = (0..100)
+            .filter(|id| all_bounds[*id].intersects(query))
+            .collect::<Vec<_>>();
+        assert_eq!(actual, expected);
+    }
+}
@@ -0,0 +1,162 @@
+use crate::Error;
+use std::fmt;
+
+// A position in a sequence that can always be split, so a primitive can be inserted between
+// any two others without renumbering anything, e.g. when elements are reordered in an editor.
+// Use it as the layer of a `LayeredBoundsTree` to paint elements in the order of their indices.
+//
+// An index is a fraction in (0, 1) whose base-256 digits are compared lexicographically. The
+// last digit is never zero, which guarantees there's always room for another index below.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "Vec<u8>", into = "Vec<u8>"))]
+pub struct FractionalIndex(Vec<u8>);
+
+impl Default for FractionalIndex {
+    fn default() -> Self {
+        Self::between(None, None)
+    }
+}
+
+impl FractionalIndex {
+    // Returns an index strictly between `lower` and `upper`, where `None` stands for the
+    // start or end of the sequence. Panics if `lower` isn't below `upper`.
+    pub fn between(lower: Option<&Self>, upper: Option<&Self>) -> Self {
+        if let (Some(lower), Some(upper)) = (lower, upper) {
+            assert!(lower < upper, "{lower:?} must be below {upper:?}");
+        }
+        let lower = lower.map_or(&[][..], |lower| &lower.0);
+        // `upper` only constrains the digits of the new index while the two share a prefix.
+        // Without an upper bound, every digit can go up to 255.
+        let mut upper = upper.map(|upper| &upper.0[..]);
+
+        let mut digits = Vec::new();
+        for i in 0.. {
+            let low = lower.get(i).copied().unwrap_or(0) as u16;
+            let high = upper.map_or(256, |upper| upper.get(i).copied().unwrap_or(0) as u16);
+            if high - low > 1 {
+                digits.push(((low + high) / 2) as u8);
+                break;
+            }
+            digits.push(low as u8);
+            if high > low {
+                // The new index is now below `upper` no matter which digits follow.
+                upper = None;
+            }
+        }
+        FractionalIndex(digits)
+    }
+
+    pub fn before(&self) -> Self {
+        Self::between(None, Some(self))
+    }
+
+    pub fn after(&self) -> Self {
+        Self::between(Some(self), None)
+    }
+}
+
+// Digits that don't form a valid index, i.e. are empty or end in a zero, are rejected, since
+// `between` could then run out of room.
+impl TryFrom<Vec<u8>> for FractionalIndex {
+    type Error = Error;
+
+    fn try_from(digits: Vec<u8>) -> Result<Self, Error> {
+        match digits.last() {
+            Some(digit) if *digit != 0 => Ok(FractionalIndex(digits)),
+            _ => Err(Error::DeserializationMismatch {
+                field: "fractional index",
+                expected: "digits ending in a nonzero digit".into(),
+                found: format!("{digits:?}"),
+            }),
+        }
+    }
+}
+
+impl From<FractionalIndex> for Vec<u8> {
+    fn from(index: FractionalIndex) -> Self {
+        index.0
+    }
+}
+
+impl fmt::Debug for FractionalIndex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "FractionalIndex(")?;
+        for digit in &self.0 {
+            write!(f, "{digit:02x}")?;
+        }
+        write!(f, ")")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Bounds, LayeredBoundsTree, Point};
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn test_between() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(3);
+        let mut indices = vec![FractionalIndex::default()];
+        for _ in 0..2000 {
+            let ix = rng.gen_range(0..=indices.len());
+            let index =
+                FractionalIndex::between(ix.checked_sub(1).map(|ix| &indices[ix]), indices.get(ix));
+            indices.insert(ix, index);
+        }
+        assert!(indices.windows(2).all(|pair| pair[0] < pair[1]));
+
+        // Repeatedly inserting at the same spot grows indices by about a bit per insertion.
+        let first = FractionalIndex::default();
+        let mut last = first.after();
+        for _ in 0..100 {
+            last = FractionalIndex::between(Some(&first), Some(&last));
+        }
+        assert!(first < last && last.0.len() <= 15);
+    }
+
+    #[test]
+    fn test_reordering_layers() {
+        let bounds = Bounds {
+            min: Point { x: 0.0, y: 0.0 },
+            max: Point { x: 10.0, y: 10.0 },
+        };
+        let bottom = FractionalIndex::default();
+        let top = bottom.after();
+        let middle = FractionalIndex::between(Some(&bottom), Some(&top));
+
+        let mut tree = LayeredBoundsTree::new();
+        tree.insert(top, bounds, "top");
+        tree.insert(bottom, bounds, "bottom");
+        tree.insert(middle, bounds, "middle");
+        let painted = tree
+            .iter_in_order()
+            .map(|(_, primitive)| *primitive.data)
+            .collect::<Vec<_>>();
+        assert_eq!(painted, ["bottom", "middle", "top"]);
+    }
+
+    #[test]
+    fn test_try_from_digits() {
+        assert_eq!(
+            FractionalIndex::try_from(vec![0, 128]),
+            Ok(FractionalIndex(vec![0, 128]))
+        );
+        assert!(FractionalIndex::try_from(vec![]).is_err());
+        assert!(FractionalIndex::try_from(vec![128, 0]).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_deserialize_rejects_invalid_digits() {
+        let index = FractionalIndex::default().after();
+        let json = serde_json::to_string(&index).unwrap();
+        assert_eq!(
+            serde_json::from_str::<FractionalIndex>(&json).unwrap(),
+            index
+        );
+        assert!(serde_json::from_str::<FractionalIndex>("[]").is_err());
+        assert!(serde_json::from_str::<FractionalIndex>("[128,0]").is_err());
+    }
+}
@@ -0,0 +1,75 @@
+use crate::{Bounds, BoundsTree, Point, Scalar};
+use geo_types::{Coord, CoordNum, Rect};
+
+impl<S: CoordNum> From<Coord<S>> for Point<S> {
+    fn from(coord: Coord<S>) -> Self {
+        Point {
+            x: coord.x,
+            y: coord.y,
+        }
+    }
+}
+
+impl<S: CoordNum> From<Point<S>> for Coord<S> {
+    fn from(point: Point<S>) -> Self {
+        Coord {
+            x: point.x,
+            y: point.y,
+        }
+    }
+}
+
+impl<S: CoordNum> From<Rect<S>> for Bounds<S> {
+    fn from(rect: Rect<S>) -> Self {
+        Bounds {
+            min: rect.min().into(),
+            max: rect.max().into(),
+        }
+    }
+}
+
+impl<S: CoordNum> From<Bounds<S>> for Rect<S> {
+    fn from(bounds: Bounds<S>) -> Self {
+        Rect::new(Coord::from(bounds.min), Coord::from(bounds.max))
+    }
+}
+
+impl<T: Clone, S: Scalar + CoordNum> BoundsTree<T, Bounds<S>> {
+    // Builds a tree from the bounding rects of a collection of geometries, e.g. as computed
+    // by `geo::BoundingRect`. Geometries without a bounding rect (such as empty collections)
+    // should be filtered out beforehand.
+    pub fn from_rects(rects: impl IntoIterator<Item = (Rect<S>, T)>) -> Self {
+        let mut tree = BoundsTree::new();
+        for (rect, data) in rects {
+            tree.insert(rect.into(), data);
+        }
+        tree
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo_types::coord;
+
+    #[test]
+    fn test_from_rects() {
+        let rects = [
+            Rect::new(coord! { x: 0.0, y: 0.0 }, coord! { x: 10.0, y: 10.0 }),
+            // Rects normalize their corners, so this one spans (5, 5) to (15, 15).
+            Rect::new(coord! { x: 15.0, y: 5.0 }, coord! { x: 5.0, y: 15.0 }),
+            Rect::new(coord! { x: 20.0, y: 20.0 }, coord! { x: 30.0, y: 30.0 }),
+        ];
+        let tree = BoundsTree::from_rects(rects.iter().copied().zip(0..));
+        let mut orders = tree
+            .iter()
+            .map(|primitive| (*primitive.data, primitive.order))
+            .collect::<Vec<_>>();
+        orders.sort();
+        assert_eq!(orders, [(0, 1), (1, 2), (2, 1)]);
+
+        let bounds = Bounds::from(rects[1]);
+        assert_eq!(bounds.min, Point { x: 5.0, y: 5.0 });
+        assert_eq!(Rect::from(bounds), rects[1]);
+    }
+}
@@ -0,0 +1,148 @@
+use crate::{bounds_tree::Node, Bounds, BoundsTree, Scalar};
+use std::fmt::{self, Write};
+
+impl<T: Clone, S: Scalar + fmt::Display> BoundsTree<T, Bounds<S>> {
+    // Exports the tree as a GeoJSON FeatureCollection with one polygon per leaf, so it can be
+    // inspected in tools like QGIS or geojson.io. Leaves carry their `order` and the `key`
+    // returned for their data. Internal nodes can be included as well, carrying their
+    // `depth` and the maximum order within their subtree. Nodes with NaN or infinite
+    // coordinates are left out, since JSON has no numbers for them.
+    pub fn to_geojson(&self, include_internal_nodes: bool, key: impl Fn(&T) -> String) -> String {
+        let mut features = Vec::new();
+        let mut stack = Vec::new();
+        stack.extend(self.tree.root.map(|root| (root, 0)));
+        while let Some((index, depth)) = stack.pop() {
+            let node = self.node(index);
+            let properties = match node {
+                Node::Leaf { data, summary, .. } => {
+                    format!(
+                        r#"{{"kind": "leaf", "order": {summary}, "key": {}}}"#,
+                        json_string(&key(data))
+                    )
+                }
+                Node::Internal {
+                    left,
+                    right,
+                    summary,
+                    ..
+                } => {
+                    stack.push((*right, depth + 1));
+                    stack.push((*left, depth + 1));
+                    if !include_internal_nodes {
+                        continue;
+                    }
+                    format!(r#"{{"kind": "internal", "depth": {depth}, "max_order": {summary}}}"#)
+                }
+            };
+
+            let Bounds { min, max } = node.bounds();
+            let (Some(min_x), Some(min_y), Some(max_x), Some(max_y)) = (
+                json_number(min.x),
+                json_number(min.y),
+                json_number(max.x),
+                json_number(max.y),
+            ) else {
+                continue;
+            };
+            features.push(format!(
+                r#"{{"type": "Feature", "properties": {properties}, "geometry": {{"type": "Polygon", "coordinates": [[[{min_x}, {min_y}], [{max_x}, {min_y}], [{max_x}, {max_y}], [{min_x}, {max_y}], [{min_x}, {min_y}]]]}}}}"#
+            ));
+        }
+
+        format!(
+            r#"{{"type": "FeatureCollection", "features": [{}]}}"#,
+            features.join(", ")
+        )
+    }
+}
+
+// Formats a coordinate as a JSON number, or returns `None` for values like NaN and infinity
+// that JSON can't represent.
+fn json_number<S: fmt::Display>(value: S) -> Option<String> {
+    let formatted = value.to_string();
+    formatted
+        .parse::<f64>()
+        .is_ok_and(f64::is_finite)
+        .then_some(formatted)
+}
+
+fn json_string(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len() + 2);
+    escaped.push('"');
+    for character in value.chars() {
+        match character {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            character if character.is_control() => {
+                write!(escaped, "\\u{:04x}", character as u32).unwrap()
+            }
+            character => escaped.push(character),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Point;
+
+    #[test]
+    fn test_geojson_export() {
+        let mut tree = BoundsTree::new();
+        for (name, x) in [("a", 0.0), ("b \"quoted\"", 5.0), ("c", 20.0)] {
+            let bounds = Bounds {
+                min: Point { x, y: 0.0 },
+                max: Point {
+                    x: x + 10.0,
+                    y: 10.0,
+                },
+            };
+            tree.insert(bounds, name);
+        }
+
+        let leaves: serde_json::Value =
+            serde_json::from_str(&tree.to_geojson(false, |name| name.to_string())).unwrap();
+        let features = leaves["features"].as_array().unwrap();
+        assert_eq!(features.len(), 3);
+        assert_eq!(features[0]["properties"]["key"], "a");
+        assert_eq!(features[0]["properties"]["order"], 1);
+        let corner = &features[0]["geometry"]["coordinates"][0][2];
+        assert_eq!(
+            (corner[0].as_f64(), corner[1].as_f64()),
+            (Some(10.0), Some(10.0))
+        );
+        assert_eq!(features[1]["properties"]["key"], "b \"quoted\"");
+
+        let all: serde_json::Value =
+            serde_json::from_str(&tree.to_geojson(true, |name| name.to_string())).unwrap();
+        let features = all["features"].as_array().unwrap();
+        assert_eq!(features.len(), 5);
+        assert_eq!(features[0]["properties"]["kind"], "internal");
+        assert_eq!(features[0]["properties"]["max_order"], 2);
+    }
+
+    #[test]
+    fn test_geojson_skips_non_finite_bounds() {
+        let mut tree = BoundsTree::new();
+        tree.insert(Bounds::from_xywh(0.0, 0.0, 10.0, 10.0), "finite");
+        tree.insert(Bounds::from_xywh(f32::NAN, 0.0, 10.0, 10.0), "nan");
+        tree.insert(
+            Bounds::new(
+                Point { x: 0.0, y: 0.0 },
+                Point {
+                    x: f32::INFINITY,
+                    y: 10.0,
+                },
+            ),
+            "infinite",
+        );
+
+        let all: serde_json::Value =
+            serde_json::from_str(&tree.to_geojson(true, |name| name.to_string())).unwrap();
+        let features = all["features"].as_array().unwrap();
+        assert_eq!(features.len(), 1);
+        assert_eq!(features[0]["properties"]["key"], "finite");
+    }
+}
@@ -0,0 +1,105 @@
+use crate::{Bounds, Bounds3, Point, Point3};
+
+impl From<glam::Vec2> for Point<f32> {
+    fn from(point: glam::Vec2) -> Self {
+        Point {
+            x: point.x,
+            y: point.y,
+        }
+    }
+}
+
+impl From<Point<f32>> for glam::Vec2 {
+    fn from(point: Point<f32>) -> Self {
+        glam::Vec2::new(point.x, point.y)
+    }
+}
+
+impl From<glam::DVec2> for Point<f64> {
+    fn from(point: glam::DVec2) -> Self {
+        Point {
+            x: point.x,
+            y: point.y,
+        }
+    }
+}
+
+impl From<Point<f64>> for glam::DVec2 {
+    fn from(point: Point<f64>) -> Self {
+        glam::DVec2::new(point.x, point.y)
+    }
+}
+
+impl From<glam::Vec3> for Point3<f32> {
+    fn from(point: glam::Vec3) -> Self {
+        Point3 {
+            x: point.x,
+            y: point.y,
+            z: point.z,
+        }
+    }
+}
+
+impl From<Point3<f32>> for glam::Vec3 {
+    fn from(point: Point3<f32>) -> Self {
+        glam::Vec3::new(point.x, point.y, point.z)
+    }
+}
+
+// Bounds convert from a `(min, max)` pair, so queries can be written as
+// `tree.query_cursor((min, max))` without building a `Bounds` by hand.
+impl From<(glam::Vec2, glam::Vec2)> for Bounds<f32> {
+    fn from((min, max): (glam::Vec2, glam::Vec2)) -> Self {
+        Bounds {
+            min: min.into(),
+            max: max.into(),
+        }
+    }
+}
+
+impl From<(glam::DVec2, glam::DVec2)> for Bounds<f64> {
+    fn from((min, max): (glam::DVec2, glam::DVec2)) -> Self {
+        Bounds {
+            min: min.into(),
+            max: max.into(),
+        }
+    }
+}
+
+impl From<(glam::Vec3, glam::Vec3)> for Bounds3<f32> {
+    fn from((min, max): (glam::Vec3, glam::Vec3)) -> Self {
+        Bounds3 {
+            min: min.into(),
+            max: max.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BoundsTree;
+    use glam::Vec2;
+
+    #[test]
+    fn test_glam_queries() {
+        let mut tree = BoundsTree::<_, Bounds>::new();
+        tree.insert((Vec2::ZERO, Vec2::splat(10.0)).into(), 'a');
+        tree.insert((Vec2::splat(5.0), Vec2::splat(15.0)).into(), 'b');
+        tree.insert((Vec2::splat(20.0), Vec2::splat(30.0)).into(), 'c');
+
+        let mut results = Vec::new();
+        tree.query_cursor((Vec2::splat(8.0), Vec2::splat(9.0)))
+            .poll(usize::MAX, &mut results);
+        let mut hits = results
+            .iter()
+            .map(|primitive| *primitive.data)
+            .collect::<Vec<_>>();
+        hits.sort();
+        assert_eq!(hits, ['a', 'b']);
+        assert_eq!(
+            Vec2::from(Point::from(Vec2::new(1.0, 2.0))),
+            Vec2::new(1.0, 2.0)
+        );
+    }
+}
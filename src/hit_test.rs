@@ -0,0 +1,127 @@
+use crate::{bounds_tree::Node, Bounds, BoundsTree, Point, Primitive, Scalar};
+use std::cmp;
+
+impl<T: Clone, S: Scalar> BoundsTree<T, Bounds<S>> {
+    // Returns the topmost primitive containing the point, i.e. the one a click at that point
+    // should go to. Subtrees whose maximum order doesn't beat the best hit so far are skipped.
+    pub fn hit_test(&self, point: Point<S>) -> Option<Primitive<&T, Bounds<S>>> {
+        let mut hit: Option<Primitive<&T, Bounds<S>>> = None;
+        let mut stack = Vec::new();
+        stack.extend(self.tree.root);
+        while let Some(index) = stack.pop() {
+            let node = self.node(index);
+            if hit.as_ref().is_some_and(|hit| hit.order >= node.summary())
+                || !node.bounds().contains_point(point)
+            {
+                continue;
+            }
+            match node {
+                Node::Leaf {
+                    bounds,
+                    data,
+                    summary: order,
+                } => {
+                    hit = Some(Primitive {
+                        data,
+                        bounds: *bounds,
+                        order: *order,
+                    });
+                }
+                Node::Internal { left, right, .. } => {
+                    stack.push(*left);
+                    stack.push(*right);
+                }
+            }
+        }
+        hit
+    }
+
+    // Yields every primitive containing the point from the topmost down, which is the path an
+    // event bubbles along. Reverse it to get the capture phase.
+    pub fn hit_test_all(
+        &self,
+        point: Point<S>,
+    ) -> impl DoubleEndedIterator<Item = Primitive<&T, Bounds<S>>> {
+        let mut hits = Vec::new();
+        let mut stack = Vec::new();
+        stack.extend(self.tree.root);
+        while let Some(index) = stack.pop() {
+            match self.node(index) {
+                Node::Leaf {
+                    bounds,
+                    data,
+                    summary: order,
+                } => {
+                    if bounds.contains_point(point) {
+                        hits.push(Primitive {
+                            data,
+                            bounds: *bounds,
+                            order: *order,
+                        });
+                    }
+                }
+                Node::Internal {
+                    bounds,
+                    left,
+                    right,
+                    ..
+                } => {
+                    if bounds.contains_point(point) {
+                        stack.push(*left);
+                        stack.push(*right);
+                    }
+                }
+            }
+        }
+        hits.sort_by_key(|hit| cmp::Reverse(hit.order));
+        hits.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::strip;
+
+    #[test]
+    fn test_hit_test() {
+        let mut tree = BoundsTree::new();
+        tree.insert(strip(0.0, 100.0), "window");
+        tree.insert(strip(10.0, 10.0), "button");
+        tree.insert(strip(20.0, 10.0), "other button");
+        tree.insert(strip(15.0, 2.0), "icon");
+
+        let hit = |x| tree.hit_test(Point { x, y: 5.0 }).map(|hit| *hit.data);
+        assert_eq!(hit(5.0), Some("window"));
+        assert_eq!(hit(10.0), Some("button"));
+        assert_eq!(hit(16.0), Some("icon"));
+        assert_eq!(hit(20.0), Some("other button"));
+        assert_eq!(hit(100.0), None);
+        assert!(tree.hit_test(Point { x: 5.0, y: -1.0 }).is_none());
+    }
+
+    #[test]
+    fn test_hit_test_all() {
+        let mut tree = BoundsTree::new();
+        tree.insert(strip(0.0, 100.0), "window");
+        tree.insert(strip(10.0, 10.0), "button");
+        tree.insert(strip(20.0, 10.0), "other button");
+        tree.insert(strip(15.0, 2.0), "icon");
+
+        let hits = |x| {
+            tree.hit_test_all(Point { x, y: 5.0 })
+                .map(|hit| *hit.data)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(hits(16.0), ["icon", "button", "window"]);
+        assert_eq!(hits(25.0), ["other button", "window"]);
+        assert!(hits(100.0).is_empty());
+
+        let capture = tree
+            .hit_test_all(Point { x: 16.0, y: 5.0 })
+            .rev()
+            .map(|hit| hit.order)
+            .collect::<Vec<_>>();
+        assert_eq!(capture, [1, 2, 3]);
+    }
+}
@@ -0,0 +1,178 @@
+use crate::{Bounds, BoundsTree, Error, Point, ShardedBuilder};
+use serde::{de::DeserializeOwned, Deserialize};
+use std::{
+    fmt,
+    io::{self, BufRead, Read},
+    thread,
+};
+
+// A single primitive as stored in CSV (with a `min_x,min_y,max_x,max_y,id` header) or
+// NDJSON (one object with the same fields per line).
+#[derive(Deserialize)]
+struct Record<T> {
+    min_x: f32,
+    min_y: f32,
+    max_x: f32,
+    max_y: f32,
+    id: T,
+}
+
+#[derive(Debug)]
+pub enum ImportError {
+    Io(io::Error),
+    Csv(csv::Error),
+    // A malformed NDJSON record, along with its 1-based line number.
+    Json {
+        line: usize,
+        error: serde_json::Error,
+    },
+    // A well-formed record whose bounds are invalid, along with its 1-based number among the
+    // records, not counting the CSV header or blank NDJSON lines.
+    InvalidBounds {
+        record: usize,
+        error: Error,
+    },
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::Io(error) => write!(f, "{error}"),
+            ImportError::Csv(error) => write!(f, "{error}"),
+            ImportError::Json { line, error } => write!(f, "line {line}: {error}"),
+            ImportError::InvalidBounds { record, error } => write!(f, "record {record}: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for ImportError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ImportError::Io(error) => Some(error),
+            ImportError::Csv(error) => Some(error),
+            ImportError::Json { error, .. } => Some(error),
+            ImportError::InvalidBounds { error, .. } => Some(error),
+        }
+    }
+}
+
+impl<T: Clone + Send + DeserializeOwned> BoundsTree<T> {
+    // Streams records from CSV and bulk-loads them on all available cores. Orders are
+    // assigned as if the records had been inserted in the order they appear.
+    pub fn from_csv_reader(reader: impl Read) -> Result<Self, ImportError> {
+        let mut builder = bulk_builder();
+        for (ix, record) in csv::Reader::from_reader(reader)
+            .into_deserialize()
+            .enumerate()
+        {
+            push_record(&mut builder, ix + 1, record.map_err(ImportError::Csv)?)?;
+        }
+        Ok(builder.build())
+    }
+
+    // Like `from_csv_reader`, for newline-delimited JSON. Blank lines are skipped.
+    pub fn from_ndjson_reader(reader: impl BufRead) -> Result<Self, ImportError> {
+        let mut builder = bulk_builder();
+        let mut records = 0;
+        for (ix, line) in reader.lines().enumerate() {
+            let line = line.map_err(ImportError::Io)?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record = serde_json::from_str(&line).map_err(|error| ImportError::Json {
+                line: ix + 1,
+                error,
+            })?;
+            records += 1;
+            push_record(&mut builder, records, record)?;
+        }
+        Ok(builder.build())
+    }
+}
+
+fn bulk_builder<T: Clone + Send>() -> ShardedBuilder<T> {
+    ShardedBuilder::new(thread::available_parallelism().map_or(1, |count| count.get()))
+}
+
+fn push_record<T: Clone + Send>(
+    builder: &mut ShardedBuilder<T>,
+    number: usize,
+    record: Record<T>,
+) -> Result<(), ImportError> {
+    let bounds = Bounds {
+        min: Point {
+            x: record.min_x,
+            y: record.min_y,
+        },
+        max: Point {
+            x: record.max_x,
+            y: record.max_y,
+        },
+    };
+    if !bounds.is_valid() {
+        return Err(ImportError::InvalidBounds {
+            record: number,
+            error: Error::InvalidBounds {
+                bounds: format!("{bounds:?}"),
+            },
+        });
+    }
+    builder.push(bounds, record.id);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn orders(tree: &BoundsTree<String>) -> Vec<(String, u32)> {
+        let mut orders = tree
+            .iter()
+            .map(|primitive| (primitive.data.clone(), primitive.order))
+            .collect::<Vec<_>>();
+        orders.sort();
+        orders
+    }
+
+    #[test]
+    fn test_import() {
+        let csv = "min_x,min_y,max_x,max_y,id\n0,0,10,10,a\n5,5,15,15,b\n20,20,30,30,c\n";
+        let tree = BoundsTree::<String>::from_csv_reader(csv.as_bytes()).unwrap();
+        let expected = [("a".into(), 1), ("b".into(), 2), ("c".into(), 1)];
+        assert_eq!(orders(&tree), expected);
+
+        let ndjson = r#"{"min_x": 0, "min_y": 0, "max_x": 10, "max_y": 10, "id": "a"}
+{"min_x": 5, "min_y": 5, "max_x": 15, "max_y": 15, "id": "b"}
+
+{"min_x": 20, "min_y": 20, "max_x": 30, "max_y": 30, "id": "c"}
+"#;
+        let tree = BoundsTree::<String>::from_ndjson_reader(ndjson.as_bytes()).unwrap();
+        assert_eq!(orders(&tree), expected);
+
+        let error = BoundsTree::<String>::from_ndjson_reader("{}\n".as_bytes()).unwrap_err();
+        assert!(matches!(error, ImportError::Json { line: 1, .. }));
+        assert!(BoundsTree::<String>::from_csv_reader("min_x\nfoo\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_import_invalid_bounds() {
+        let csv = "min_x,min_y,max_x,max_y,id\n0,0,10,10,a\nNaN,0,10,10,b\n";
+        let error = BoundsTree::<String>::from_csv_reader(csv.as_bytes()).unwrap_err();
+        assert!(matches!(
+            error,
+            ImportError::InvalidBounds { record: 2, .. }
+        ));
+
+        let ndjson = r#"{"min_x": 0, "min_y": 0, "max_x": 10, "max_y": 10, "id": "a"}
+
+{"min_x": 10, "min_y": 0, "max_x": 0, "max_y": 10, "id": "b"}
+"#;
+        let error = BoundsTree::<String>::from_ndjson_reader(ndjson.as_bytes()).unwrap_err();
+        assert!(matches!(
+            error,
+            ImportError::InvalidBounds { record: 2, .. }
+        ));
+        assert!(error.to_string().starts_with("record 2: invalid bounds"));
+        assert!(std::error::Error::source(&error).is_some());
+    }
+}
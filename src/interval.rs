@@ -0,0 +1,101 @@
+use crate::{BoundingBox, BoundsTree, Scalar};
+
+// A `BoundsTree` over one-dimensional intervals, e.g. text ranges or spans on a timeline.
+pub type IntervalTree<T, S = f32> = BoundsTree<T, Interval<S>>;
+
+// A half-open interval: intervals that merely touch at an endpoint don't intersect.
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Interval<S = f32> {
+    pub start: S,
+    pub end: S,
+}
+
+impl<S: Scalar> BoundingBox for Interval<S> {
+    type Cost = S;
+
+    fn merge(self, other: Self) -> Self {
+        Interval {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+
+    fn intersects(self, other: Self) -> bool {
+        self.start < other.end && other.start < self.end
+    }
+
+    fn contains(self, other: Self) -> bool {
+        self.start <= other.start && self.end >= other.end
+    }
+
+    fn cost(self) -> S {
+        self.end - self.start
+    }
+
+    // NaN endpoints fail the comparison, like reversed intervals.
+    fn is_valid(self) -> bool {
+        self.start <= self.end
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_ranges() {
+        let range = |start: i64, end: i64| Interval { start, end };
+
+        let mut tree = IntervalTree::new();
+        assert_eq!(tree.insert(range(0, 10), "a"), 1);
+        assert_eq!(tree.insert(range(10, 20), "b"), 1);
+        assert_eq!(tree.insert(range(5, 15), "c"), 2);
+        assert_eq!(tree.insert(range(12, 13), "d"), 3);
+        assert_eq!(tree.insert(range(20, 20), "e"), 1);
+
+        let mut cursor = tree.query_cursor(range(0, 6));
+        let mut results = Vec::new();
+        cursor.poll(usize::MAX, &mut results);
+        let mut results = results
+            .into_iter()
+            .map(|primitive| *primitive.data)
+            .collect::<Vec<_>>();
+        results.sort();
+        assert_eq!(results, ["a", "c"]);
+    }
+
+    #[test]
+    fn test_invalid_intervals() {
+        let mut tree = IntervalTree::new();
+        assert!(tree
+            .try_insert(
+                Interval {
+                    start: 5.0,
+                    end: 1.0
+                },
+                "reversed"
+            )
+            .is_err());
+        assert!(tree
+            .try_insert(
+                Interval {
+                    start: f32::NAN,
+                    end: 1.0
+                },
+                "nan"
+            )
+            .is_err());
+        assert_eq!(
+            tree.try_insert(
+                Interval {
+                    start: 1.0,
+                    end: 1.0
+                },
+                "empty"
+            ),
+            Ok(1)
+        );
+        assert_eq!(tree.len(), 1);
+    }
+}
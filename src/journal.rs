@@ -0,0 +1,132 @@
+use crate::{BoundingBox, Bounds, BoundsTree, PrimitiveHandle};
+
+// A mutation of a `BoundsTree`, along with all of its inputs.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Mutation<T, B = Bounds> {
+    Insert { bounds: B, data: T },
+    Remove { handle: PrimitiveHandle },
+    Update { handle: PrimitiveHandle, bounds: B },
+}
+
+// Wraps a `BoundsTree`, recording every mutation so the journal can be stored alongside a bug
+// report and replayed later. Tree construction is deterministic, so replaying a journal
+// reproduces the exact same tree, down to its layout. Every primitive gets a handle, so
+// replaying also hands out the same handles, which is how removals are recorded.
+#[derive(Debug)]
+pub struct JournaledBoundsTree<T, B = Bounds> {
+    tree: BoundsTree<T, B>,
+    journal: Vec<Mutation<T, B>>,
+}
+
+impl<T: Clone, B: BoundingBox> Default for JournaledBoundsTree<T, B> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone, B: BoundingBox> JournaledBoundsTree<T, B> {
+    pub fn new() -> Self {
+        JournaledBoundsTree {
+            tree: BoundsTree::new(),
+            journal: Vec::new(),
+        }
+    }
+
+    pub fn insert(&mut self, bounds: B, data: T) -> u32 {
+        self.insert_with_handle(bounds, data).1
+    }
+
+    pub fn insert_with_handle(&mut self, bounds: B, data: T) -> (PrimitiveHandle, u32) {
+        self.journal.push(Mutation::Insert {
+            bounds,
+            data: data.clone(),
+        });
+        self.tree.insert_with_handle(bounds, data)
+    }
+
+    pub fn remove(&mut self, handle: PrimitiveHandle) -> Option<T> {
+        self.journal.push(Mutation::Remove { handle });
+        self.tree.remove(handle)
+    }
+
+    pub fn update(&mut self, handle: PrimitiveHandle, bounds: B) -> Option<u32> {
+        self.journal.push(Mutation::Update { handle, bounds });
+        self.tree.update(handle, bounds)
+    }
+
+    pub fn tree(&self) -> &BoundsTree<T, B> {
+        &self.tree
+    }
+
+    pub fn journal(&self) -> &[Mutation<T, B>] {
+        &self.journal
+    }
+
+    pub fn into_parts(self) -> (BoundsTree<T, B>, Vec<Mutation<T, B>>) {
+        (self.tree, self.journal)
+    }
+}
+
+impl<T: Clone, B: BoundingBox> BoundsTree<T, B> {
+    // Builds a tree by applying a journal recorded by `JournaledBoundsTree` to an empty tree.
+    pub fn replay<'a>(journal: impl IntoIterator<Item = &'a Mutation<T, B>>) -> Self
+    where
+        T: 'a,
+        B: 'a,
+    {
+        let mut tree = BoundsTree::new();
+        for mutation in journal {
+            match mutation {
+                Mutation::Insert { bounds, data } => {
+                    tree.insert_with_handle(*bounds, data.clone());
+                }
+                Mutation::Remove { handle } => {
+                    tree.remove(*handle);
+                }
+                Mutation::Update { handle, bounds } => {
+                    tree.update(*handle, *bounds);
+                }
+            }
+        }
+        tree
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::random_bounds;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn test_replay_reproduces_layout() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+        let mut journaled = JournaledBoundsTree::new();
+        let mut handles = Vec::new();
+        for id in 0..100 {
+            if id % 4 == 2 {
+                let handle = handles.swap_remove(rng.gen_range(0..handles.len()));
+                assert!(journaled.remove(handle).is_some());
+                continue;
+            }
+            let bounds = random_bounds(&mut rng, 50.0);
+            if id % 4 == 3 {
+                let handle = handles[rng.gen_range(0..handles.len())];
+                assert!(journaled.update(handle, bounds).is_some());
+            } else {
+                handles.push(journaled.insert_with_handle(bounds, id).0);
+            }
+        }
+        assert_eq!(journaled.journal().len(), 100);
+
+        #[cfg(feature = "serde")]
+        let journal: Vec<Mutation<i32>> =
+            serde_json::from_str(&serde_json::to_string(journaled.journal()).unwrap()).unwrap();
+        #[cfg(not(feature = "serde"))]
+        let journal = journaled.journal().to_vec();
+
+        let replayed = BoundsTree::replay(&journal);
+        assert_eq!(replayed.flatten(), journaled.tree().flatten());
+    }
+}
@@ -0,0 +1,94 @@
+use crate::{BoundingBox, Bounds, BoundsTree};
+
+// A `BoundsTree` that separates a small `Copy` key, which is what queries yield, from a
+// potentially large value that is stored out of line and only accessed through its handle.
+// This keeps the hierarchy compact and avoids touching values while traversing it.
+#[derive(Debug)]
+pub struct KeyedBoundsTree<K, V, B = Bounds> {
+    tree: BoundsTree<(K, ValueHandle), B>,
+    values: Vec<V>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ValueHandle(usize);
+
+impl<K: Copy, V, B: BoundingBox> Default for KeyedBoundsTree<K, V, B> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Copy, V, B: BoundingBox> KeyedBoundsTree<K, V, B> {
+    pub fn new() -> Self {
+        KeyedBoundsTree {
+            tree: BoundsTree::new(),
+            values: Vec::new(),
+        }
+    }
+
+    // Inserts a new primitive, returning the handle of its value along with its order.
+    pub fn insert(&mut self, bounds: B, key: K, value: V) -> (ValueHandle, u32) {
+        let handle = ValueHandle(self.values.len());
+        self.values.push(value);
+        let order = self.tree.insert(bounds, (key, handle));
+        (handle, order)
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn value(&self, handle: ValueHandle) -> &V {
+        &self.values[handle.0]
+    }
+
+    pub fn value_mut(&mut self, handle: ValueHandle) -> &mut V {
+        &mut self.values[handle.0]
+    }
+
+    // Yields the key and value handle of every primitive intersecting `bounds`.
+    pub fn query(&self, bounds: B) -> impl Iterator<Item = (K, ValueHandle)> + '_ {
+        self.tree
+            .tree
+            .search(move |node_bounds, _| node_bounds.intersects(bounds))
+            .map(|(_, (key, handle))| (*key, *handle))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (K, ValueHandle)> + '_ {
+        self.tree
+            .tree
+            .iter()
+            .map(|(_, (key, handle))| (*key, *handle))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::strip;
+
+    #[test]
+    fn test_keyed_tree() {
+        let mut tree = KeyedBoundsTree::new();
+        let (a, order) = tree.insert(strip(0.0, 10.0), 'a', vec![0u8; 1024]);
+        assert_eq!(order, 1);
+        let (b, order) = tree.insert(strip(5.0, 10.0), 'b', vec![1u8; 1024]);
+        assert_eq!(order, 2);
+        let (_, order) = tree.insert(strip(20.0, 10.0), 'c', Vec::new());
+        assert_eq!(order, 1);
+        assert_eq!(tree.len(), 3);
+
+        let mut hits = tree.query(strip(2.0, 10.0)).collect::<Vec<_>>();
+        hits.sort();
+        assert_eq!(hits, [('a', a), ('b', b)]);
+
+        tree.value_mut(b).clear();
+        assert!(tree.value(b).is_empty());
+        assert_eq!(tree.value(a).len(), 1024);
+        assert_eq!(tree.iter().count(), 3);
+    }
+}
@@ -0,0 +1,151 @@
+use crate::{BoundingBox, Bounds, BoundsTree, Point, Primitive, Scalar};
+use std::collections::BTreeMap;
+
+// A stack of independently ordered layers, e.g. background, content, and overlay. Every layer
+// is drawn entirely above the layers before it according to `L`'s ordering, so a primitive in
+// an overlay layer goes above all content without having to intersect any of it.
+#[derive(Debug, Clone)]
+pub struct LayeredBoundsTree<L, T, B = Bounds> {
+    layers: BTreeMap<L, BoundsTree<T, B>>,
+}
+
+// Layers keyed by an explicit z-index, like CSS's `z-index`. The z-index takes precedence over
+// the order derived from overlaps, which only breaks ties between equal z-indices.
+pub type ZIndexedBoundsTree<T, B = Bounds> = LayeredBoundsTree<i32, T, B>;
+
+impl<L: Ord, T: Clone, B: BoundingBox> Default for LayeredBoundsTree<L, T, B> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<L: Ord, T: Clone, B: BoundingBox> LayeredBoundsTree<L, T, B> {
+    pub fn new() -> Self {
+        LayeredBoundsTree {
+            layers: BTreeMap::new(),
+        }
+    }
+
+    // Inserts a primitive into a layer, returning its order within that layer.
+    pub fn insert(&mut self, layer: L, new_bounds: B, data: T) -> u32 {
+        self.layers
+            .entry(layer)
+            .or_default()
+            .insert(new_bounds, data)
+    }
+
+    pub fn layer(&self, layer: &L) -> Option<&BoundsTree<T, B>> {
+        self.layers.get(layer)
+    }
+
+    pub fn layer_mut(&mut self, layer: &L) -> Option<&mut BoundsTree<T, B>> {
+        self.layers.get_mut(layer)
+    }
+
+    // Yields every primitive along with its layer in painter's order, i.e. layer by layer and
+    // by ascending order within each layer.
+    pub fn iter_in_order(&self) -> impl Iterator<Item = (&L, Primitive<&T, B>)> {
+        self.layers.iter().flat_map(|(layer, tree)| {
+            tree.iter_in_order()
+                .map(move |primitive| (layer, primitive))
+        })
+    }
+}
+
+impl<T: Clone, B: BoundingBox> ZIndexedBoundsTree<T, B> {
+    // Inserts a primitive without an explicit z-index, which behaves like a z-index of zero.
+    pub fn insert_auto(&mut self, new_bounds: B, data: T) -> u32 {
+        self.insert(0, new_bounds, data)
+    }
+}
+
+impl<L: Ord, T: Clone, S: Scalar> LayeredBoundsTree<L, T, Bounds<S>> {
+    // Returns the topmost primitive containing the point, searching the layers from the top.
+    pub fn hit_test(&self, point: Point<S>) -> Option<(&L, Primitive<&T, Bounds<S>>)> {
+        self.layers
+            .iter()
+            .rev()
+            .find_map(|(layer, tree)| Some((layer, tree.hit_test(point)?)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::strip;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+    enum Layer {
+        Background,
+        Content,
+        Overlay,
+    }
+
+    #[test]
+    fn test_layers() {
+        let mut tree = LayeredBoundsTree::new();
+        assert_eq!(tree.insert(Layer::Overlay, strip(50.0, 10.0), "cursor"), 1);
+        assert_eq!(tree.insert(Layer::Content, strip(0.0, 10.0), "text"), 1);
+        assert_eq!(
+            tree.insert(Layer::Content, strip(5.0, 50.0), "selection"),
+            2
+        );
+        assert_eq!(tree.insert(Layer::Background, strip(0.0, 100.0), "fill"), 1);
+
+        let painted = tree
+            .iter_in_order()
+            .map(|(layer, primitive)| (*layer, *primitive.data))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            painted,
+            [
+                (Layer::Background, "fill"),
+                (Layer::Content, "text"),
+                (Layer::Content, "selection"),
+                (Layer::Overlay, "cursor"),
+            ]
+        );
+
+        let hit = |x| {
+            tree.hit_test(Point { x, y: 5.0 })
+                .map(|(layer, primitive)| (*layer, *primitive.data))
+        };
+        assert_eq!(hit(52.0), Some((Layer::Overlay, "cursor")));
+        assert_eq!(hit(8.0), Some((Layer::Content, "selection")));
+        assert_eq!(hit(90.0), Some((Layer::Background, "fill")));
+        assert_eq!(tree.layer(&Layer::Content).unwrap().iter().count(), 2);
+    }
+
+    #[test]
+    fn test_z_index() {
+        let mut tree = ZIndexedBoundsTree::new();
+        tree.insert(1, strip(0.0, 10.0), "raised");
+        tree.insert_auto(strip(5.0, 10.0), "card");
+        tree.insert_auto(strip(8.0, 10.0), "label");
+        tree.insert(-1, strip(0.0, 100.0), "backdrop");
+
+        let painted = tree
+            .iter_in_order()
+            .map(|(z_index, primitive)| (*z_index, primitive.order, *primitive.data))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            painted,
+            [
+                (-1, 1, "backdrop"),
+                (0, 1, "card"),
+                (0, 2, "label"),
+                (1, 1, "raised")
+            ]
+        );
+        assert_eq!(
+            tree.hit_test(Point { x: 9.0, y: 5.0 })
+                .map(|(_, hit)| *hit.data),
+            Some("raised")
+        );
+        assert_eq!(
+            tree.hit_test(Point { x: 12.0, y: 5.0 })
+                .map(|(_, hit)| *hit.data),
+            Some("label")
+        );
+    }
+}
@@ -1,3 +1,115 @@
+mod affine;
+#[cfg(feature = "arbitrary")]
+mod arbitrary_interop;
+mod augmented_tree;
+mod binary;
+mod bounding_box;
+mod bounds3;
+mod bounds_n;
 mod bounds_tree;
+mod broad_phase;
+mod coarse_grid;
+mod diff;
+mod dot;
+mod entity_tree;
+mod error;
+#[cfg(feature = "euclid")]
+mod euclid_interop;
+mod flat_tree;
+mod fractional_index;
+#[cfg(feature = "geo")]
+mod geo_interop;
+mod geojson;
+#[cfg(feature = "glam")]
+mod glam_interop;
+mod hit_test;
+#[cfg(feature = "import")]
+mod import;
+mod interval;
+mod journal;
+mod keyed_bounds_tree;
+mod layered_bounds_tree;
+#[cfg(feature = "oracle")]
+mod linear_index;
+#[cfg(feature = "mint")]
+mod mint_interop;
+mod occlusion;
+mod ordered_bounds;
+#[cfg(feature = "parry")]
+mod parry_interop;
+mod persistent_bounds_tree;
+#[cfg(feature = "serde")]
+pub mod portable;
+mod primitive_handle;
+#[cfg(feature = "python")]
+mod python;
+mod quadtree;
+mod query_cursor;
+mod retained_bounds_tree;
+#[cfg(feature = "rstar")]
+mod rstar_interop;
+mod scalar;
+mod sharded_builder;
+mod snapshot;
+mod spatial_index;
+mod stacking_context;
+mod stats;
+#[cfg(feature = "proptest")]
+pub mod strategies;
+#[cfg(feature = "svg")]
+mod svg;
+mod sweep_and_prune;
+#[cfg(test)]
+mod test_fixtures;
+mod tiles;
+#[cfg(feature = "svg")]
+mod trace_recorder;
+mod uniform_grid;
+mod validate;
+#[cfg(feature = "wasm")]
+mod wasm;
 
+pub use affine::*;
+pub use augmented_tree::*;
+pub use bounding_box::*;
+pub use bounds3::*;
+pub use bounds_n::*;
 pub use bounds_tree::*;
+pub use broad_phase::*;
+pub use coarse_grid::*;
+pub use diff::*;
+pub use entity_tree::*;
+pub use error::*;
+pub use flat_tree::*;
+pub use fractional_index::*;
+#[cfg(feature = "import")]
+pub use import::*;
+pub use interval::*;
+pub use journal::*;
+pub use keyed_bounds_tree::*;
+pub use layered_bounds_tree::*;
+#[cfg(feature = "oracle")]
+pub use linear_index::*;
+pub use ordered_bounds::*;
+pub use persistent_bounds_tree::*;
+pub use primitive_handle::*;
+#[cfg(feature = "python")]
+pub use python::*;
+pub use quadtree::*;
+pub use query_cursor::*;
+pub use retained_bounds_tree::*;
+pub use scalar::*;
+pub use sharded_builder::*;
+pub use spatial_index::*;
+pub use stacking_context::*;
+pub use stats::*;
+#[cfg(feature = "svg")]
+pub use svg::*;
+pub use sweep_and_prune::*;
+pub use tiles::*;
+#[cfg(feature = "svg")]
+pub use trace_recorder::*;
+pub use uniform_grid::*;
+pub use validate::*;
+#[cfg(feature = "wasm")]
+pub use wasm::*;
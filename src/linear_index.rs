@@ -0,0 +1,110 @@
+use crate::{BoundingBox, Bounds, Error, Primitive, QueryStats};
+
+// A brute-force reference implementation of `BoundsTree` that stores primitives in a list and
+// tests every one of them on insertion and query. It's far too slow for real use, but simple
+// enough to be obviously correct, which makes it an oracle for differential tests.
+#[derive(Debug)]
+pub struct LinearIndex<T, B = Bounds> {
+    primitives: Vec<Primitive<T, B>>,
+}
+
+impl<T, B: BoundingBox> Default for LinearIndex<T, B> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, B: BoundingBox> LinearIndex<T, B> {
+    pub fn new() -> Self {
+        LinearIndex {
+            primitives: Vec::new(),
+        }
+    }
+
+    pub fn insert(&mut self, bounds: B, data: T) -> u32 {
+        let order = self
+            .primitives
+            .iter()
+            .filter(|primitive| primitive.bounds.intersects(bounds))
+            .map(|primitive| primitive.order)
+            .max()
+            .unwrap_or(0)
+            + 1;
+        self.primitives.push(Primitive {
+            data,
+            bounds,
+            order,
+        });
+        order
+    }
+
+    pub fn try_insert(&mut self, bounds: B, data: T) -> Result<u32, Error> {
+        if !bounds.is_valid() {
+            return Err(Error::InvalidBounds {
+                bounds: format!("{bounds:?}"),
+            });
+        }
+        Ok(self.insert(bounds, data))
+    }
+
+    // Yields primitives in insertion order.
+    pub fn iter(&self) -> impl Iterator<Item = Primitive<&T, B>> {
+        self.primitives.iter().map(|primitive| Primitive {
+            data: &primitive.data,
+            bounds: primitive.bounds,
+            order: primitive.order,
+        })
+    }
+
+    // Returns the primitives intersecting `bounds` in insertion order. The statistics describe
+    // a linear scan, where every primitive is tested.
+    pub fn query_with_stats(&self, bounds: impl Into<B>) -> (Vec<Primitive<&T, B>>, QueryStats) {
+        let bounds = bounds.into();
+        let results = self
+            .iter()
+            .filter(|primitive| primitive.bounds.intersects(bounds))
+            .collect::<Vec<_>>();
+        let stats = QueryStats {
+            node_count: self.primitives.len(),
+            nodes_visited: self.primitives.len(),
+            leaves_tested: self.primitives.len(),
+            hits: results.len(),
+            subtrees_pruned: 0,
+        };
+        (results, stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::random_bounds;
+    use crate::BoundsTree;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_matches_bounds_tree() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(5);
+        let mut tree = BoundsTree::new();
+        let mut oracle = LinearIndex::new();
+        for id in 0..200 {
+            let bounds = random_bounds(&mut rng, 50.0);
+            assert_eq!(tree.insert(bounds, id), oracle.insert(bounds, id));
+        }
+        for _ in 0..20 {
+            let query = random_bounds(&mut rng, 50.0);
+            let sorted = |primitives: Vec<Primitive<&i32>>| {
+                let mut hits = primitives
+                    .into_iter()
+                    .map(|primitive| (*primitive.data, primitive.order))
+                    .collect::<Vec<_>>();
+                hits.sort();
+                hits
+            };
+            assert_eq!(
+                sorted(tree.query_with_stats(query).0),
+                sorted(oracle.query_with_stats(query).0)
+            );
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use crate::{Bounds, Bounds3, Point, Point3};
+
+impl<S> From<mint::Point2<S>> for Point<S> {
+    fn from(point: mint::Point2<S>) -> Self {
+        Point {
+            x: point.x,
+            y: point.y,
+        }
+    }
+}
+
+impl<S> From<Point<S>> for mint::Point2<S> {
+    fn from(point: Point<S>) -> Self {
+        mint::Point2 {
+            x: point.x,
+            y: point.y,
+        }
+    }
+}
+
+impl<S> From<mint::Point3<S>> for Point3<S> {
+    fn from(point: mint::Point3<S>) -> Self {
+        Point3 {
+            x: point.x,
+            y: point.y,
+            z: point.z,
+        }
+    }
+}
+
+impl<S> From<Point3<S>> for mint::Point3<S> {
+    fn from(point: Point3<S>) -> Self {
+        mint::Point3 {
+            x: point.x,
+            y: point.y,
+            z: point.z,
+        }
+    }
+}
+
+// mint has no rectangle type, so bounds convert to and from a `(min, max)` pair of points.
+impl<S> From<(mint::Point2<S>, mint::Point2<S>)> for Bounds<S> {
+    fn from((min, max): (mint::Point2<S>, mint::Point2<S>)) -> Self {
+        Bounds {
+            min: min.into(),
+            max: max.into(),
+        }
+    }
+}
+
+impl<S> From<Bounds<S>> for (mint::Point2<S>, mint::Point2<S>) {
+    fn from(bounds: Bounds<S>) -> Self {
+        (bounds.min.into(), bounds.max.into())
+    }
+}
+
+impl<S> From<(mint::Point3<S>, mint::Point3<S>)> for Bounds3<S> {
+    fn from((min, max): (mint::Point3<S>, mint::Point3<S>)) -> Self {
+        Bounds3 {
+            min: min.into(),
+            max: max.into(),
+        }
+    }
+}
+
+impl<S> From<Bounds3<S>> for (mint::Point3<S>, mint::Point3<S>) {
+    fn from(bounds: Bounds3<S>) -> Self {
+        (bounds.min.into(), bounds.max.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mint_round_trip() {
+        let min = mint::Point2 { x: 1.0, y: 2.0 };
+        let max = mint::Point2 { x: 3.0, y: 4.0 };
+        let bounds = Bounds::from((min, max));
+        assert_eq!(bounds.min, Point { x: 1.0, y: 2.0 });
+        assert_eq!(bounds.max, Point { x: 3.0, y: 4.0 });
+        assert_eq!(
+            <(mint::Point2<f32>, mint::Point2<f32>)>::from(bounds),
+            (min, max)
+        );
+
+        let point = mint::Point3 { x: 1, y: 2, z: 3 };
+        assert_eq!(mint::Point3::from(Point3::from(point)), point);
+    }
+}
@@ -0,0 +1,75 @@
+use crate::{bounds_tree::Node, BoundingBox, BoundsTree, Primitive};
+
+impl<T: Clone, B: BoundingBox> BoundsTree<T, B> {
+    // Yields the primitives that aren't completely covered by a single opaque primitive above
+    // them, where `is_opaque` decides which primitives hide what's below them. Primitives only
+    // covered by several opaque primitives together are still yielded, so this is conservative.
+    pub fn iter_visible<'a>(
+        &'a self,
+        is_opaque: impl Fn(&T) -> bool + 'a,
+    ) -> impl Iterator<Item = Primitive<&'a T, B>> {
+        self.iter()
+            .filter(move |primitive| !self.is_occluded(primitive, &is_opaque))
+    }
+
+    fn is_occluded(&self, primitive: &Primitive<&T, B>, is_opaque: &impl Fn(&T) -> bool) -> bool {
+        let mut stack = Vec::new();
+        stack.extend(self.tree.root);
+        while let Some(index) = stack.pop() {
+            let node = self.node(index);
+            // Only subtrees that contain the primitive can contain a leaf that does.
+            if node.summary() <= primitive.order || !node.bounds().contains(primitive.bounds) {
+                continue;
+            }
+            match node {
+                Node::Leaf { data, .. } => {
+                    if is_opaque(data) {
+                        return true;
+                    }
+                }
+                Node::Internal { left, right, .. } => {
+                    stack.push(*left);
+                    stack.push(*right);
+                }
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::strip;
+
+    #[test]
+    fn test_iter_visible() {
+        let mut tree = BoundsTree::new();
+        tree.insert(strip(0.0, 10.0), ("hidden", false));
+        tree.insert(strip(5.0, 10.0), ("partially hidden", false));
+        tree.insert(strip(20.0, 10.0), ("behind glass", false));
+        tree.insert(strip(0.0, 12.0), ("panel", true));
+        tree.insert(strip(18.0, 14.0), ("glass", false));
+        tree.insert(strip(40.0, 10.0), ("above", false));
+        tree.insert(strip(38.0, 14.0), ("backdrop", true));
+        tree.insert(strip(40.0, 10.0), ("on top", false));
+
+        let mut visible = tree
+            .iter_visible(|(_, opaque)| *opaque)
+            .map(|primitive| primitive.data.0)
+            .collect::<Vec<_>>();
+        visible.sort();
+        assert_eq!(
+            visible,
+            [
+                "backdrop",
+                "behind glass",
+                "glass",
+                "on top",
+                "panel",
+                "partially hidden"
+            ]
+        );
+        assert_eq!(tree.iter_visible(|_| false).count(), 8);
+    }
+}
@@ -0,0 +1,87 @@
+use crate::Bounds;
+use std::{
+    cmp::Ordering,
+    hash::{Hash, Hasher},
+};
+
+// Bounds that can key a `HashMap` or `BTreeMap`, e.g. to deduplicate damage regions or memoize
+// layout. Coordinates are compared by their bits, so NaN coordinates equal themselves, except
+// that zero and negative zero are equal. The ordering compares `min` before `max` and `x` before
+// `y`, and doesn't mean anything geometrically.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OrderedBounds(pub Bounds);
+
+impl OrderedBounds {
+    fn coordinates(&self) -> [f32; 4] {
+        let Bounds { min, max } = self.0;
+        // Adding zero turns negative zero into zero and leaves everything else untouched.
+        [min.x, min.y, max.x, max.y].map(|coordinate| coordinate + 0.0)
+    }
+}
+
+impl From<Bounds> for OrderedBounds {
+    fn from(bounds: Bounds) -> Self {
+        OrderedBounds(bounds)
+    }
+}
+
+impl PartialEq for OrderedBounds {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for OrderedBounds {}
+
+impl PartialOrd for OrderedBounds {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OrderedBounds {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.coordinates()
+            .iter()
+            .zip(other.coordinates())
+            .map(|(a, b)| a.total_cmp(&b))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+impl Hash for OrderedBounds {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for coordinate in self.coordinates() {
+            coordinate.to_bits().hash(state);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Point;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_ordered_bounds() {
+        let a = Bounds::from_xywh(0.0, 0.0, 10.0, 10.0);
+        let b = Bounds::from_xywh(-0.0, 0.0, 10.0, 10.0);
+        let c = Bounds::from_xywh(5.0, 0.0, 10.0, 10.0);
+        let nan = Bounds::new(
+            Point {
+                x: f32::NAN,
+                y: 0.0,
+            },
+            Point { x: 1.0, y: 1.0 },
+        );
+        let set = [a, b, c, nan, nan]
+            .into_iter()
+            .map(OrderedBounds)
+            .collect::<HashSet<_>>();
+        assert_eq!(set.len(), 3);
+        assert!(set.contains(&OrderedBounds(a)) && set.contains(&OrderedBounds(nan)));
+        assert!(OrderedBounds(a) < OrderedBounds(c));
+    }
+}
@@ -0,0 +1,51 @@
+use crate::{Bounds, Point};
+use parry2d::{bounding_volume::Aabb, math};
+
+// Lets `BroadPhase` consume the AABBs parry computes for colliders, e.g.
+// `broad_phase.set_bounds(proxy, collider.compute_aabb().into())`.
+impl From<Aabb> for Bounds<f32> {
+    fn from(aabb: Aabb) -> Self {
+        Bounds {
+            min: Point {
+                x: aabb.mins.x,
+                y: aabb.mins.y,
+            },
+            max: Point {
+                x: aabb.maxs.x,
+                y: aabb.maxs.y,
+            },
+        }
+    }
+}
+
+impl From<Bounds<f32>> for Aabb {
+    fn from(bounds: Bounds<f32>) -> Self {
+        Aabb::new(
+            math::Point::new(bounds.min.x, bounds.min.y),
+            math::Point::new(bounds.max.x, bounds.max.y),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BroadPhase, PairEvent};
+    use parry2d::{math::Isometry, shape::Ball};
+
+    #[test]
+    fn test_parry_aabbs() {
+        let ball = Ball::new(1.0);
+        let mut broad_phase = BroadPhase::<_, Bounds>::new();
+        broad_phase.create_proxy(1, ball.aabb(&Isometry::translation(0.0, 0.0)).into());
+        broad_phase.create_proxy(2, ball.aabb(&Isometry::translation(1.5, 0.0)).into());
+        broad_phase.create_proxy(3, ball.aabb(&Isometry::translation(5.0, 0.0)).into());
+
+        let mut events = Vec::new();
+        broad_phase.update(&mut events);
+        assert_eq!(events, [PairEvent::Added(1, 2)]);
+
+        let aabb = ball.aabb(&Isometry::translation(5.0, 0.0));
+        assert_eq!(Aabb::from(Bounds::from(aabb)), aabb);
+    }
+}
@@ -0,0 +1,465 @@
+use crate::{
+    augmented_tree::Augmentation, bounds_tree::MaxOrdering, dot::DotWriter, BoundingBox, Bounds,
+    InsertionCost, Primitive,
+};
+use std::{iter, sync::Arc};
+
+// A fully persistent variant of `BoundsTree`. Every mutation returns a new version of the
+// tree that shares all untouched subtrees with the version it was derived from, so keeping
+// old versions around (e.g. for undo) only costs the nodes along each modified path.
+#[derive(Debug)]
+pub struct PersistentBoundsTree<T, B = Bounds> {
+    root: Option<Arc<Node<T, B>>>,
+    // Carried over to every version derived from this one.
+    insertion_cost: InsertionCost<B>,
+}
+
+impl<T, B> Clone for PersistentBoundsTree<T, B> {
+    fn clone(&self) -> Self {
+        PersistentBoundsTree {
+            root: self.root.clone(),
+            insertion_cost: self.insertion_cost,
+        }
+    }
+}
+
+impl<T, B: BoundingBox> Default for PersistentBoundsTree<T, B> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, B: BoundingBox> PersistentBoundsTree<T, B> {
+    pub fn new() -> Self {
+        Self::with_insertion_cost(InsertionCost::default())
+    }
+
+    // Creates a tree that measures bounds with `insertion_cost` when choosing where to insert
+    // primitives, see `BoundsTree::with_insertion_cost`.
+    pub fn with_insertion_cost(insertion_cost: InsertionCost<B>) -> Self {
+        PersistentBoundsTree {
+            root: None,
+            insertion_cost,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    pub fn insert(&self, new_bounds: B, data: T) -> (Self, u32) {
+        let ordering = self
+            .root
+            .as_ref()
+            .map_or(0, |root| Self::collect_max_ordering(root, new_bounds))
+            + 1;
+        let leaf = Arc::new(Node::Leaf {
+            bounds: new_bounds,
+            data,
+            order: ordering,
+        });
+        let root = match &self.root {
+            Some(root) => self.insert_leaf(root, leaf),
+            None => leaf,
+        };
+        #[cfg(feature = "tracing")]
+        tracing::trace!(order = ordering, "inserted primitive");
+        #[cfg(feature = "metrics")]
+        metrics::counter!("aabb.inserts").increment(1);
+        let tree = PersistentBoundsTree {
+            root: Some(root),
+            insertion_cost: self.insertion_cost,
+        };
+        (tree, ordering)
+    }
+
+    pub fn remove(&self, bounds: B, data: &T) -> Option<Self>
+    where
+        T: PartialEq,
+    {
+        let root = Self::remove_leaf(self.root.as_ref()?, bounds, data);
+        #[cfg(feature = "tracing")]
+        tracing::trace!(found = root.is_some(), "removed primitive");
+        #[cfg(feature = "metrics")]
+        if root.is_some() {
+            metrics::counter!("aabb.removals").increment(1);
+        }
+        Some(PersistentBoundsTree {
+            root: root?,
+            insertion_cost: self.insertion_cost,
+        })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Primitive<&T, B>> {
+        self.search(|_| true)
+    }
+
+    // Yields every primitive intersecting `bounds`.
+    pub fn query(&self, bounds: B) -> impl Iterator<Item = Primitive<&T, B>> {
+        self.search(move |node_bounds| node_bounds.intersects(bounds))
+    }
+
+    fn search(&self, mut descend: impl FnMut(B) -> bool) -> impl Iterator<Item = Primitive<&T, B>> {
+        let mut stack = Vec::new();
+        stack.extend(self.root.as_deref());
+        iter::from_fn(move || {
+            while let Some(node) = stack.pop() {
+                if !descend(node.bounds()) {
+                    continue;
+                }
+                match node {
+                    Node::Leaf {
+                        bounds,
+                        data,
+                        order,
+                    } => {
+                        return Some(Primitive {
+                            bounds: *bounds,
+                            data,
+                            order: *order,
+                        });
+                    }
+                    Node::Internal { left, right, .. } => {
+                        stack.push(left);
+                        stack.push(right);
+                    }
+                }
+            }
+            None
+        })
+    }
+
+    // Returns true if both versions share the same root, i.e. one was cloned from the other
+    // without any intervening mutation.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        match (&self.root, &other.root) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+
+    // Renders this version of the tree as a Graphviz DOT graph, see `BoundsTree::to_dot`.
+    pub fn to_dot(&self) -> String {
+        let mut dot = DotWriter::new();
+        let mut stack = Vec::new();
+        stack.extend(self.root.as_deref().map(|root| (root, 0, None)));
+        while let Some((node, depth, parent)) = stack.pop() {
+            let id = dot.node(
+                depth,
+                node.bounds(),
+                node.max_ordering(),
+                matches!(node, Node::Leaf { .. }),
+                parent,
+            );
+            if let Node::Internal { left, right, .. } = node {
+                stack.push((right, depth + 1, Some(id)));
+                stack.push((left, depth + 1, Some(id)));
+            }
+        }
+        dot.finish()
+    }
+
+    // Descends using the same heuristic as `BoundsTree`, then copies every internal node along
+    // the path from the bottom up.
+    fn insert_leaf(&self, root: &Arc<Node<T, B>>, leaf: Arc<Node<T, B>>) -> Arc<Node<T, B>> {
+        let new_bounds = leaf.bounds();
+        let mut path = Vec::new();
+        let mut node = root;
+        while let Node::Internal { left, right, .. } = node.as_ref() {
+            let went_left = self.insertion_cost.is_cheaper(
+                new_bounds.merge(left.bounds()),
+                new_bounds.merge(right.bounds()),
+            );
+            path.push((node, went_left));
+            node = if went_left { left } else { right };
+        }
+
+        let mut new_node = Node::internal(node.clone(), leaf);
+        for (parent, went_left) in path.into_iter().rev() {
+            new_node = parent.with_child(new_node, went_left);
+        }
+        new_node
+    }
+
+    // Returns `None` if the leaf wasn't found under `root`, otherwise the replacement for
+    // `root`, which is itself `None` if `root` was the removed leaf.
+    fn remove_leaf(root: &Arc<Node<T, B>>, bounds: B, data: &T) -> Option<Option<Arc<Node<T, B>>>>
+    where
+        T: PartialEq,
+    {
+        // A depth-first search that keeps the path to the current node, along with whether
+        // each node on it is the left child of its parent.
+        let mut path = Vec::new();
+        let mut stack = vec![(root, 0, false)];
+        let found = loop {
+            let (node, depth, is_left) = stack.pop()?;
+            path.truncate(depth);
+            path.push((node, is_left));
+            match node.as_ref() {
+                Node::Leaf {
+                    bounds: leaf_bounds,
+                    data: leaf_data,
+                    ..
+                } => {
+                    if *leaf_bounds == bounds && leaf_data == data {
+                        break path;
+                    }
+                }
+                Node::Internal {
+                    left,
+                    right,
+                    bounds: node_bounds,
+                    ..
+                } => {
+                    if node_bounds.contains(bounds) {
+                        stack.push((right, depth + 1, false));
+                        stack.push((left, depth + 1, true));
+                    }
+                }
+            }
+        };
+
+        // When a child disappears, its sibling takes the place of the parent.
+        let mut replacement = None;
+        for ix in (1..found.len()).rev() {
+            let (parent, _) = found[ix - 1];
+            let (_, is_left) = found[ix];
+            replacement = Some(match replacement {
+                Some(new_child) => parent.with_child(new_child, is_left),
+                None => parent.child(!is_left).clone(),
+            });
+        }
+        Some(replacement)
+    }
+
+    // Like `BoundsTree::max_order_in`, skipping subtrees whose maximum order doesn't exceed
+    // the maximum found so far.
+    fn collect_max_ordering(root: &Node<T, B>, bounds: B) -> u32 {
+        let mut max_ordering = 0;
+        let mut stack = vec![root];
+        while let Some(node) = stack.pop() {
+            if node.max_ordering() <= max_ordering || !bounds.intersects(node.bounds()) {
+                continue;
+            }
+            match node {
+                Node::Leaf { order, .. } => max_ordering = *order,
+                Node::Internal { left, right, .. } => {
+                    stack.push(left);
+                    stack.push(right);
+                }
+            }
+        }
+        max_ordering
+    }
+}
+
+// Old versions can hold degenerate paths that are as deep as the tree is long, so nodes that
+// aren't shared with other versions are dropped iteratively rather than recursively.
+impl<T, B> Drop for PersistentBoundsTree<T, B> {
+    fn drop(&mut self) {
+        let mut stack = Vec::new();
+        stack.extend(self.root.take());
+        while let Some(node) = stack.pop() {
+            if let Ok(Node::Internal { left, right, .. }) = Arc::try_unwrap(node) {
+                stack.push(left);
+                stack.push(right);
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Node<T, B> {
+    Leaf {
+        bounds: B,
+        data: T,
+        order: u32,
+    },
+    Internal {
+        left: Arc<Node<T, B>>,
+        right: Arc<Node<T, B>>,
+        bounds: B,
+        max_ordering: u32,
+    },
+}
+
+impl<T, B: BoundingBox> Node<T, B> {
+    fn internal(left: Arc<Node<T, B>>, right: Arc<Node<T, B>>) -> Arc<Node<T, B>> {
+        let bounds = left.bounds().merge(right.bounds());
+        let max_ordering =
+            <MaxOrdering as Augmentation<T, B>>::combine(left.max_ordering(), right.max_ordering());
+        Arc::new(Node::Internal {
+            left,
+            right,
+            bounds,
+            max_ordering,
+        })
+    }
+
+    // A copy of this internal node with one of its children replaced.
+    fn with_child(&self, new_child: Arc<Node<T, B>>, is_left: bool) -> Arc<Node<T, B>> {
+        if is_left {
+            Node::internal(new_child, self.child(false).clone())
+        } else {
+            Node::internal(self.child(true).clone(), new_child)
+        }
+    }
+
+    fn child(&self, left: bool) -> &Arc<Node<T, B>> {
+        match self {
+            Node::Internal { left: child, .. } if left => child,
+            Node::Internal { right: child, .. } => child,
+            Node::Leaf { .. } => unreachable!("leaves have no children"),
+        }
+    }
+
+    fn bounds(&self) -> B {
+        match self {
+            Node::Leaf { bounds, .. } => *bounds,
+            Node::Internal { bounds, .. } => *bounds,
+        }
+    }
+
+    fn max_ordering(&self) -> u32 {
+        match self {
+            Node::Leaf { order, .. } => *order,
+            Node::Internal { max_ordering, .. } => *max_ordering,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_fixtures::random_bounds, Point};
+
+    fn bounds(min_x: f32, min_y: f32, max_x: f32, max_y: f32) -> Bounds {
+        Bounds {
+            min: Point { x: min_x, y: min_y },
+            max: Point { x: max_x, y: max_y },
+        }
+    }
+
+    fn contents(tree: &PersistentBoundsTree<&'static str>) -> Vec<(&'static str, u32)> {
+        let mut contents = tree
+            .iter()
+            .map(|primitive| (*primitive.data, primitive.order))
+            .collect::<Vec<_>>();
+        contents.sort();
+        contents
+    }
+
+    #[test]
+    fn test_versions_are_independent() {
+        let v0 = PersistentBoundsTree::new();
+        let (v1, order) = v0.insert(bounds(0.0, 0.0, 10.0, 10.0), "a");
+        assert_eq!(order, 1);
+        let (v2, order) = v1.insert(bounds(5.0, 5.0, 15.0, 15.0), "b");
+        assert_eq!(order, 2);
+        let (v3, order) = v2.insert(bounds(20.0, 20.0, 30.0, 30.0), "c");
+        assert_eq!(order, 1);
+
+        assert!(v0.is_empty());
+        assert_eq!(contents(&v1), [("a", 1)]);
+        assert_eq!(contents(&v2), [("a", 1), ("b", 2)]);
+        assert_eq!(v3.query(bounds(8.0, 8.0, 9.0, 9.0)).count(), 2);
+        assert_eq!(v3.to_dot().matches("shape=box").count(), 3);
+        assert_eq!(contents(&v3), [("a", 1), ("b", 2), ("c", 1)]);
+
+        let v4 = v3.remove(bounds(5.0, 5.0, 15.0, 15.0), &"b").unwrap();
+        assert_eq!(contents(&v4), [("a", 1), ("c", 1)]);
+        assert_eq!(contents(&v3), [("a", 1), ("b", 2), ("c", 1)]);
+        assert!(v4.remove(bounds(5.0, 5.0, 15.0, 15.0), &"b").is_none());
+
+        // Removing "b" lowers the maximum order in the overlapping region again.
+        let (_, order) = v4.insert(bounds(8.0, 8.0, 12.0, 12.0), "d");
+        assert_eq!(order, 2);
+        let (_, order) = v3.insert(bounds(8.0, 8.0, 12.0, 12.0), "d");
+        assert_eq!(order, 3);
+    }
+
+    #[test]
+    fn test_degenerate_tree() {
+        // Coincident primitives make the tree about as deep as it's long, which used to
+        // overflow the stack when inserting, removing and dropping recursively.
+        std::thread::Builder::new()
+            .stack_size(256 * 1024)
+            .spawn(|| {
+                let mut versions = vec![PersistentBoundsTree::new()];
+                for id in 0..4_000 {
+                    let (next, order) = versions
+                        .last()
+                        .unwrap()
+                        .insert(bounds(0.0, 0.0, 1.0, 1.0), id);
+                    assert_eq!(order, id + 1);
+                    versions.push(next);
+                }
+                let tree = versions.pop().unwrap();
+                let tree = tree.remove(bounds(0.0, 0.0, 1.0, 1.0), &0).unwrap();
+                assert_eq!(tree.iter().count(), 3_999);
+                let (_, order) = tree.insert(bounds(0.5, 0.5, 2.0, 2.0), 4_000);
+                assert_eq!(order, 4_001);
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+    }
+
+    #[test]
+    fn test_matches_bounds_tree() {
+        use rand::SeedableRng;
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+        let mut tree = crate::BoundsTree::new();
+        let mut persistent = PersistentBoundsTree::new();
+        for id in 0..200 {
+            let new_bounds = random_bounds(&mut rng, 50.0);
+
+            let (next, order) = persistent.insert(new_bounds, id);
+            assert_eq!(order, tree.insert(new_bounds, id));
+            persistent = next;
+        }
+    }
+
+    #[test]
+    fn test_insertion_cost() {
+        use rand::{Rng, SeedableRng};
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(11);
+        let primitives = (0..200)
+            .map(|id| {
+                let min_x = rng.gen_range(0.0..1000.0);
+                let min_y = rng.gen_range(0.0..1000.0);
+                let max_x = min_x + rng.gen_range(0.0..300.0);
+                let max_y = min_y + rng.gen_range(0.0..3.0);
+                (bounds(min_x, min_y, max_x, max_y), id)
+            })
+            .collect::<Vec<_>>();
+        let build = |mut tree: PersistentBoundsTree<usize>, primitives: &[(Bounds, usize)]| {
+            for (bounds, id) in primitives {
+                tree = tree.insert(*bounds, *id).0;
+            }
+            tree
+        };
+
+        let half_perimeter = build(PersistentBoundsTree::new(), &primitives);
+        let area = build(
+            PersistentBoundsTree::with_insertion_cost(InsertionCost::AREA),
+            &primitives,
+        );
+        assert_ne!(half_perimeter.to_dot(), area.to_dot());
+
+        // Versions derived by removing keep measuring bounds the same way.
+        let extra = bounds(0.0, 0.0, 1.0, 1.0);
+        let derived = build(
+            PersistentBoundsTree::with_insertion_cost(InsertionCost::AREA),
+            &primitives[..1],
+        )
+        .insert(extra, 1000)
+        .0
+        .remove(extra, &1000)
+        .unwrap();
+        assert_eq!(build(derived, &primitives[1..]).to_dot(), area.to_dot());
+    }
+}
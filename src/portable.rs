@@ -0,0 +1,106 @@
+// A serialization format for `BoundsTree` that doesn't depend on its internal layout. Rather
+// than the hierarchy, it stores a flat list of `(bounds, data)` pairs and rebuilds the tree
+// when deserializing, so data written by one version of this crate can be read by another.
+//
+// Use it with `#[serde(with = "aabb::portable")]` on a `BoundsTree` field.
+//
+// Primitives are written sorted by order, so reinserting them in that sequence keeps every
+// primitive above the intersecting primitives it was above before. The orders themselves
+// aren't stored, so a loaded tree is ordered as if `BoundsTree::compact_orders` had been
+// called: gaps left by removals and orders raised by `insert_with_min_order` or
+// `insert_above` don't survive a round trip.
+//
+// Deserializing fails on invalid bounds, see `BoundsTree::try_insert`.
+use crate::{BoundingBox, BoundsTree};
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+
+pub fn serialize<T, B, S>(tree: &BoundsTree<T, B>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: Clone + Serialize,
+    B: BoundingBox + Serialize,
+    S: Serializer,
+{
+    let mut primitives = tree.iter().collect::<Vec<_>>();
+    primitives.sort_by_key(|primitive| primitive.order);
+    serializer.collect_seq(
+        primitives
+            .into_iter()
+            .map(|primitive| (primitive.bounds, primitive.data)),
+    )
+}
+
+pub fn deserialize<'de, T, B, D>(deserializer: D) -> Result<BoundsTree<T, B>, D::Error>
+where
+    T: Clone + Deserialize<'de>,
+    B: BoundingBox + Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    let primitives = Vec::<(B, T)>::deserialize(deserializer)?;
+    let mut tree = BoundsTree::new();
+    for (bounds, data) in primitives {
+        tree.try_insert(bounds, data).map_err(D::Error::custom)?;
+    }
+    Ok(tree)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_fixtures::random_bounds;
+    use crate::{Bounds, BoundsTree};
+    use rand::SeedableRng;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize)]
+    struct Scene {
+        #[serde(with = "crate::portable")]
+        tree: BoundsTree<usize>,
+    }
+
+    #[test]
+    fn test_portable_round_trip() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(5);
+        let mut tree = BoundsTree::new();
+        for id in 0..100 {
+            let bounds = random_bounds(&mut rng, 50.0);
+            tree.insert(bounds, id);
+        }
+
+        let primitives = |tree: &BoundsTree<usize>| {
+            let mut primitives = tree
+                .iter()
+                .map(|primitive| (*primitive.data, primitive.bounds, primitive.order))
+                .collect::<Vec<_>>();
+            primitives.sort_by_key(|(id, _, _)| *id);
+            primitives
+        };
+        let expected = primitives(&tree);
+        let json = serde_json::to_string(&Scene { tree }).unwrap();
+        let loaded: Scene = serde_json::from_str(&json).unwrap();
+        assert_eq!(primitives(&loaded.tree), expected);
+    }
+
+    #[test]
+    fn test_portable_compacts_orders() {
+        let mut tree = BoundsTree::new();
+        tree.insert_with_min_order(Bounds::from_xywh(0.0, 0.0, 10.0, 10.0), 0, 10);
+        tree.insert(Bounds::from_xywh(5.0, 5.0, 10.0, 10.0), 1);
+        tree.insert(Bounds::from_xywh(20.0, 0.0, 10.0, 10.0), 2);
+
+        let json = serde_json::to_string(&Scene { tree }).unwrap();
+        let loaded: Scene = serde_json::from_str(&json).unwrap();
+        let mut orders = loaded
+            .tree
+            .iter()
+            .map(|primitive| (*primitive.data, primitive.order))
+            .collect::<Vec<_>>();
+        orders.sort();
+        assert_eq!(orders, [(0, 1), (1, 2), (2, 1)]);
+    }
+
+    #[test]
+    fn test_portable_rejects_invalid_bounds() {
+        let json = r#"{"tree":[[{"min":{"x":10.0,"y":0.0},"max":{"x":0.0,"y":10.0}},0]]}"#;
+        let error = serde_json::from_str::<Scene>(json).err().unwrap();
+        assert!(error.to_string().contains("invalid bounds"), "{error}");
+    }
+}
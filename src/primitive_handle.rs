@@ -0,0 +1,343 @@
+use crate::{bounds_tree::Node, BoundingBox, BoundsTree, Error};
+
+// Identifies a primitive inserted with `BoundsTree::insert_with_handle`. Like `ProxyHandle`,
+// it's made up of a slot and the generation of that slot, so handles to removed primitives
+// are rejected even after their slot was reused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PrimitiveHandle {
+    index: usize,
+    generation: u32,
+}
+
+// Maps handles to the index of their leaf, which changes when other leaves are removed.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct HandleTable {
+    slots: Vec<HandleSlot>,
+    free_slots: Vec<usize>,
+    // The slot of every node that has a handle, indexed by node.
+    node_slots: Vec<Option<usize>>,
+}
+
+#[derive(Debug, Clone)]
+struct HandleSlot {
+    generation: u32,
+    node: Option<usize>,
+}
+
+impl HandleTable {
+    pub(crate) fn issue(&mut self, node: usize) -> PrimitiveHandle {
+        let index = self.free_slots.pop().unwrap_or_else(|| {
+            self.slots.push(HandleSlot {
+                generation: 0,
+                node: None,
+            });
+            self.slots.len() - 1
+        });
+        let handle = PrimitiveHandle {
+            index,
+            generation: self.slots[index].generation,
+        };
+        self.attach(handle, node);
+        handle
+    }
+
+    pub(crate) fn node(&self, handle: PrimitiveHandle) -> Option<usize> {
+        let slot = self.slots.get(handle.index)?;
+        if slot.generation != handle.generation {
+            return None;
+        }
+        slot.node
+    }
+
+    // Invalidates the handle and frees its slot for reuse.
+    pub(crate) fn release(&mut self, handle: PrimitiveHandle) {
+        self.detach(handle);
+        let slot = &mut self.slots[handle.index];
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free_slots.push(handle.index);
+    }
+
+    // Unlinks a handle from its leaf while keeping it valid, so it can be attached to the
+    // leaf's replacement.
+    pub(crate) fn detach(&mut self, handle: PrimitiveHandle) {
+        if let Some(node) = self.slots[handle.index].node.take() {
+            self.node_slots[node] = None;
+        }
+    }
+
+    pub(crate) fn attach(&mut self, handle: PrimitiveHandle, node: usize) {
+        self.slots[handle.index].node = Some(node);
+        if self.node_slots.len() <= node {
+            self.node_slots.resize(node + 1, None);
+        }
+        self.node_slots[node] = Some(handle.index);
+    }
+
+    // Invalidates every outstanding handle.
+    pub(crate) fn clear(&mut self) {
+        for (index, slot) in self.slots.iter_mut().enumerate() {
+            if slot.node.take().is_some() {
+                slot.generation = slot.generation.wrapping_add(1);
+                self.free_slots.push(index);
+            }
+        }
+        self.node_slots.clear();
+    }
+
+    pub(crate) fn node_moved(&mut self, from: usize, to: usize) {
+        let Some(index) = self.node_slots.get_mut(from).and_then(Option::take) else {
+            return;
+        };
+        self.slots[index].node = Some(to);
+        self.node_slots[to] = Some(index);
+    }
+}
+
+impl<T: Clone, B: BoundingBox> BoundsTree<T, B> {
+    // Like `insert`, but also returns a handle that can be used to remove the primitive later.
+    pub fn insert_with_handle(&mut self, new_bounds: B, data: T) -> (PrimitiveHandle, u32) {
+        let (leaf, order) = self.insert_leaf(new_bounds, data, 1);
+        (self.handles.issue(leaf), order)
+    }
+
+    // Inserts a primitive that's ordered above an existing one, whether or not they intersect,
+    // e.g. a tooltip or context menu anchored to an element. The new primitive gets a handle
+    // too, so other primitives can in turn be anchored to it.
+    pub fn insert_above(
+        &mut self,
+        anchor: PrimitiveHandle,
+        new_bounds: B,
+        data: T,
+    ) -> Result<(PrimitiveHandle, u32), Error> {
+        let anchor_order = self.order_of(anchor).ok_or(Error::StaleHandle {
+            index: anchor.index,
+            generation: anchor.generation,
+        })?;
+        // Anchors already at the maximum order leave no room above them, see
+        // `renormalize_orders`.
+        let min_order = anchor_order.checked_add(1).ok_or(Error::CapacityExceeded {
+            limit: u32::MAX as usize,
+            requested: anchor_order as usize + 1,
+        })?;
+        let (leaf, order) = self.insert_leaf(new_bounds, data, min_order);
+        Ok((self.handles.issue(leaf), order))
+    }
+
+    pub fn get(&self, handle: PrimitiveHandle) -> Option<&T> {
+        match self.node(self.handles.node(handle)?) {
+            Node::Leaf { data, .. } => Some(data),
+            Node::Internal { .. } => None,
+        }
+    }
+
+    // Gives access to a primitive's data. Its bounds and order can't change this way, so the
+    // tree stays valid.
+    pub fn get_mut(&mut self, handle: PrimitiveHandle) -> Option<&mut T> {
+        match self.node_mut(self.handles.node(handle)?) {
+            Node::Leaf { data, .. } => Some(data),
+            Node::Internal { .. } => None,
+        }
+    }
+
+    pub fn order_of(&self, handle: PrimitiveHandle) -> Option<u32> {
+        match self.node(self.handles.node(handle)?) {
+            Node::Leaf { summary: order, .. } => Some(*order),
+            Node::Internal { .. } => None,
+        }
+    }
+
+    // Moves a primitive and recomputes its order as if it was inserted last, so it ends up above
+    // every primitive it now intersects, including ones that used to be above it. Other
+    // primitives keep their orders. Returns the new order, or `None` if the primitive was
+    // removed.
+    pub fn update(&mut self, handle: PrimitiveHandle, new_bounds: B) -> Option<u32> {
+        let data = self.detach_leaf(handle)?;
+        let (leaf, order) = self.insert_leaf(new_bounds, data, 1);
+        self.handles.attach(handle, leaf);
+        Some(order)
+    }
+
+    // Removes a primitive, returning its data, or `None` if it was already removed. The orders
+    // of the remaining primitives are left untouched, so they stay consistent with each other.
+    pub fn remove(&mut self, handle: PrimitiveHandle) -> Option<T> {
+        let data = self.detach_leaf(handle)?;
+        self.handles.release(handle);
+        #[cfg(feature = "tracing")]
+        tracing::trace!(nodes = self.tree.nodes.len(), "removed primitive");
+        #[cfg(feature = "metrics")]
+        metrics::counter!("aabb.removals").increment(1);
+        Some(data)
+    }
+
+    // Removes a primitive's leaf, leaving its handle valid but unattached. The leaf's index may
+    // be taken over by a moved node during removal, so the handle is unlinked first and linked
+    // again if the removal fails.
+    fn detach_leaf(&mut self, handle: PrimitiveHandle) -> Option<T> {
+        let leaf = self.handles.node(handle)?;
+        self.handles.detach(handle);
+        let handles = &mut self.handles;
+        match self
+            .tree
+            .remove_leaf(leaf, |from, to| handles.node_moved(from, to))
+        {
+            Some((_, data, _)) => Some(data),
+            None => {
+                self.handles.attach(handle, leaf);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::{random_bounds, strip};
+    use crate::{Bounds, Point};
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn test_remove() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(11);
+        let mut tree = BoundsTree::new();
+        let mut live = Vec::new();
+        for step in 0..500 {
+            if !live.is_empty() && rng.gen_bool(0.4) {
+                let (handle, id) = live.swap_remove(rng.gen_range(0..live.len()));
+                assert_eq!(tree.remove(handle), Some(id));
+                assert_eq!(tree.remove(handle), None);
+            } else {
+                let bounds = random_bounds(&mut rng, 50.0);
+                let (handle, _) = tree.insert_with_handle(bounds, step);
+                live.push((handle, step));
+            }
+            assert_eq!(tree.validate(), Ok(()));
+            assert_eq!(tree.tree.nodes.len(), (2 * live.len()).saturating_sub(1));
+
+            let mut actual = tree
+                .iter()
+                .map(|primitive| *primitive.data)
+                .collect::<Vec<_>>();
+            actual.sort();
+            let mut expected = live.iter().map(|(_, id)| *id).collect::<Vec<_>>();
+            expected.sort();
+            assert_eq!(actual, expected);
+        }
+    }
+
+    #[test]
+    fn test_payload_access() {
+        let mut tree = BoundsTree::new();
+        let (a, _) = tree.insert_with_handle(strip(0.0, 10.0), "a".to_string());
+        let (b, order) = tree.insert_with_handle(strip(5.0, 10.0), "b".to_string());
+        assert_eq!(order, 2);
+        assert_eq!(tree.order_of(a), Some(1));
+        assert_eq!(tree.order_of(b), Some(2));
+
+        tree.get_mut(b).unwrap().push('!');
+        assert_eq!(tree.get(b).map(String::as_str), Some("b!"));
+
+        // Handles keep working when removals move other primitives around.
+        tree.remove(a);
+        assert_eq!(tree.get(a), None);
+        assert_eq!(tree.order_of(a), None);
+        assert_eq!(tree.get(b).map(String::as_str), Some("b!"));
+        assert_eq!(tree.order_of(b), Some(2));
+    }
+
+    #[test]
+    fn test_insert_above() {
+        let mut tree = BoundsTree::new();
+        tree.insert(strip(0.0, 10.0), "content");
+        let (button, order) = tree.insert_with_handle(strip(5.0, 10.0), "button");
+        assert_eq!(order, 2);
+
+        // The menu doesn't touch the button, but still goes above it.
+        let (menu, order) = tree
+            .insert_above(button, strip(40.0, 10.0), "menu")
+            .unwrap();
+        assert_eq!(order, 3);
+        let (_, order) = tree
+            .insert_above(menu, strip(60.0, 10.0), "submenu")
+            .unwrap();
+        assert_eq!(order, 4);
+
+        tree.remove(button);
+        assert!(matches!(
+            tree.insert_above(button, strip(40.0, 10.0), "tooltip"),
+            Err(Error::StaleHandle { .. })
+        ));
+
+        // Nothing fits above the maximum order.
+        tree.insert_with_min_order(strip(80.0, 10.0), "banner", u32::MAX - 1);
+        let (badge, order) = tree.insert_with_handle(strip(85.0, 10.0), "badge");
+        assert_eq!(order, u32::MAX);
+        assert!(matches!(
+            tree.insert_above(badge, strip(120.0, 10.0), "tooltip"),
+            Err(Error::CapacityExceeded { .. })
+        ));
+    }
+
+    #[test]
+    fn test_update() {
+        let mut tree = BoundsTree::new();
+        let (a, _) = tree.insert_with_handle(strip(0.0, 10.0), 'a');
+        let (b, _) = tree.insert_with_handle(strip(5.0, 10.0), 'b');
+        let (c, _) = tree.insert_with_handle(strip(30.0, 10.0), 'c');
+        assert_eq!(tree.order_of(b), Some(2));
+
+        // Moving `a` onto `c` puts it above `c`, while `b` stays above where `a` used to be.
+        assert_eq!(tree.update(a, strip(32.0, 10.0)), Some(2));
+        assert_eq!(tree.order_of(b), Some(2));
+        assert_eq!(tree.order_of(c), Some(1));
+        assert_eq!(tree.get(a), Some(&'a'));
+        assert_eq!(tree.update(b, strip(100.0, 10.0)), Some(1));
+        assert_eq!(tree.validate(), Ok(()));
+
+        tree.remove(c);
+        assert_eq!(tree.update(c, strip(0.0, 10.0)), None);
+        assert_eq!(tree.iter().count(), 2);
+    }
+
+    #[test]
+    fn test_clear() {
+        let bounds = Bounds {
+            min: Point { x: 0.0, y: 0.0 },
+            max: Point { x: 10.0, y: 10.0 },
+        };
+        let mut tree = BoundsTree::with_capacity(16);
+        let capacity = tree.tree.nodes.capacity();
+        assert!(capacity >= 31);
+        for frame in 0..3 {
+            tree.clear();
+            let (handle, order) = tree.insert_with_handle(bounds, frame);
+            assert_eq!(order, 1);
+            assert_eq!(tree.insert(bounds, frame), 2);
+            assert_eq!(tree.get(handle), Some(&frame));
+            tree.clear();
+            assert_eq!(tree.get(handle), None);
+            assert_eq!(tree.iter().count(), 0);
+        }
+        assert_eq!(tree.tree.nodes.capacity(), capacity);
+    }
+
+    #[test]
+    fn test_remove_next_to_nan_bounds() {
+        // Bounds with NaN coordinates aren't contained by any node, which used to keep
+        // removals from finding the parent of the node moved into the vacated slot.
+        let mut tree = BoundsTree::new();
+        let (first, _) = tree.insert_with_handle(Bounds::from_xywh(0.0, 0.0, 10.0, 10.0), 1);
+        let (second, _) = tree.insert_with_handle(Bounds::from_xywh(20.0, 0.0, 10.0, 10.0), 2);
+        let (nan, _) = tree.insert_with_handle(Bounds::from_xywh(f32::NAN, 0.0, 1.0, 1.0), 3);
+
+        assert_eq!(tree.remove(first), Some(1));
+        assert_eq!(tree.len(), 2);
+        assert!(tree
+            .update(second, Bounds::from_xywh(5.0, 0.0, 1.0, 1.0))
+            .is_some());
+        assert_eq!(tree.remove(nan), Some(3));
+        assert_eq!(tree.remove(second), Some(2));
+        assert!(tree.is_empty());
+    }
+}
@@ -0,0 +1,101 @@
+use crate::{Bounds, BoundsTree, Point};
+use numpy::{IntoPyArray, PyArray1, PyReadonlyArray1, PyReadonlyArray2};
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+// Python bindings for a tree of `f32` bounds identified by integer ids. Bulk methods take
+// and return NumPy arrays, with bounds as rows of `[min_x, min_y, max_x, max_y]`.
+#[pyclass(name = "BoundsTree", module = "aabb")]
+#[derive(Default)]
+pub struct PyBoundsTree {
+    tree: BoundsTree<u64>,
+}
+
+#[pymethods]
+impl PyBoundsTree {
+    #[new]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn __len__(&self) -> usize {
+        self.tree.len()
+    }
+
+    // Inserts a primitive and returns its order.
+    pub fn insert(&mut self, id: u64, min_x: f32, min_y: f32, max_x: f32, max_y: f32) -> u32 {
+        self.tree.insert(bounds(min_x, min_y, max_x, max_y), id)
+    }
+
+    // Inserts one primitive per row of `bounds`, returning their orders.
+    pub fn insert_many<'py>(
+        &mut self,
+        py: Python<'py>,
+        ids: PyReadonlyArray1<'py, u64>,
+        bounds: PyReadonlyArray2<'py, f32>,
+    ) -> PyResult<Bound<'py, PyArray1<u32>>> {
+        let ids = ids.as_array();
+        let bounds = bounds.as_array();
+        if bounds.shape() != [ids.len(), 4] {
+            return Err(PyValueError::new_err("expected an (n, 4) array of bounds"));
+        }
+        let orders = ids
+            .iter()
+            .zip(bounds.rows())
+            .map(|(id, row)| self.insert(*id, row[0], row[1], row[2], row[3]))
+            .collect::<Vec<_>>();
+        Ok(orders.into_pyarray(py))
+    }
+
+    // Returns the ids of all primitives intersecting the given bounds.
+    pub fn query<'py>(
+        &self,
+        py: Python<'py>,
+        min_x: f32,
+        min_y: f32,
+        max_x: f32,
+        max_y: f32,
+    ) -> Bound<'py, PyArray1<u64>> {
+        self.query_ids(bounds(min_x, min_y, max_x, max_y))
+            .into_pyarray(py)
+    }
+}
+
+impl PyBoundsTree {
+    fn query_ids(&self, bounds: Bounds) -> Vec<u64> {
+        self.tree
+            .tree
+            .search(|node_bounds, _| node_bounds.intersects(bounds))
+            .map(|(_, id)| *id)
+            .collect()
+    }
+}
+
+fn bounds(min_x: f32, min_y: f32, max_x: f32, max_y: f32) -> Bounds {
+    Bounds {
+        min: Point { x: min_x, y: min_y },
+        max: Point { x: max_x, y: max_y },
+    }
+}
+
+#[pymodule]
+fn aabb(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyBoundsTree>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_python_tree() {
+        let mut tree = PyBoundsTree::new();
+        assert_eq!(tree.insert(1, 0.0, 0.0, 10.0, 10.0), 1);
+        assert_eq!(tree.insert(2, 5.0, 5.0, 15.0, 15.0), 2);
+        assert_eq!(tree.insert(3, 20.0, 20.0, 30.0, 30.0), 1);
+        assert_eq!(tree.__len__(), 3);
+
+        let mut hits = tree.query_ids(bounds(8.0, 8.0, 9.0, 9.0));
+        hits.sort();
+        assert_eq!(hits, [1, 2]);
+    }
+}
@@ -0,0 +1,234 @@
+use crate::{Bounds, Point, SpatialIndex};
+
+// The number of items a quadrant holds before it's split.
+const QUADRANT_CAPACITY: usize = 8;
+const MAX_DEPTH: u32 = 16;
+
+// A region quadtree that recursively splits a fixed region into quadrants. Quadrant
+// boundaries are always at the same coordinates rather than fit to the items like the nodes of
+// a `BoundsTree`, which suits point-like items and deterministic bucketing, e.g. for interest
+// management in networked games. Items are stored in the smallest quadrant that fully contains
+// them, and items outside the region are stored in the root.
+#[derive(Debug, Clone)]
+pub struct Quadtree<T> {
+    quadrants: Vec<Quadrant>,
+    slots: Vec<QuadtreeSlot<T>>,
+    free_slots: Vec<usize>,
+}
+
+// Identifies an item of a `Quadtree`, rejecting handles to removed items like `GridHandle`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct QuadtreeHandle {
+    index: usize,
+    generation: u32,
+}
+
+#[derive(Debug, Clone)]
+struct Quadrant {
+    region: Bounds,
+    depth: u32,
+    // The index of the first of four contiguous child quadrants, once this one is split.
+    children: Option<usize>,
+    items: Vec<usize>,
+}
+
+#[derive(Debug, Clone)]
+struct QuadtreeSlot<T> {
+    generation: u32,
+    quadrant: usize,
+    item: Option<(Bounds, T)>,
+}
+
+impl<T> Quadtree<T> {
+    pub fn new(region: Bounds) -> Self {
+        Quadtree {
+            quadrants: vec![Quadrant {
+                region,
+                depth: 0,
+                children: None,
+                items: Vec::new(),
+            }],
+            slots: Vec::new(),
+            free_slots: Vec::new(),
+        }
+    }
+
+    pub fn region(&self) -> Bounds {
+        self.quadrants[0].region
+    }
+
+    // The child of a split quadrant that fully contains the bounds, if any.
+    fn child_containing(&self, quadrant: usize, bounds: Bounds) -> Option<usize> {
+        let children = self.quadrants[quadrant].children?;
+        (children..children + 4).find(|child| self.quadrants[*child].region.contains(bounds))
+    }
+
+    fn item(&self, index: usize) -> (Bounds, &T) {
+        let (bounds, data) = self.slots[index].item.as_ref().unwrap();
+        (*bounds, data)
+    }
+
+    fn split(&mut self, quadrant: usize) {
+        let Quadrant { region, depth, .. } = self.quadrants[quadrant];
+        let center = Point {
+            x: (region.min.x + region.max.x) / 2.0,
+            y: (region.min.y + region.max.y) / 2.0,
+        };
+        let children = self.quadrants.len();
+        for (min_x, max_x, min_y, max_y) in [
+            (region.min.x, center.x, region.min.y, center.y),
+            (center.x, region.max.x, region.min.y, center.y),
+            (region.min.x, center.x, center.y, region.max.y),
+            (center.x, region.max.x, center.y, region.max.y),
+        ] {
+            self.quadrants.push(Quadrant {
+                region: Bounds {
+                    min: Point { x: min_x, y: min_y },
+                    max: Point { x: max_x, y: max_y },
+                },
+                depth: depth + 1,
+                children: None,
+                items: Vec::new(),
+            });
+        }
+        self.quadrants[quadrant].children = Some(children);
+
+        let items = std::mem::take(&mut self.quadrants[quadrant].items);
+        for index in items {
+            let (bounds, _) = self.item(index);
+            let target = self.child_containing(quadrant, bounds).unwrap_or(quadrant);
+            self.quadrants[target].items.push(index);
+            self.slots[index].quadrant = target;
+        }
+    }
+}
+
+impl<T> SpatialIndex<T> for Quadtree<T> {
+    type Handle = QuadtreeHandle;
+
+    fn insert(&mut self, bounds: Bounds, data: T) -> QuadtreeHandle {
+        let mut quadrant = 0;
+        while let Some(child) = self.child_containing(quadrant, bounds) {
+            quadrant = child;
+        }
+
+        let index = self.free_slots.pop().unwrap_or_else(|| {
+            self.slots.push(QuadtreeSlot {
+                generation: 0,
+                quadrant,
+                item: None,
+            });
+            self.slots.len() - 1
+        });
+        let slot = &mut self.slots[index];
+        slot.quadrant = quadrant;
+        slot.item = Some((bounds, data));
+        let handle = QuadtreeHandle {
+            index,
+            generation: slot.generation,
+        };
+
+        let target = &mut self.quadrants[quadrant];
+        target.items.push(index);
+        if target.children.is_none()
+            && target.items.len() > QUADRANT_CAPACITY
+            && target.depth < MAX_DEPTH
+        {
+            self.split(quadrant);
+        }
+        handle
+    }
+
+    // Quadrants are never merged back together, so emptied parts of the region stay split.
+    fn remove(&mut self, handle: QuadtreeHandle) -> Option<T> {
+        let slot = self
+            .slots
+            .get_mut(handle.index)
+            .filter(|slot| slot.generation == handle.generation)?;
+        let (_, data) = slot.item.take()?;
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free_slots.push(handle.index);
+
+        let items = &mut self.quadrants[slot.quadrant].items;
+        let ix = items
+            .iter()
+            .position(|index| *index == handle.index)
+            .unwrap();
+        items.swap_remove(ix);
+        Some(data)
+    }
+
+    fn query<'a>(&'a self, bounds: Bounds) -> impl Iterator<Item = (Bounds, &'a T)>
+    where
+        T: 'a,
+    {
+        let mut results = Vec::new();
+        // The root is always visited, since it also holds the items outside of the region.
+        let mut stack = vec![0];
+        while let Some(quadrant) = stack.pop() {
+            let quadrant = &self.quadrants[quadrant];
+            for index in &quadrant.items {
+                let (item_bounds, data) = self.item(*index);
+                if item_bounds.intersects(bounds) {
+                    results.push((item_bounds, data));
+                }
+            }
+            if let Some(children) = quadrant.children {
+                stack.extend(
+                    (children..children + 4)
+                        .filter(|child| self.quadrants[*child].region.intersects(bounds)),
+                );
+            }
+        }
+        results.into_iter()
+    }
+
+    // Branch and bound search like `BoundsTree`'s, since items are never closer to the point
+    // than the quadrant containing them.
+    fn nearest(&self, point: Point) -> Option<(Bounds, &T)> {
+        let mut nearest = None;
+        let mut nearest_distance = f32::INFINITY;
+        let mut stack = vec![0];
+        while let Some(quadrant) = stack.pop() {
+            let quadrant = &self.quadrants[quadrant];
+            for index in &quadrant.items {
+                let (bounds, data) = self.item(*index);
+                let distance = bounds.distance_squared_to_point(point);
+                if distance < nearest_distance {
+                    nearest_distance = distance;
+                    nearest = Some((bounds, data));
+                }
+            }
+            if let Some(children) = quadrant.children {
+                let mut children = (children..children + 4)
+                    .map(|child| {
+                        let distance = self.quadrants[child]
+                            .region
+                            .distance_squared_to_point(point);
+                        (distance, child)
+                    })
+                    .filter(|(distance, _)| *distance < nearest_distance)
+                    .collect::<Vec<_>>();
+                // The closest child is pushed last, so it's visited first.
+                children.sort_by(|a, b| b.0.total_cmp(&a.0));
+                stack.extend(children.into_iter().map(|(_, child)| child));
+            }
+        }
+        nearest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spatial_index::tests::check_spatial_index;
+
+    #[test]
+    fn test_quadtree() {
+        // Part of the random items fall outside of the region.
+        check_spatial_index(Quadtree::new(Bounds {
+            min: Point { x: -80.0, y: -80.0 },
+            max: Point { x: 80.0, y: 80.0 },
+        }));
+    }
+}
@@ -0,0 +1,278 @@
+use crate::{bounds_tree::Node, BoundingBox, Bounds, BoundsTree, Primitive};
+
+// An intersection query that can be run incrementally, so that an expensive query can be
+// spread across multiple frames instead of blocking a single one.
+pub struct QueryCursor<'a, T, B = Bounds> {
+    tree: &'a BoundsTree<T, B>,
+    bounds: B,
+    stack: Vec<StackEntry>,
+    stats: QueryStats,
+    observer: Option<Box<dyn FnMut(TraversalEvent<B>) + 'a>>,
+}
+
+enum StackEntry {
+    Visit(usize),
+    // Only pushed when there's an observer, which is notified once the subtree is done.
+    Leave(usize),
+}
+
+// The steps of a query's traversal, reported to the observer registered with
+// `QueryCursor::with_observer`. Every visited node is entered, then either pruned or descended
+// into (leaves are neither), and left once its subtree has been traversed. Nodes are
+// identified by their index in the tree, which is stable until the tree is mutated.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TraversalEvent<B = Bounds> {
+    Enter { node: usize, bounds: B },
+    Pruned { node: usize },
+    Descended { node: usize },
+    Leave { node: usize },
+}
+
+// Counters describing how much of the tree a query had to traverse, for tuning margins,
+// heuristics, and rebuild policies.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct QueryStats {
+    // The number of nodes in the tree when the query started.
+    pub node_count: usize,
+    pub nodes_visited: usize,
+    // The number of leaves whose bounds were tested against the query.
+    pub leaves_tested: usize,
+    pub hits: usize,
+    // The number of internal nodes whose subtree was skipped because it was disjoint from
+    // the query.
+    pub subtrees_pruned: usize,
+}
+
+impl QueryStats {
+    // The fraction of the tree the query didn't need to visit.
+    pub fn pruned_fraction(&self) -> f64 {
+        if self.node_count == 0 {
+            0.0
+        } else {
+            1.0 - self.nodes_visited as f64 / self.node_count as f64
+        }
+    }
+}
+
+impl<T: Clone, B: BoundingBox> BoundsTree<T, B> {
+    pub fn query_cursor(&self, bounds: impl Into<B>) -> QueryCursor<'_, T, B> {
+        QueryCursor {
+            tree: self,
+            bounds: bounds.into(),
+            stack: self.tree.root.map(StackEntry::Visit).into_iter().collect(),
+            stats: QueryStats {
+                node_count: self.tree.nodes.len(),
+                ..Default::default()
+            },
+            observer: None,
+        }
+    }
+
+    // Runs a query to completion, returning the intersecting primitives along with
+    // statistics about the traversal.
+    pub fn query_with_stats(&self, bounds: impl Into<B>) -> (Vec<Primitive<&T, B>>, QueryStats) {
+        let mut cursor = self.query_cursor(bounds);
+        let mut results = Vec::new();
+        cursor.poll(usize::MAX, &mut results);
+        (results, cursor.stats())
+    }
+}
+
+impl<'a, T, B: BoundingBox> QueryCursor<'a, T, B> {
+    // Registers a callback that's invoked for every step of the traversal, e.g. to visualize
+    // or profile queries.
+    pub fn with_observer(mut self, observer: impl FnMut(TraversalEvent<B>) + 'a) -> Self {
+        self.observer = Some(Box::new(observer));
+        self
+    }
+
+    // Visits at most `budget` nodes, appending every primitive that intersects the query
+    // bounds to `results`. Returns true once the query has completed.
+    pub fn poll(&mut self, mut budget: usize, results: &mut Vec<Primitive<&'a T, B>>) -> bool {
+        #[cfg(feature = "tracing")]
+        let (initial_budget, initial_results) = (budget, results.len());
+        #[cfg(feature = "metrics")]
+        let was_done = self.is_done();
+        while budget > 0 {
+            let index = match self.stack.pop() {
+                Some(StackEntry::Visit(index)) => index,
+                Some(StackEntry::Leave(index)) => {
+                    self.notify(TraversalEvent::Leave { node: index });
+                    continue;
+                }
+                None => break,
+            };
+            budget -= 1;
+            self.stats.nodes_visited += 1;
+
+            let node = &self.tree.tree.nodes[index];
+            let intersects = node.bounds().intersects(self.bounds);
+            self.notify(TraversalEvent::Enter {
+                node: index,
+                bounds: node.bounds(),
+            });
+            match node {
+                Node::Leaf { .. } => self.stats.leaves_tested += 1,
+                Node::Internal { .. } if !intersects => {
+                    self.stats.subtrees_pruned += 1;
+                    self.notify(TraversalEvent::Pruned { node: index });
+                }
+                Node::Internal { .. } => self.notify(TraversalEvent::Descended { node: index }),
+            }
+            if !intersects {
+                self.notify(TraversalEvent::Leave { node: index });
+                continue;
+            }
+            match node {
+                Node::Leaf {
+                    bounds,
+                    data,
+                    summary: order,
+                } => {
+                    self.stats.hits += 1;
+                    results.push(Primitive {
+                        bounds: *bounds,
+                        data,
+                        order: *order,
+                    });
+                    self.notify(TraversalEvent::Leave { node: index });
+                }
+                Node::Internal { left, right, .. } => {
+                    if self.observer.is_some() {
+                        self.stack.push(StackEntry::Leave(index));
+                    }
+                    self.stack.push(StackEntry::Visit(*right));
+                    self.stack.push(StackEntry::Visit(*left));
+                }
+            }
+        }
+        #[cfg(feature = "tracing")]
+        tracing::trace!(
+            visited = initial_budget - budget,
+            results = results.len() - initial_results,
+            done = self.is_done(),
+            "polled query"
+        );
+        #[cfg(feature = "metrics")]
+        if !was_done && self.is_done() {
+            metrics::histogram!("aabb.query.nodes_visited").record(self.stats.nodes_visited as f64);
+        }
+        self.is_done()
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.stack.is_empty()
+    }
+
+    fn notify(&mut self, event: TraversalEvent<B>) {
+        if let Some(observer) = &mut self.observer {
+            observer(event);
+        }
+    }
+
+    // Statistics about the traversal so far.
+    pub fn stats(&self) -> QueryStats {
+        self.stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::{random_bounds, strip};
+    use crate::Point;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_query_across_multiple_polls() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(3);
+        let mut tree = BoundsTree::new();
+        let mut all_bounds = Vec::new();
+        for id in 0..200 {
+            let bounds = random_bounds(&mut rng, 50.0);
+            tree.insert(bounds, id);
+            all_bounds.push(bounds);
+        }
+
+        let query = Bounds {
+            min: Point { x: -50.0, y: -50.0 },
+            max: Point { x: 50.0, y: 50.0 },
+        };
+        let mut cursor = tree.query_cursor(query);
+        let mut results = Vec::new();
+        let mut polls = 1;
+        while !cursor.poll(8, &mut results) {
+            polls += 1;
+        }
+        assert!(polls > 1);
+        assert!(cursor.poll(8, &mut results));
+
+        let mut actual = results
+            .iter()
+            .map(|primitive| *primitive.data)
+            .collect::<Vec<_>>();
+        actual.sort();
+        let expected = (0..200)
+            .filter(|id| all_bounds[*id].intersects(query))
+            .collect::<Vec<_>>();
+        assert_eq!(actual, expected);
+
+        let (results, stats) = tree.query_with_stats(query);
+        assert_eq!(stats, cursor.stats());
+        assert_eq!(stats.hits, results.len());
+        assert_eq!(stats.node_count, 399);
+        assert!(stats.leaves_tested >= stats.hits);
+        assert!(stats.subtrees_pruned > 0);
+        assert!(stats.pruned_fraction() > 0.0);
+    }
+
+    #[test]
+    fn test_observer() {
+        let mut tree = BoundsTree::new();
+        for x in [0.0, 50.0, 100.0] {
+            tree.insert(strip(x, 10.0), x as u32);
+        }
+
+        let mut events = Vec::new();
+        let mut cursor = tree
+            .query_cursor(strip(45.0, 10.0))
+            .with_observer(|event| events.push(event));
+        let mut results = Vec::new();
+        while !cursor.poll(1, &mut results) {}
+        drop(cursor);
+        assert_eq!(results.len(), 1);
+
+        // Every entered node is left exactly once, after all of its descendants.
+        let mut open = Vec::new();
+        let (mut pruned, mut descended) = (0, 0);
+        for event in &events {
+            match *event {
+                TraversalEvent::Enter { node, .. } => open.push(node),
+                TraversalEvent::Leave { node } => assert_eq!(open.pop(), Some(node)),
+                TraversalEvent::Pruned { node } | TraversalEvent::Descended { node } => {
+                    assert_eq!(open.last(), Some(&node));
+                    if matches!(event, TraversalEvent::Pruned { .. }) {
+                        pruned += 1;
+                    } else {
+                        descended += 1;
+                    }
+                }
+            }
+        }
+        assert!(open.is_empty());
+        let stats = tree.query_with_stats(strip(45.0, 10.0)).1;
+        assert_eq!(pruned, stats.subtrees_pruned);
+        assert_eq!(
+            descended + pruned + stats.leaves_tested,
+            stats.nodes_visited
+        );
+    }
+
+    #[test]
+    fn test_empty_tree() {
+        let tree = BoundsTree::<()>::new();
+        let mut cursor = tree.query_cursor(Bounds::default());
+        assert!(cursor.is_done());
+        assert!(cursor.poll(0, &mut Vec::new()));
+    }
+}
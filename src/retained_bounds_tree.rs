@@ -0,0 +1,217 @@
+use crate::{BoundingBox, Bounds, BoundsTree, PrimitiveHandle};
+use std::{collections::HashMap, hash::Hash};
+
+// A `BoundsTree` that's rebuilt every frame from primitives identified by stable keys, e.g.
+// element ids, but only touches the primitives that changed since the previous frame. In a
+// mostly static UI, most primitives keep their place in the tree and their order.
+#[derive(Debug)]
+pub struct RetainedBoundsTree<K, T, B = Bounds> {
+    tree: BoundsTree<T, B>,
+    primitives: HashMap<K, RetainedPrimitive<B>>,
+    // The primitives already placed during `update_frame`, with their orders as data. Kept
+    // between frames to reuse its allocations.
+    placed: BoundsTree<u32, B>,
+    frame: u64,
+}
+
+#[derive(Debug)]
+struct RetainedPrimitive<B> {
+    handle: PrimitiveHandle,
+    bounds: B,
+    // The last frame that contained the primitive.
+    frame: u64,
+}
+
+// What happened to the primitives of a frame passed to `RetainedBoundsTree::update_frame`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FrameUpdate {
+    // Primitives whose bounds didn't change, which kept their order.
+    pub reused: usize,
+    // Primitives whose bounds didn't change, but that now follow an intersecting primitive
+    // they used to be below, so they were ordered above it.
+    pub reordered: usize,
+    pub moved: usize,
+    pub inserted: usize,
+    pub removed: usize,
+}
+
+impl<K: Eq + Hash, T: Clone, B: BoundingBox> Default for RetainedBoundsTree<K, T, B> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Eq + Hash, T: Clone, B: BoundingBox> RetainedBoundsTree<K, T, B> {
+    pub fn new() -> Self {
+        RetainedBoundsTree {
+            tree: BoundsTree::new(),
+            primitives: HashMap::new(),
+            placed: BoundsTree::new(),
+            frame: 0,
+        }
+    }
+
+    // Replaces the previous frame's primitives with this frame's, given in painter's order
+    // with unique keys. Primitives with unchanged bounds keep their order and only have their
+    // data replaced, unless an intersecting primitive that precedes them in this frame is
+    // ordered at or above them, e.g. because two overlapping elements swapped places. Those,
+    // along with moved and new primitives, are ordered above everything they intersect, like
+    // `BoundsTree::update` and `BoundsTree::insert` do. Primitives that are missing from this
+    // frame are removed.
+    pub fn update_frame(&mut self, primitives: impl IntoIterator<Item = (K, B, T)>) -> FrameUpdate {
+        self.frame += 1;
+        let frame = self.frame;
+        let primitives = primitives.into_iter().collect::<Vec<_>>();
+        for (key, ..) in &primitives {
+            if let Some(retained) = self.primitives.get_mut(key) {
+                retained.frame = frame;
+            }
+        }
+
+        // Remove stale primitives first, so they don't push up the orders of the new ones.
+        let mut update = FrameUpdate::default();
+        let tree = &mut self.tree;
+        self.primitives.retain(|_, retained| {
+            if retained.frame == frame {
+                true
+            } else {
+                tree.remove(retained.handle);
+                update.removed += 1;
+                false
+            }
+        });
+
+        self.placed.clear();
+        for (key, bounds, data) in primitives {
+            let order = match self.primitives.get_mut(&key) {
+                Some(retained) => {
+                    let order = if retained.bounds != bounds {
+                        retained.bounds = bounds;
+                        update.moved += 1;
+                        self.tree.update(retained.handle, bounds).unwrap()
+                    } else {
+                        let order = self.tree.order_of(retained.handle).unwrap();
+                        let preceding_order = self
+                            .placed
+                            .iter_intersecting(bounds)
+                            .map(|primitive| *primitive.data)
+                            .max();
+                        if preceding_order.is_some_and(|preceding| preceding >= order) {
+                            update.reordered += 1;
+                            self.tree.update(retained.handle, bounds).unwrap()
+                        } else {
+                            update.reused += 1;
+                            order
+                        }
+                    };
+                    *self.tree.get_mut(retained.handle).unwrap() = data;
+                    order
+                }
+                None => {
+                    let (handle, order) = self.tree.insert_with_handle(bounds, data);
+                    self.primitives.insert(
+                        key,
+                        RetainedPrimitive {
+                            handle,
+                            bounds,
+                            frame,
+                        },
+                    );
+                    update.inserted += 1;
+                    order
+                }
+            };
+            self.placed.insert(bounds, order);
+        }
+        update
+    }
+
+    pub fn tree(&self) -> &BoundsTree<T, B> {
+        &self.tree
+    }
+
+    pub fn order_of(&self, key: &K) -> Option<u32> {
+        self.tree.order_of(self.primitives.get(key)?.handle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::strip;
+
+    #[test]
+    fn test_update_frame() {
+        let mut tree = RetainedBoundsTree::new();
+        let update = tree.update_frame([
+            ('a', strip(0.0, 10.0), "a"),
+            ('b', strip(5.0, 10.0), "b"),
+            ('c', strip(30.0, 10.0), "c"),
+        ]);
+        assert_eq!(update.inserted, 3);
+        assert_eq!(tree.order_of(&'b'), Some(2));
+
+        let update = tree.update_frame([
+            ('a', strip(0.0, 10.0), "a"),
+            ('b', strip(5.0, 10.0), "b!"),
+            ('d', strip(32.0, 10.0), "d"),
+        ]);
+        assert_eq!(
+            update,
+            FrameUpdate {
+                reused: 2,
+                reordered: 0,
+                moved: 0,
+                inserted: 1,
+                removed: 1
+            }
+        );
+        assert_eq!(tree.order_of(&'b'), Some(2));
+        assert_eq!(tree.order_of(&'c'), None);
+        assert_eq!(tree.order_of(&'d'), Some(1));
+
+        let update = tree.update_frame([
+            ('a', strip(0.0, 10.0), "a"),
+            ('b', strip(5.0, 10.0), "b!"),
+            ('d', strip(8.0, 10.0), "d"),
+        ]);
+        assert_eq!((update.reused, update.moved), (2, 1));
+        assert_eq!(tree.order_of(&'d'), Some(3));
+
+        let mut data = tree
+            .tree()
+            .iter()
+            .map(|primitive| *primitive.data)
+            .collect::<Vec<_>>();
+        data.sort();
+        assert_eq!(data, ["a", "b!", "d"]);
+    }
+
+    #[test]
+    fn test_update_frame_swapped_primitives() {
+        let mut tree = RetainedBoundsTree::new();
+        tree.update_frame([
+            ('a', strip(0.0, 10.0), "a"),
+            ('b', strip(5.0, 10.0), "b"),
+            ('c', strip(30.0, 10.0), "c"),
+        ]);
+
+        // Swapping two overlapping elements puts `a` on top, even though neither moved.
+        let update = tree.update_frame([
+            ('b', strip(5.0, 10.0), "b"),
+            ('c', strip(30.0, 10.0), "c"),
+            ('a', strip(0.0, 10.0), "a"),
+        ]);
+        assert_eq!((update.reused, update.reordered), (2, 1));
+        assert_eq!(tree.order_of(&'b'), Some(2));
+        assert_eq!(tree.order_of(&'a'), Some(3));
+
+        // The same frame again doesn't reorder anything.
+        let update = tree.update_frame([
+            ('b', strip(5.0, 10.0), "b"),
+            ('c', strip(30.0, 10.0), "c"),
+            ('a', strip(0.0, 10.0), "a"),
+        ]);
+        assert_eq!((update.reused, update.reordered), (3, 0));
+    }
+}
@@ -0,0 +1,125 @@
+use crate::{Bounds, BoundsTree, Point, Scalar};
+use rstar::{RTreeNum, AABB};
+
+// Helpers for migrating from rstar. The query methods below mirror the names and semantics
+// of their `rstar::RTree` counterparts, where boxes that merely touch are considered to
+// intersect, unlike in the rest of this crate.
+impl<S: RTreeNum> From<AABB<[S; 2]>> for Bounds<S> {
+    fn from(envelope: AABB<[S; 2]>) -> Self {
+        let [min_x, min_y] = envelope.lower();
+        let [max_x, max_y] = envelope.upper();
+        Bounds {
+            min: Point { x: min_x, y: min_y },
+            max: Point { x: max_x, y: max_y },
+        }
+    }
+}
+
+impl<S: RTreeNum> From<Bounds<S>> for AABB<[S; 2]> {
+    fn from(bounds: Bounds<S>) -> Self {
+        AABB::from_corners([bounds.min.x, bounds.min.y], [bounds.max.x, bounds.max.y])
+    }
+}
+
+impl<T: Clone, S: Scalar + RTreeNum> BoundsTree<T, Bounds<S>> {
+    // Yields the data of every primitive fully contained in `envelope`.
+    pub fn locate_in_envelope(&self, envelope: &AABB<[S; 2]>) -> impl Iterator<Item = &T> {
+        let envelope = Bounds::from(*envelope);
+        self.tree
+            .search(move |bounds, _| overlaps_inclusive(bounds, envelope))
+            .filter(move |(bounds, _)| envelope.contains(*bounds))
+            .map(|(_, data)| data)
+    }
+
+    // Yields the data of every primitive intersecting or touching `envelope`.
+    pub fn locate_in_envelope_intersecting(
+        &self,
+        envelope: &AABB<[S; 2]>,
+    ) -> impl Iterator<Item = &T> {
+        let envelope = Bounds::from(*envelope);
+        self.tree
+            .search(move |bounds, _| overlaps_inclusive(bounds, envelope))
+            .map(|(_, data)| data)
+    }
+
+    // Returns the data of the primitive closest to `query_point`, measured from the point to
+    // the nearest point of each primitive's bounds.
+    pub fn nearest_neighbor(&self, query_point: &[S; 2]) -> Option<&T> {
+        let point = Point {
+            x: query_point[0],
+            y: query_point[1],
+        };
+        self.nearest_primitive(point).map(|(_, data)| data)
+    }
+}
+
+fn overlaps_inclusive<S: Scalar>(a: Bounds<S>, b: Bounds<S>) -> bool {
+    a.min.x <= b.max.x && a.max.x >= b.min.x && a.min.y <= b.max.y && a.max.y >= b.min.y
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rstar::{primitives::Rectangle as Rect, RTree, RTreeObject};
+
+    #[test]
+    fn test_matches_rstar() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+        let mut tree = BoundsTree::new();
+        let mut rects = Vec::new();
+        for id in 0..200 {
+            let min = [rng.gen_range(-100.0..100.0), rng.gen_range(-100.0..100.0)];
+            let max = [
+                min[0] + rng.gen_range(0.0..20.0),
+                min[1] + rng.gen_range(0.0..20.0),
+            ];
+            let rect = Rect::from_corners(min, max);
+            tree.insert(Bounds::from(rect.envelope()), id);
+            rects.push(rect);
+        }
+        let rtree = RTree::bulk_load(rects.clone());
+        let id_of = |rect: &Rect<[f64; 2]>| rects.iter().position(|other| other == rect).unwrap();
+
+        let envelope = AABB::from_corners([-30.0, -30.0], [30.0, 30.0]);
+        let sorted = |ids: Vec<usize>| {
+            let mut ids = ids;
+            ids.sort();
+            ids
+        };
+        assert_eq!(
+            sorted(tree.locate_in_envelope(&envelope).copied().collect()),
+            sorted(rtree.locate_in_envelope(&envelope).map(id_of).collect())
+        );
+        assert_eq!(
+            sorted(
+                tree.locate_in_envelope_intersecting(&envelope)
+                    .copied()
+                    .collect()
+            ),
+            sorted(
+                rtree
+                    .locate_in_envelope_intersecting(&envelope)
+                    .map(id_of)
+                    .collect()
+            )
+        );
+
+        for _ in 0..20 {
+            let point = [rng.gen_range(-120.0..120.0), rng.gen_range(-120.0..120.0)];
+            let expected = rtree.nearest_neighbor(&point).map(id_of).unwrap();
+            let actual = *tree.nearest_neighbor(&point).unwrap();
+            let distance = |id: usize| {
+                Bounds::from(rects[id].envelope()).distance_squared_to_point(Point {
+                    x: point[0],
+                    y: point[1],
+                })
+            };
+            assert_eq!(distance(actual), distance(expected));
+        }
+        assert_eq!(
+            BoundsTree::<(), Bounds<f64>>::new().nearest_neighbor(&[0.0, 0.0]),
+            None
+        );
+    }
+}
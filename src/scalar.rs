@@ -0,0 +1,210 @@
+use std::{
+    fmt,
+    ops::{Add, Sub},
+};
+
+// The coordinate type of points and bounds. Implemented for `f32` and `f64`, the latter
+// being useful for world coordinates far away from the origin, where `f32` loses precision.
+//
+// Custom float types can be used as well by implementing this trait. `Default` must be zero,
+// and `min`/`max` should return the smaller/larger of the two values like `f32::min` does.
+// `LOWEST` and `HIGHEST` are the smallest and largest representable values, i.e. the
+// infinities for float types, and are used to build `Bounds::EMPTY`.
+pub trait Scalar:
+    Copy + Default + PartialOrd + fmt::Debug + Add<Output = Self> + Sub<Output = Self>
+{
+    const LOWEST: Self;
+    const HIGHEST: Self;
+
+    fn min(self, other: Self) -> Self;
+    fn max(self, other: Self) -> Self;
+}
+
+impl Scalar for f32 {
+    const LOWEST: Self = f32::NEG_INFINITY;
+    const HIGHEST: Self = f32::INFINITY;
+
+    fn min(self, other: Self) -> Self {
+        f32::min(self, other)
+    }
+
+    fn max(self, other: Self) -> Self {
+        f32::max(self, other)
+    }
+}
+
+impl Scalar for f64 {
+    const LOWEST: Self = f64::NEG_INFINITY;
+    const HIGHEST: Self = f64::INFINITY;
+
+    fn min(self, other: Self) -> Self {
+        f64::min(self, other)
+    }
+
+    fn max(self, other: Self) -> Self {
+        f64::max(self, other)
+    }
+}
+
+// Integer coordinates are compared exactly, which makes them a good fit for tile maps and
+// pixel-aligned layouts. Insertion costs are computed in the coordinate type itself, so the
+// width plus height of the bounds of the whole tree must not overflow it.
+macro_rules! impl_integer_scalar {
+    ($($ty:ty),*) => {
+        $(
+            impl Scalar for $ty {
+                const LOWEST: Self = <$ty>::MIN;
+                const HIGHEST: Self = <$ty>::MAX;
+
+                fn min(self, other: Self) -> Self {
+                    Ord::min(self, other)
+                }
+
+                fn max(self, other: Self) -> Self {
+                    Ord::max(self, other)
+                }
+            }
+        )*
+    };
+}
+
+impl_integer_scalar!(i32, i64);
+
+// Fixed-point coordinates from the `fixed` crate (e.g. `I32F32`), for lockstep simulations
+// where every peer must build exactly the same tree and floating point isn't an option.
+#[cfg(feature = "fixed")]
+impl<Frac: fixed::types::extra::LeEqU32> Scalar for fixed::FixedI32<Frac> {
+    const LOWEST: Self = Self::MIN;
+    const HIGHEST: Self = Self::MAX;
+
+    fn min(self, other: Self) -> Self {
+        Ord::min(self, other)
+    }
+
+    fn max(self, other: Self) -> Self {
+        Ord::max(self, other)
+    }
+}
+
+#[cfg(feature = "fixed")]
+impl<Frac: fixed::types::extra::LeEqU64> Scalar for fixed::FixedI64<Frac> {
+    const LOWEST: Self = Self::MIN;
+    const HIGHEST: Self = Self::MAX;
+
+    fn min(self, other: Self) -> Self {
+        Ord::min(self, other)
+    }
+
+    fn max(self, other: Self) -> Self {
+        Ord::max(self, other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Bounds, BoundsTree, Point, Scalar};
+    use std::ops::{Add, Sub};
+
+    // A stand-in for a user-defined float type, e.g. a half-precision or software float.
+    #[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+    struct CustomFloat(f32);
+
+    impl Add for CustomFloat {
+        type Output = Self;
+
+        fn add(self, other: Self) -> Self {
+            CustomFloat(self.0 + other.0)
+        }
+    }
+
+    impl Sub for CustomFloat {
+        type Output = Self;
+
+        fn sub(self, other: Self) -> Self {
+            CustomFloat(self.0 - other.0)
+        }
+    }
+
+    impl Scalar for CustomFloat {
+        const LOWEST: Self = CustomFloat(f32::NEG_INFINITY);
+        const HIGHEST: Self = CustomFloat(f32::INFINITY);
+
+        fn min(self, other: Self) -> Self {
+            CustomFloat(self.0.min(other.0))
+        }
+
+        fn max(self, other: Self) -> Self {
+            CustomFloat(self.0.max(other.0))
+        }
+    }
+
+    #[test]
+    fn test_custom_scalar() {
+        let bounds = |min: f32, max: f32| Bounds {
+            min: Point {
+                x: CustomFloat(min),
+                y: CustomFloat(min),
+            },
+            max: Point {
+                x: CustomFloat(max),
+                y: CustomFloat(max),
+            },
+        };
+
+        let mut tree = BoundsTree::new();
+        assert_eq!(tree.insert(bounds(0.0, 10.0), 1), 1);
+        assert_eq!(tree.insert(bounds(5.0, 15.0), 2), 2);
+        assert_eq!(tree.insert(bounds(15.0, 20.0), 3), 1);
+        assert_eq!(tree.insert(bounds(0.0, 20.0), 4), 3);
+    }
+
+    #[test]
+    fn test_integer_scalar() {
+        let tile = |x: i32, y: i32| Bounds {
+            min: Point { x, y },
+            max: Point { x: x + 1, y: y + 1 },
+        };
+
+        // Tiles sharing an edge never intersect, no matter how far from the origin they are.
+        let mut tree = BoundsTree::new();
+        for x in 0..16 {
+            for y in 0..16 {
+                assert_eq!(tree.insert(tile((1 << 24) + x, y), (x, y)), 1);
+            }
+        }
+        assert_eq!(tree.insert(tile((1 << 24) + 5, 5), (5, 5)), 2);
+
+        let mut tree = BoundsTree::<_, Bounds<i64>>::new();
+        let bounds = Bounds {
+            min: Point { x: 1 << 40, y: 0 },
+            max: Point {
+                x: (1 << 40) + 1,
+                y: 1,
+            },
+        };
+        assert_eq!(tree.insert(bounds, ()), 1);
+        assert_eq!(tree.insert(bounds, ()), 2);
+    }
+
+    #[cfg(feature = "fixed")]
+    #[test]
+    fn test_fixed_point_scalar() {
+        use fixed::types::I32F32;
+
+        let bounds = |min: f64, max: f64| Bounds {
+            min: Point {
+                x: I32F32::from_num(min),
+                y: I32F32::from_num(min),
+            },
+            max: Point {
+                x: I32F32::from_num(max),
+                y: I32F32::from_num(max),
+            },
+        };
+
+        let mut tree = BoundsTree::new();
+        assert_eq!(tree.insert(bounds(0.0, 0.5), 1), 1);
+        assert_eq!(tree.insert(bounds(0.25, 0.75), 2), 2);
+        assert_eq!(tree.insert(bounds(0.75, 1.0), 3), 1);
+    }
+}
@@ -0,0 +1,60 @@
+use crate::{BoundingBox, BoundsTree};
+use std::fmt;
+
+impl<T: Clone + fmt::Debug, B: BoundingBox> BoundsTree<T, B> {
+    // A canonical, human-readable listing of the tree's contents for snapshot tests (e.g.
+    // with insta). There's one line per primitive with its order, bounds, and data, sorted by
+    // order and then by text, so the result only depends on the primitives and their orders,
+    // not on the shape of the tree.
+    pub fn to_snapshot(&self) -> String {
+        let mut lines = self
+            .iter()
+            .map(|primitive| {
+                (
+                    primitive.order,
+                    format!(
+                        "{}: {:?} => {:?}",
+                        primitive.order, primitive.bounds, primitive.data
+                    ),
+                )
+            })
+            .collect::<Vec<_>>();
+        lines.sort();
+
+        let mut snapshot = String::new();
+        for (_, line) in lines {
+            snapshot.push_str(&line);
+            snapshot.push('\n');
+        }
+        snapshot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{test_fixtures::strip, BoundsTree};
+
+    #[test]
+    fn test_snapshot_is_independent_of_shape() {
+        let mut a = BoundsTree::new();
+        let mut b = BoundsTree::new();
+        for x in [0.0, 20.0, 40.0, 5.0] {
+            a.insert(strip(x, 10.0), x as u32);
+        }
+        for x in [40.0, 20.0, 0.0, 5.0] {
+            b.insert(strip(x, 10.0), x as u32);
+        }
+
+        let snapshot = a.to_snapshot();
+        assert_eq!(
+            snapshot,
+            "\
+1: Bounds { min: (x: 0.00, y: 0.00), max: (x: 10.00, y: 10.00) } => 0
+1: Bounds { min: (x: 20.00, y: 0.00), max: (x: 30.00, y: 10.00) } => 20
+1: Bounds { min: (x: 40.00, y: 0.00), max: (x: 50.00, y: 10.00) } => 40
+2: Bounds { min: (x: 5.00, y: 0.00), max: (x: 15.00, y: 10.00) } => 5
+"
+        );
+        assert_eq!(snapshot, b.to_snapshot());
+    }
+}
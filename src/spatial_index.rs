@@ -0,0 +1,133 @@
+use crate::{bounds_tree::Node, Bounds, BoundsTree, Point, PrimitiveHandle, Scalar};
+use std::ops::Mul;
+
+// The operations shared by the crate's 2D spatial indices, so code written against this trait
+// can switch between them, e.g. to benchmark a grid against a tree for its workload. Like
+// everywhere else in the crate, bounds that merely touch don't intersect.
+pub trait SpatialIndex<T> {
+    type Handle: Copy;
+
+    fn insert(&mut self, bounds: Bounds, data: T) -> Self::Handle;
+
+    // Removes an item, returning its data, or `None` if it was already removed.
+    fn remove(&mut self, handle: Self::Handle) -> Option<T>;
+
+    // Yields every item intersecting `bounds`, in no particular order.
+    fn query<'a>(&'a self, bounds: Bounds) -> impl Iterator<Item = (Bounds, &'a T)>
+    where
+        T: 'a;
+
+    // Returns the item closest to `point`, measured from the point to the nearest point of
+    // each item's bounds. Ties are broken arbitrarily.
+    fn nearest(&self, point: Point) -> Option<(Bounds, &T)>;
+}
+
+impl<T: Clone> SpatialIndex<T> for BoundsTree<T> {
+    type Handle = PrimitiveHandle;
+
+    fn insert(&mut self, bounds: Bounds, data: T) -> PrimitiveHandle {
+        self.insert_with_handle(bounds, data).0
+    }
+
+    fn remove(&mut self, handle: PrimitiveHandle) -> Option<T> {
+        BoundsTree::remove(self, handle)
+    }
+
+    fn query<'a>(&'a self, bounds: Bounds) -> impl Iterator<Item = (Bounds, &'a T)>
+    where
+        T: 'a,
+    {
+        self.iter_intersecting(bounds)
+            .map(|primitive| (primitive.bounds, primitive.data))
+    }
+
+    fn nearest(&self, point: Point) -> Option<(Bounds, &T)> {
+        self.nearest_primitive(point)
+    }
+}
+
+impl<T: Clone, S: Scalar + Mul<Output = S>> BoundsTree<T, Bounds<S>> {
+    // Returns the primitive closest to `point`, measured from the point to the nearest point
+    // of its bounds. This is a branch and bound search, visiting the closer child first so
+    // that the other one can usually be pruned.
+    pub(crate) fn nearest_primitive(&self, point: Point<S>) -> Option<(Bounds<S>, &T)> {
+        let mut nearest: Option<(S, Bounds<S>, &T)> = None;
+        let mut stack = Vec::new();
+        stack.extend(self.tree.root);
+        while let Some(index) = stack.pop() {
+            let node = self.node(index);
+            let distance = node.bounds().distance_squared_to_point(point);
+            if nearest.is_some_and(|(nearest_distance, ..)| distance >= nearest_distance) {
+                continue;
+            }
+            match node {
+                Node::Leaf { bounds, data, .. } => nearest = Some((distance, *bounds, data)),
+                Node::Internal { left, right, .. } => {
+                    let left_distance = self.node(*left).bounds().distance_squared_to_point(point);
+                    let right_distance =
+                        self.node(*right).bounds().distance_squared_to_point(point);
+                    // The child pushed last is visited first.
+                    if left_distance < right_distance {
+                        stack.push(*right);
+                        stack.push(*left);
+                    } else {
+                        stack.push(*left);
+                        stack.push(*right);
+                    }
+                }
+            }
+        }
+        nearest.map(|(_, bounds, data)| (bounds, data))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::test_fixtures::random_bounds;
+    use rand::{Rng, SeedableRng};
+
+    // Checks an empty index against a brute force search through random insertions,
+    // removals, queries, and nearest neighbor lookups.
+    pub(crate) fn check_spatial_index(mut index: impl SpatialIndex<usize>) {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(13);
+        assert!(index.nearest(Point::default()).is_none());
+        let mut live = Vec::new();
+        for id in 0..300 {
+            if !live.is_empty() && rng.gen_bool(0.3) {
+                let (handle, _, id) = live.swap_remove(rng.gen_range(0..live.len()));
+                assert_eq!(index.remove(handle), Some(id));
+                assert_eq!(index.remove(handle), None);
+            } else {
+                let bounds = random_bounds(&mut rng, 20.0);
+                live.push((index.insert(bounds, id), bounds, id));
+            }
+
+            let query = random_bounds(&mut rng, 20.0);
+            let mut actual = index.query(query).map(|(_, id)| *id).collect::<Vec<_>>();
+            actual.sort();
+            let mut expected = live
+                .iter()
+                .filter(|(_, bounds, _)| bounds.intersects(query))
+                .map(|(_, _, id)| *id)
+                .collect::<Vec<_>>();
+            expected.sort();
+            assert_eq!(actual, expected);
+
+            let point = random_bounds(&mut rng, 20.0).min;
+            let actual = index
+                .nearest(point)
+                .map(|(bounds, _)| bounds.distance_squared_to_point(point));
+            let expected = live
+                .iter()
+                .map(|(_, bounds, _)| bounds.distance_squared_to_point(point))
+                .min_by(f32::total_cmp);
+            assert_eq!(actual, expected);
+        }
+    }
+
+    #[test]
+    fn test_bounds_tree() {
+        check_spatial_index(BoundsTree::new());
+    }
+}
@@ -0,0 +1,238 @@
+use crate::{BoundingBox, Bounds, BoundsTree, Point, Scalar};
+
+// An item of a tree that models CSS stacking contexts. A nested context is ordered within its
+// parent like any other primitive, and all of its primitives are painted at that order, so
+// they can't interleave with the parent's primitives no matter their orders within the context.
+#[derive(Debug, Clone)]
+pub enum StackingItem<T, B = Bounds> {
+    Primitive(T),
+    Context(Box<BoundsTree<StackingItem<T, B>, B>>),
+    // A context whose primitives are only visible, and can only be hit, within `clip`.
+    Clipped {
+        clip: B,
+        context: Box<BoundsTree<StackingItem<T, B>, B>>,
+    },
+}
+
+// A primitive of a tree of stacking contexts, along with its order in every context from the
+// outermost one down to its own. Comparing `orders` lexicographically gives painter's order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StackedPrimitive<'a, T, B = Bounds> {
+    pub data: &'a T,
+    pub bounds: B,
+    pub orders: Vec<u32>,
+}
+
+// The steps of drawing a tree of stacking contexts, as yielded by `iter_commands`. Every
+// `PushClip` is balanced by a `PopClip`, and clips nest, so a renderer can maintain a stack of
+// scissor rectangles by intersecting each pushed clip with the one below it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DrawCommand<'a, T, B = Bounds> {
+    PushClip(B),
+    Draw(StackedPrimitive<'a, T, B>),
+    PopClip,
+}
+
+impl<T: Clone, B: BoundingBox> BoundsTree<StackingItem<T, B>, B> {
+    // Nests a tree as a stacking context covering all of its primitives. Returns the order of
+    // the context, or `None` if the tree is empty, in which case nothing is inserted.
+    pub fn insert_context(&mut self, context: BoundsTree<StackingItem<T, B>, B>) -> Option<u32> {
+        let bounds = context.root_bounds()?;
+        Some(self.insert(bounds, StackingItem::Context(Box::new(context))))
+    }
+
+    // Nests a tree as a stacking context that's clipped to `clip`. The context is ordered as if
+    // it covered the whole clip, regardless of the bounds of its primitives.
+    pub fn insert_clipped(&mut self, clip: B, context: BoundsTree<StackingItem<T, B>, B>) -> u32 {
+        self.insert(
+            clip,
+            StackingItem::Clipped {
+                clip,
+                context: Box::new(context),
+            },
+        )
+    }
+
+    // Yields the primitives of this context and all nested ones in painter's order.
+    pub fn iter_stacked(&self) -> impl Iterator<Item = StackedPrimitive<'_, T, B>> {
+        let mut primitives = Vec::new();
+        self.collect_stacked(&mut Vec::new(), &mut primitives);
+        primitives.into_iter()
+    }
+
+    fn collect_stacked<'a>(
+        &'a self,
+        orders: &mut Vec<u32>,
+        primitives: &mut Vec<StackedPrimitive<'a, T, B>>,
+    ) {
+        for primitive in self.iter_in_order() {
+            orders.push(primitive.order);
+            match primitive.data {
+                StackingItem::Primitive(data) => primitives.push(StackedPrimitive {
+                    data,
+                    bounds: primitive.bounds,
+                    orders: orders.clone(),
+                }),
+                StackingItem::Context(context) | StackingItem::Clipped { context, .. } => {
+                    context.collect_stacked(orders, primitives)
+                }
+            }
+            orders.pop();
+        }
+    }
+
+    // Yields the commands to draw this context and all nested ones in painter's order,
+    // bracketing the primitives of every clipped context with clip commands.
+    pub fn iter_commands(&self) -> impl Iterator<Item = DrawCommand<'_, T, B>> {
+        let mut commands = Vec::new();
+        self.collect_commands(&mut Vec::new(), &mut commands);
+        commands.into_iter()
+    }
+
+    fn collect_commands<'a>(
+        &'a self,
+        orders: &mut Vec<u32>,
+        commands: &mut Vec<DrawCommand<'a, T, B>>,
+    ) {
+        for primitive in self.iter_in_order() {
+            orders.push(primitive.order);
+            match primitive.data {
+                StackingItem::Primitive(data) => {
+                    commands.push(DrawCommand::Draw(StackedPrimitive {
+                        data,
+                        bounds: primitive.bounds,
+                        orders: orders.clone(),
+                    }))
+                }
+                StackingItem::Context(context) => context.collect_commands(orders, commands),
+                StackingItem::Clipped { clip, context } => {
+                    commands.push(DrawCommand::PushClip(*clip));
+                    context.collect_commands(orders, commands);
+                    commands.push(DrawCommand::PopClip);
+                }
+            }
+            orders.pop();
+        }
+    }
+}
+
+impl<T: Clone, S: Scalar> BoundsTree<StackingItem<T, Bounds<S>>, Bounds<S>> {
+    // Returns the topmost primitive containing the point. Contexts are searched from the top
+    // down, and a context without any primitive at the point lets the search continue below it.
+    // Clipped contexts are only searched when the point is within their clip.
+    pub fn hit_test_stacked(&self, point: Point<S>) -> Option<StackedPrimitive<'_, T, Bounds<S>>> {
+        self.hit_test_stacked_with(point, &mut Vec::new())
+    }
+
+    fn hit_test_stacked_with(
+        &self,
+        point: Point<S>,
+        orders: &mut Vec<u32>,
+    ) -> Option<StackedPrimitive<'_, T, Bounds<S>>> {
+        for hit in self.hit_test_all(point) {
+            orders.push(hit.order);
+            match hit.data {
+                StackingItem::Primitive(data) => {
+                    return Some(StackedPrimitive {
+                        data,
+                        bounds: hit.bounds,
+                        orders: orders.clone(),
+                    })
+                }
+                // Clipped contexts were inserted with their clip as bounds, so they're only hit
+                // within their clip.
+                StackingItem::Context(context) | StackingItem::Clipped { context, .. } => {
+                    if let Some(hit) = context.hit_test_stacked_with(point, orders) {
+                        return Some(hit);
+                    }
+                }
+            }
+            orders.pop();
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::strip;
+
+    #[test]
+    fn test_stacking_contexts() {
+        let mut context = BoundsTree::new();
+        context.insert(strip(10.0, 10.0), StackingItem::Primitive("card"));
+        context.insert(strip(15.0, 10.0), StackingItem::Primitive("badge"));
+
+        let mut root = BoundsTree::new();
+        root.insert(strip(0.0, 100.0), StackingItem::Primitive("background"));
+        assert_eq!(root.insert_context(context), Some(2));
+        assert_eq!(root.insert_context(BoundsTree::new()), None);
+        root.insert(strip(18.0, 12.0), StackingItem::Primitive("popup"));
+
+        let stacked = root
+            .iter_stacked()
+            .map(|primitive| (*primitive.data, primitive.orders))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            stacked,
+            [
+                ("background", vec![1]),
+                ("card", vec![2, 1]),
+                ("badge", vec![2, 2]),
+                ("popup", vec![3]),
+            ]
+        );
+
+        let hit = |x| {
+            root.hit_test_stacked(Point { x, y: 5.0 })
+                .map(|hit| *hit.data)
+        };
+        assert_eq!(hit(5.0), Some("background"));
+        assert_eq!(hit(12.0), Some("card"));
+        assert_eq!(hit(16.0), Some("badge"));
+        assert_eq!(hit(22.0), Some("popup"));
+        assert_eq!(hit(200.0), None);
+    }
+
+    #[test]
+    fn test_clipped_contexts() {
+        let mut list = BoundsTree::new();
+        list.insert(strip(0.0, 20.0), StackingItem::Primitive("first row"));
+        list.insert(strip(18.0, 20.0), StackingItem::Primitive("second row"));
+
+        let mut root = BoundsTree::new();
+        root.insert(strip(0.0, 100.0), StackingItem::Primitive("background"));
+        assert_eq!(root.insert_clipped(strip(0.0, 30.0), list), 2);
+        root.insert(strip(28.0, 4.0), StackingItem::Primitive("button"));
+
+        let commands = root
+            .iter_commands()
+            .map(|command| match command {
+                DrawCommand::PushClip(clip) => format!("push {}", clip.max.x),
+                DrawCommand::Draw(primitive) => primitive.data.to_string(),
+                DrawCommand::PopClip => "pop".to_string(),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            commands,
+            [
+                "background",
+                "push 30",
+                "first row",
+                "second row",
+                "pop",
+                "button"
+            ]
+        );
+
+        let hit = |x| {
+            root.hit_test_stacked(Point { x, y: 5.0 })
+                .map(|hit| *hit.data)
+        };
+        assert_eq!(hit(25.0), Some("second row"));
+        // The rest of the second row is clipped away, revealing the background.
+        assert_eq!(hit(35.0), Some("background"));
+        assert_eq!(hit(29.0), Some("button"));
+    }
+}
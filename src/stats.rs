@@ -0,0 +1,109 @@
+use crate::{bounds_tree::Node, BoundingBox, BoundsTree};
+
+// A summary of the shape of a tree, for deciding when to rebuild it or comparing insertion
+// heuristics.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct TreeStats {
+    pub leaf_count: usize,
+    pub node_count: usize,
+    // The number of nodes on the longest path from the root to a leaf.
+    pub height: usize,
+    // The average number of edges between the root and each leaf.
+    pub average_leaf_depth: f64,
+    // The total cost of all internal nodes relative to the root's, which is proportional to
+    // the expected number of nodes a random query visits. Lower is better.
+    pub sah_cost: f64,
+    // The fraction of internal nodes whose children overlap, both of which must be visited
+    // by any query hitting the overlap.
+    pub overlap_factor: f64,
+}
+
+impl<T: Clone, B: BoundingBox> BoundsTree<T, B>
+where
+    B::Cost: Into<f64>,
+{
+    pub fn stats(&self) -> TreeStats {
+        let mut stats = TreeStats {
+            node_count: self.tree.nodes.len(),
+            ..Default::default()
+        };
+        let Some(root) = self.tree.root else {
+            return stats;
+        };
+
+        let mut total_leaf_depth = 0;
+        let mut internal_cost = 0.0;
+        let mut internal_count = 0;
+        let mut overlap_count = 0;
+        let mut stack = vec![(root, 0)];
+        while let Some((index, depth)) = stack.pop() {
+            stats.height = stats.height.max(depth + 1);
+            match self.node(index) {
+                Node::Leaf { .. } => {
+                    stats.leaf_count += 1;
+                    total_leaf_depth += depth;
+                }
+                Node::Internal {
+                    left,
+                    right,
+                    bounds,
+                    ..
+                } => {
+                    internal_count += 1;
+                    internal_cost += bounds.cost().into();
+                    if self
+                        .node(*left)
+                        .bounds()
+                        .intersects(self.node(*right).bounds())
+                    {
+                        overlap_count += 1;
+                    }
+                    stack.push((*left, depth + 1));
+                    stack.push((*right, depth + 1));
+                }
+            }
+        }
+
+        stats.average_leaf_depth = total_leaf_depth as f64 / stats.leaf_count as f64;
+        let root_cost = self.node(root).bounds().cost().into();
+        if root_cost > 0.0 {
+            stats.sah_cost = internal_cost / root_cost;
+        }
+        if internal_count > 0 {
+            stats.overlap_factor = overlap_count as f64 / internal_count as f64;
+        }
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Bounds, Point};
+
+    #[test]
+    fn test_stats() {
+        let mut tree = BoundsTree::new();
+        assert_eq!(tree.stats(), TreeStats::default());
+
+        for (ix, x) in [0.0, 5.0, 20.0, 22.0].into_iter().enumerate() {
+            let bounds = Bounds {
+                min: Point { x, y: 0.0 },
+                max: Point {
+                    x: x + 10.0,
+                    y: 10.0,
+                },
+            };
+            tree.insert(bounds, ix);
+        }
+
+        // The tree is a chain: (0, (5, (20, 22))), where each leaf is named by its x.
+        let stats = tree.stats();
+        assert_eq!(stats.leaf_count, 4);
+        assert_eq!(stats.node_count, 7);
+        assert_eq!(stats.height, 4);
+        assert_eq!(stats.average_leaf_depth, 2.25);
+        assert_eq!(stats.sah_cost, (42.0 + 37.0 + 22.0) / 42.0);
+        assert_eq!(stats.overlap_factor, 2.0 / 3.0);
+    }
+}
@@ -0,0 +1,86 @@
+// Proptest strategies for generating geometry, for downstream crates that want to
+// property-test code driving a `BoundsTree`.
+use crate::{Bounds, Point};
+use proptest::prelude::*;
+use std::ops::Range;
+
+pub fn point(coords: Range<f32>) -> impl Strategy<Value = Point> {
+    (coords.clone(), coords).prop_map(|(x, y)| Point { x, y })
+}
+
+// Well-formed bounds with a positive area whose corners lie within `coords`.
+pub fn bounds(coords: Range<f32>) -> impl Strategy<Value = Bounds> {
+    (point(coords.clone()), point(coords)).prop_filter_map("empty bounds", |(a, b)| {
+        let bounds = Bounds {
+            min: Point {
+                x: a.x.min(b.x),
+                y: a.y.min(b.y),
+            },
+            max: Point {
+                x: a.x.max(b.x),
+                y: a.y.max(b.y),
+            },
+        };
+        (bounds.min.x < bounds.max.x && bounds.min.y < bounds.max.y).then_some(bounds)
+    })
+}
+
+// Bounds without any area: single points, and horizontal or vertical segments. These don't
+// even intersect themselves, which makes them a useful edge case.
+pub fn degenerate_bounds(coords: Range<f32>) -> impl Strategy<Value = Bounds> {
+    (point(coords.clone()), coords, 0..3).prop_map(|(min, extent, shape)| {
+        let max = match shape {
+            0 => min,
+            1 => Point {
+                x: min.x.max(extent),
+                y: min.y,
+            },
+            _ => Point {
+                x: min.x,
+                y: min.y.max(extent),
+            },
+        };
+        Bounds { min, max }
+    })
+}
+
+// Any of the above, weighted towards well-formed bounds.
+pub fn any_bounds(coords: Range<f32>) -> impl Strategy<Value = Bounds> {
+    prop_oneof![
+        4 => bounds(coords.clone()),
+        1 => degenerate_bounds(coords),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BoundsTree;
+
+    proptest! {
+        #[test]
+        fn test_generated_bounds(
+            bounds in bounds(-100.0..100.0),
+            degenerate in degenerate_bounds(-100.0..100.0),
+        ) {
+            prop_assert!(bounds.intersects(bounds));
+            prop_assert!(!degenerate.intersects(degenerate));
+        }
+
+        #[test]
+        fn test_orders_are_consistent(all_bounds in prop::collection::vec(any_bounds(-50.0..50.0), 1..50)) {
+            let mut tree = BoundsTree::new();
+            let mut orders: Vec<u32> = Vec::new();
+            for (ix, bounds) in all_bounds.iter().enumerate() {
+                let expected = (0..ix)
+                    .filter(|other| all_bounds[*other].intersects(*bounds))
+                    .map(|other| orders[other])
+                    .max()
+                    .unwrap_or(0)
+                    + 1;
+                orders.push(tree.insert(*bounds, ix));
+                prop_assert_eq!(orders[ix], expected);
+            }
+        }
+    }
+}
@@ -0,0 +1,161 @@
+use crate::{bounds_tree::Node, Bounds, BoundsTree, Point};
+use std::fmt::{self, Write};
+
+// Controls how `BoundsTree::render_svg` draws a tree.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SvgStyle {
+    // The region to show, or `None` to fit the whole tree.
+    pub view_box: Option<Bounds>,
+    // Whether to draw internal nodes, each filled with a translucent color derived from its
+    // index, underneath the leaves.
+    pub show_internal_nodes: bool,
+    // Whether to label every leaf with the `Debug` representation of its data.
+    pub show_labels: bool,
+    pub leaf_stroke: String,
+    pub stroke_width: f32,
+    pub font_size: f32,
+}
+
+impl Default for SvgStyle {
+    fn default() -> Self {
+        SvgStyle {
+            view_box: None,
+            show_internal_nodes: true,
+            show_labels: true,
+            leaf_stroke: "black".into(),
+            stroke_width: 1.0,
+            font_size: 3.0,
+        }
+    }
+}
+
+impl<T: Clone + fmt::Debug> BoundsTree<T> {
+    // Draws the tree as an SVG document, for debugging.
+    pub fn render_svg(&self, style: &SvgStyle) -> String {
+        let view_box = style.view_box.or(self.root_bounds());
+        svg_document(view_box, &self.render_svg_content(style))
+    }
+
+    // Renders the tree's shapes without the enclosing `<svg>` element.
+    pub(crate) fn render_svg_content(&self, style: &SvgStyle) -> String {
+        let mut svg = String::new();
+        if let Some(root) = self.tree.root {
+            self.render_node(&mut svg, root, style);
+        }
+        svg
+    }
+
+    fn render_node(&self, svg: &mut String, index: usize, style: &SvgStyle) {
+        match self.node(index) {
+            Node::Internal {
+                bounds,
+                left,
+                right,
+                ..
+            } => {
+                if style.show_internal_nodes {
+                    let (red, green, blue) =
+                        ((index * 50) % 255, (index * 120) % 255, (index * 180) % 255);
+                    write!(
+                        svg,
+                        r#"<rect x="{}" y="{}" width="{}" height="{}" style="fill:rgba({red},{green},{blue},0.5);stroke:rgba({red},{green},{blue},1);stroke-width:{}" />"#,
+                        bounds.min.x,
+                        bounds.min.y,
+                        bounds.max.x - bounds.min.x,
+                        bounds.max.y - bounds.min.y,
+                        style.stroke_width
+                    )
+                    .unwrap();
+                }
+                self.render_node(svg, *left, style);
+                self.render_node(svg, *right, style);
+            }
+            Node::Leaf { bounds, data, .. } => {
+                write!(
+                    svg,
+                    r#"<rect x="{}" y="{}" width="{}" height="{}" style="fill:none;stroke:{};stroke-width:{}" />"#,
+                    bounds.min.x,
+                    bounds.min.y,
+                    bounds.max.x - bounds.min.x,
+                    bounds.max.y - bounds.min.y,
+                    escape(&style.leaf_stroke),
+                    style.stroke_width
+                )
+                .unwrap();
+                if style.show_labels {
+                    write!(
+                        svg,
+                        r#"<text x="{}" y="{}" font-size="{}" text-anchor="middle" alignment-baseline="central">{}</text>"#,
+                        (bounds.min.x + bounds.max.x) / 2.0,
+                        (bounds.min.y + bounds.max.y) / 2.0,
+                        style.font_size,
+                        escape(&format!("{data:?}"))
+                    )
+                    .unwrap();
+                }
+            }
+        }
+    }
+}
+
+pub(crate) fn svg_document(view_box: Option<Bounds>, content: &str) -> String {
+    let view_box = view_box.unwrap_or(Bounds {
+        min: Point { x: 0.0, y: 0.0 },
+        max: Point { x: 1.0, y: 1.0 },
+    });
+    format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" version="1.1" viewBox="{} {} {} {}" style="border:1px solid black;">{content}</svg>"#,
+        view_box.min.x,
+        view_box.min.y,
+        view_box.max.x - view_box.min.x,
+        view_box.max.y - view_box.min.y
+    )
+}
+
+pub(crate) fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_svg() {
+        let mut tree = BoundsTree::new();
+        for (name, x) in [("a", 0.0), ("<b>", 5.0), ("c", 20.0)] {
+            let bounds = Bounds {
+                min: Point { x, y: 0.0 },
+                max: Point {
+                    x: x + 10.0,
+                    y: 10.0,
+                },
+            };
+            tree.insert(bounds, name);
+        }
+
+        let svg = tree.render_svg(&SvgStyle::default());
+        assert!(svg.contains(r#"viewBox="0 0 30 10""#));
+        assert_eq!(svg.matches("<rect").count(), 5);
+        assert!(svg.contains("&quot;&lt;b&gt;&quot;</text>"));
+
+        let svg = tree.render_svg(&SvgStyle {
+            view_box: Some(Bounds {
+                min: Point {
+                    x: -100.0,
+                    y: -100.0,
+                },
+                max: Point { x: 100.0, y: 100.0 },
+            }),
+            show_internal_nodes: false,
+            show_labels: false,
+            ..Default::default()
+        });
+        assert!(svg.contains(r#"viewBox="-100 -100 200 200""#));
+        assert_eq!(svg.matches("<rect").count(), 3);
+        assert!(!svg.contains("<text"));
+    }
+}
@@ -0,0 +1,347 @@
+use crate::{Bounds, Error, PairEvent, ProxyHandle};
+use std::{
+    collections::{HashMap, HashSet},
+    mem,
+};
+
+// A broad phase with the same interface as `BroadPhase`, which keeps the endpoints of every
+// proxy sorted along each axis instead of querying a tree. When proxies only move a little
+// between updates, the lists stay almost sorted and re-sorting them with an insertion sort
+// finds every pair that started or stopped overlapping in close to linear time. Proxies that
+// jump across the scene are more expensive, since they're swapped past every endpoint in
+// between.
+pub struct SweepAndPrune<K> {
+    slots: Vec<Slot<K>>,
+    free_slots: Vec<usize>,
+    axes: [Vec<Endpoint>; 2],
+    // Overlapping pairs, with the smaller handle first.
+    pairs: HashSet<(ProxyHandle, ProxyHandle)>,
+    pending_events: Vec<PairEvent<K>>,
+    needs_sort: bool,
+}
+
+struct Slot<K> {
+    generation: u32,
+    proxy: Option<(K, Bounds)>,
+}
+
+#[derive(Clone, Copy)]
+struct Endpoint {
+    handle: ProxyHandle,
+    is_min: bool,
+}
+
+impl<K: Copy> Default for SweepAndPrune<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Copy> SweepAndPrune<K> {
+    pub fn new() -> Self {
+        SweepAndPrune {
+            slots: Vec::new(),
+            free_slots: Vec::new(),
+            axes: [Vec::new(), Vec::new()],
+            pairs: HashSet::new(),
+            pending_events: Vec::new(),
+            needs_sort: false,
+        }
+    }
+
+    // Registers a new proxy for the collider identified by `key`. Its overlaps are reported
+    // by the next call to `update`.
+    pub fn create_proxy(&mut self, key: K, bounds: Bounds) -> ProxyHandle {
+        let handle = match self.free_slots.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index];
+                slot.proxy = Some((key, bounds));
+                ProxyHandle {
+                    index,
+                    generation: slot.generation,
+                }
+            }
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    proxy: Some((key, bounds)),
+                });
+                ProxyHandle {
+                    index: self.slots.len() - 1,
+                    generation: 0,
+                }
+            }
+        };
+        // Endpoints past the end of the lists don't overlap anything, until sorting moves them
+        // into place.
+        for axis in &mut self.axes {
+            axis.push(Endpoint {
+                handle,
+                is_min: true,
+            });
+            axis.push(Endpoint {
+                handle,
+                is_min: false,
+            });
+        }
+        self.needs_sort = true;
+        handle
+    }
+
+    // Moves a proxy. Panics if the proxy was removed.
+    pub fn set_bounds(&mut self, handle: ProxyHandle, bounds: Bounds) {
+        self.try_set_bounds(handle, bounds).unwrap()
+    }
+
+    // Moves a proxy, failing if the proxy was removed.
+    pub fn try_set_bounds(&mut self, handle: ProxyHandle, bounds: Bounds) -> Result<(), Error> {
+        let proxy = self.proxy_mut(handle).ok_or(Error::StaleHandle {
+            index: handle.index,
+            generation: handle.generation,
+        })?;
+        if mem::replace(&mut proxy.1, bounds) != bounds {
+            self.needs_sort = true;
+        }
+        Ok(())
+    }
+
+    // Removes a proxy, reporting all of its pairs as removed on the next call to `update`.
+    pub fn remove_proxy(&mut self, handle: ProxyHandle) -> Option<K> {
+        let slot = self
+            .slots
+            .get_mut(handle.index)
+            .filter(|slot| slot.generation == handle.generation)?;
+        let (key, _) = slot.proxy.take()?;
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free_slots.push(handle.index);
+
+        for axis in &mut self.axes {
+            axis.retain(|endpoint| endpoint.handle != handle);
+        }
+        let slots = &self.slots;
+        let pending_events = &mut self.pending_events;
+        self.pairs.retain(|&(a, b)| {
+            if a != handle && b != handle {
+                return true;
+            }
+            let other = if a == handle { b } else { a };
+            let (other_key, _) = slots[other.index].proxy.unwrap();
+            pending_events.push(PairEvent::Removed(key, other_key));
+            false
+        });
+        Some(key)
+    }
+
+    pub fn bounds(&self, handle: ProxyHandle) -> Option<Bounds> {
+        Some(self.proxy(handle)?.1)
+    }
+
+    fn proxy(&self, handle: ProxyHandle) -> Option<&(K, Bounds)> {
+        let slot = self.slots.get(handle.index)?;
+        if slot.generation != handle.generation {
+            return None;
+        }
+        slot.proxy.as_ref()
+    }
+
+    fn proxy_mut(&mut self, handle: ProxyHandle) -> Option<&mut (K, Bounds)> {
+        let slot = self.slots.get_mut(handle.index)?;
+        if slot.generation != handle.generation {
+            return None;
+        }
+        slot.proxy.as_mut()
+    }
+
+    // Endpoints are ordered by coordinate, with maximums before minimums at the same
+    // coordinate so that proxies that merely touch don't overlap.
+    fn endpoint_key(&self, axis: usize, endpoint: Endpoint) -> (f32, bool) {
+        let (_, bounds) = self.slots[endpoint.handle.index].proxy.unwrap();
+        let point = if endpoint.is_min {
+            bounds.min
+        } else {
+            bounds.max
+        };
+        let coordinate = if axis == 0 { point.x } else { point.y };
+        (coordinate, endpoint.is_min)
+    }
+
+    // Appends an event for every pair that started or stopped overlapping since the last
+    // update.
+    pub fn update(&mut self, events: &mut Vec<PairEvent<K>>) {
+        events.append(&mut self.pending_events);
+        if !mem::take(&mut self.needs_sort) {
+            return;
+        }
+
+        // A pair can be swapped several times while sorting, so only the difference between
+        // its state before and after sorting is reported.
+        let mut touched_pairs = Vec::new();
+        let mut overlapped_before = HashMap::new();
+        for axis in 0..self.axes.len() {
+            let mut endpoints = mem::take(&mut self.axes[axis]);
+            for i in 1..endpoints.len() {
+                let mut j = i;
+                while j > 0 {
+                    let moving = endpoints[j];
+                    let other = endpoints[j - 1];
+                    let moving_key = self.endpoint_key(axis, moving);
+                    let other_key = self.endpoint_key(axis, other);
+                    if other_key.0 < moving_key.0
+                        || (other_key.0 == moving_key.0 && other_key.1 <= moving_key.1)
+                    {
+                        break;
+                    }
+
+                    // Moving a minimum below another proxy's maximum can make the proxies
+                    // overlap, and moving a maximum below another proxy's minimum separates
+                    // them.
+                    if moving.handle != other.handle && moving.is_min != other.is_min {
+                        let pair = (moving.handle, other.handle);
+                        let key = (pair.0.min(pair.1), pair.0.max(pair.1));
+                        let overlapping = self.pairs.contains(&key);
+                        let overlaps = moving.is_min && {
+                            let (_, a) = self.slots[moving.handle.index].proxy.unwrap();
+                            let (_, b) = self.slots[other.handle.index].proxy.unwrap();
+                            a.intersects(b)
+                        };
+                        if overlaps != overlapping {
+                            overlapped_before.entry(key).or_insert_with(|| {
+                                touched_pairs.push(pair);
+                                overlapping
+                            });
+                            if overlaps {
+                                self.pairs.insert(key);
+                            } else {
+                                self.pairs.remove(&key);
+                            }
+                        }
+                    }
+                    endpoints.swap(j - 1, j);
+                    j -= 1;
+                }
+            }
+            self.axes[axis] = endpoints;
+        }
+
+        for (a, b) in touched_pairs {
+            let key = (a.min(b), a.max(b));
+            let overlaps = self.pairs.contains(&key);
+            if overlaps != overlapped_before[&key] {
+                let (a, _) = self.slots[a.index].proxy.unwrap();
+                let (b, _) = self.slots[b.index].proxy.unwrap();
+                events.push(if overlaps {
+                    PairEvent::Added(a, b)
+                } else {
+                    PairEvent::Removed(a, b)
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::strip;
+    use crate::Point;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn test_pair_events() {
+        let mut sweep_and_prune = SweepAndPrune::new();
+        let mut events = Vec::new();
+        let a = sweep_and_prune.create_proxy('a', strip(0.0, 10.0));
+        let b = sweep_and_prune.create_proxy('b', strip(5.0, 10.0));
+        let c = sweep_and_prune.create_proxy('c', strip(30.0, 10.0));
+        sweep_and_prune.update(&mut events);
+        assert_eq!(events, [PairEvent::Added('b', 'a')]);
+
+        events.clear();
+        sweep_and_prune.set_bounds(b, strip(25.0, 10.0));
+        sweep_and_prune.set_bounds(c, strip(20.0, 10.0));
+        sweep_and_prune.update(&mut events);
+        assert_eq!(
+            events,
+            [PairEvent::Removed('a', 'b'), PairEvent::Added('c', 'b')]
+        );
+
+        // Touching proxies don't overlap.
+        events.clear();
+        sweep_and_prune.set_bounds(c, strip(35.0, 10.0));
+        sweep_and_prune.update(&mut events);
+        assert_eq!(events, [PairEvent::Removed('b', 'c')]);
+
+        events.clear();
+        sweep_and_prune.update(&mut events);
+        assert!(events.is_empty());
+
+        sweep_and_prune.set_bounds(c, strip(30.0, 10.0));
+        sweep_and_prune.update(&mut events);
+        assert_eq!(events, [PairEvent::Added('c', 'b')]);
+
+        events.clear();
+        assert_eq!(sweep_and_prune.remove_proxy(c), Some('c'));
+        assert_eq!(sweep_and_prune.remove_proxy(c), None);
+        sweep_and_prune.update(&mut events);
+        assert_eq!(events, [PairEvent::Removed('c', 'b')]);
+        assert_eq!(sweep_and_prune.bounds(a), Some(strip(0.0, 10.0)));
+    }
+
+    #[test]
+    fn test_random_moves() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(5);
+        let mut sweep_and_prune = SweepAndPrune::new();
+        let mut proxies = Vec::new();
+        let mut pairs = HashSet::new();
+        let mut events = Vec::new();
+        for key in 0..100 {
+            if proxies.len() < 40 || rng.gen_bool(0.2) {
+                let min = Point {
+                    x: rng.gen_range(0.0..100.0),
+                    y: rng.gen_range(0.0..100.0),
+                };
+                let bounds = Bounds {
+                    min,
+                    max: Point {
+                        x: min.x + rng.gen_range(0.0..10.0),
+                        y: min.y + rng.gen_range(0.0..10.0),
+                    },
+                };
+                proxies.push((sweep_and_prune.create_proxy(key, bounds), key));
+            }
+            if rng.gen_bool(0.1) {
+                let (handle, _) = proxies.swap_remove(rng.gen_range(0..proxies.len()));
+                sweep_and_prune.remove_proxy(handle);
+            }
+            for (handle, _) in &proxies {
+                if rng.gen_bool(0.5) {
+                    let mut bounds = sweep_and_prune.bounds(*handle).unwrap();
+                    let (dx, dy) = (rng.gen_range(-2.0..2.0), rng.gen_range(-2.0..2.0));
+                    bounds.min.x += dx;
+                    bounds.max.x += dx;
+                    bounds.min.y += dy;
+                    bounds.max.y += dy;
+                    sweep_and_prune.set_bounds(*handle, bounds);
+                }
+            }
+
+            sweep_and_prune.update(&mut events);
+            for event in events.drain(..) {
+                match event {
+                    PairEvent::Added(a, b) => assert!(pairs.insert((a.min(b), a.max(b)))),
+                    PairEvent::Removed(a, b) => assert!(pairs.remove(&(a.min(b), a.max(b)))),
+                }
+            }
+            let mut expected = HashSet::new();
+            for (i, (a, a_key)) in proxies.iter().enumerate() {
+                for (b, b_key) in &proxies[..i] {
+                    let a_bounds = sweep_and_prune.bounds(*a).unwrap();
+                    if a_bounds.intersects(sweep_and_prune.bounds(*b).unwrap()) {
+                        expected.insert((*a_key.min(b_key), *a_key.max(b_key)));
+                    }
+                }
+            }
+            assert_eq!(pairs, expected);
+        }
+    }
+}
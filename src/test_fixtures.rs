@@ -0,0 +1,29 @@
+// Fixtures shared by the tests of several modules.
+use crate::{Bounds, Point};
+use rand::{rngs::StdRng, Rng};
+
+// Bounds spanning `x..x + width` horizontally and `0..10` vertically, so whether two of them
+// intersect only depends on their horizontal extents.
+pub(crate) fn strip(x: f32, width: f32) -> Bounds {
+    Bounds {
+        min: Point { x, y: 0.0 },
+        max: Point {
+            x: x + width,
+            y: 10.0,
+        },
+    }
+}
+
+// Random bounds whose minimum lies within `-100..100` on both axes and that are at most
+// `max_size` wide and high.
+pub(crate) fn random_bounds(rng: &mut StdRng, max_size: f32) -> Bounds {
+    let min_x: f32 = rng.gen_range(-100.0..100.0);
+    let min_y: f32 = rng.gen_range(-100.0..100.0);
+    Bounds {
+        min: Point { x: min_x, y: min_y },
+        max: Point {
+            x: rng.gen_range(min_x..min_x + max_size),
+            y: rng.gen_range(min_y..min_y + max_size),
+        },
+    }
+}
@@ -0,0 +1,219 @@
+use crate::{Bounds, BoundsTree, Error, Point, Primitive};
+use std::ops::Range;
+
+// A grid of square tiles for tile-based renderers and compositors. Tiles are numbered in
+// row-major order, starting with the tile whose minimum corner is `origin`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TileGrid {
+    pub origin: Point,
+    pub tile_size: f32,
+    pub columns: usize,
+    pub rows: usize,
+}
+
+impl TileGrid {
+    // The smallest grid of tiles aligned to multiples of `tile_size` that covers `bounds`.
+    // Fails if the bounds aren't finite or need more tiles than can be counted. Panics if the
+    // tile size isn't positive.
+    pub fn covering(bounds: Bounds, tile_size: f32) -> Result<Self, Error> {
+        assert!(
+            tile_size > 0.0 && tile_size.is_finite(),
+            "tile size must be positive and finite"
+        );
+        let finite = [bounds.min.x, bounds.min.y, bounds.max.x, bounds.max.y]
+            .iter()
+            .all(|coordinate| coordinate.is_finite());
+        if !finite || !bounds.is_valid() {
+            return Err(Error::InvalidBounds {
+                bounds: format!("{bounds:?}"),
+            });
+        }
+        let origin = Point {
+            x: (bounds.min.x / tile_size).floor() * tile_size,
+            y: (bounds.min.y / tile_size).floor() * tile_size,
+        };
+        // Casting saturates, so grids too large to count fail the multiplication below.
+        let columns = ((bounds.max.x - origin.x) / tile_size).ceil().max(0.0) as usize;
+        let rows = ((bounds.max.y - origin.y) / tile_size).ceil().max(0.0) as usize;
+        columns.checked_mul(rows).ok_or(Error::CapacityExceeded {
+            limit: usize::MAX,
+            requested: columns.saturating_mul(rows),
+        })?;
+        Ok(TileGrid {
+            origin,
+            tile_size,
+            columns,
+            rows,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.columns * self.rows
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // The bounds of the whole grid.
+    pub fn bounds(&self) -> Bounds {
+        Bounds {
+            min: self.origin,
+            max: Point {
+                x: self.origin.x + self.columns as f32 * self.tile_size,
+                y: self.origin.y + self.rows as f32 * self.tile_size,
+            },
+        }
+    }
+
+    pub fn tile_bounds(&self, tile: usize) -> Bounds {
+        let min = Point {
+            x: self.origin.x + (tile % self.columns) as f32 * self.tile_size,
+            y: self.origin.y + (tile / self.columns) as f32 * self.tile_size,
+        };
+        Bounds {
+            min,
+            max: Point {
+                x: min.x + self.tile_size,
+                y: min.y + self.tile_size,
+            },
+        }
+    }
+
+    // Calls `f` with every tile that intersects the bounds.
+    fn for_each_tile(&self, bounds: Bounds, mut f: impl FnMut(usize)) {
+        let columns = Self::span(bounds.min.x, bounds.max.x, self.origin.x, self.tile_size);
+        let rows = Self::span(bounds.min.y, bounds.max.y, self.origin.y, self.tile_size);
+        for row in rows.start.min(self.rows)..rows.end.min(self.rows) {
+            for column in columns.start.min(self.columns)..columns.end.min(self.columns) {
+                f(row * self.columns + column);
+            }
+        }
+    }
+
+    // The tiles spanned along one axis. Like intersection tests, this excludes tiles that the
+    // bounds merely touch, and zero-sized bounds don't span any tiles.
+    fn span(min: f32, max: f32, origin: f32, tile_size: f32) -> Range<usize> {
+        if min >= max {
+            return 0..0;
+        }
+        // Casting saturates, so anything before the origin is clamped to the first tile.
+        let start = ((min - origin) / tile_size).floor() as usize;
+        let end = ((max - origin) / tile_size).ceil() as usize;
+        start..end
+    }
+}
+
+impl<T: Clone> BoundsTree<T> {
+    // Returns the maximum order of the primitives intersecting each tile of the grid, or zero
+    // for tiles without any. A compositor can skip tiles whose maximum didn't change since the
+    // previous frame, as long as nothing below their top primitive changed either.
+    pub fn max_order_per_tile(&self, grid: &TileGrid) -> Vec<u32> {
+        let mut max_orders = vec![0; grid.len()];
+        if grid.is_empty() {
+            return max_orders;
+        }
+        for primitive in self.iter_intersecting(grid.bounds()) {
+            grid.for_each_tile(primitive.bounds, |tile| {
+                max_orders[tile] = max_orders[tile].max(primitive.order);
+            });
+        }
+        max_orders
+    }
+
+    // Lists the primitives intersecting each tile of the grid in painter's order. Every
+    // primitive within the grid is visited once and appended to the tiles it spans, rather
+    // than querying the tree for every tile. Panics if the tile size isn't positive.
+    pub fn bin_into_tiles(&self, grid: &TileGrid) -> Vec<Vec<Primitive<&T>>> {
+        assert!(grid.tile_size > 0.0, "tile size must be positive");
+        let mut bins = vec![Vec::new(); grid.len()];
+        if grid.is_empty() {
+            return bins;
+        }
+        for primitive in self.iter_intersecting_in_order(grid.bounds()) {
+            grid.for_each_tile(primitive.bounds, |tile| bins[tile].push(primitive.clone()));
+        }
+        bins
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bounds(min_x: f32, min_y: f32, max_x: f32, max_y: f32) -> Bounds {
+        Bounds {
+            min: Point { x: min_x, y: min_y },
+            max: Point { x: max_x, y: max_y },
+        }
+    }
+
+    #[test]
+    fn test_max_order_per_tile() {
+        let grid = TileGrid::covering(bounds(0.0, 0.0, 30.0, 20.0), 10.0).unwrap();
+        assert_eq!((grid.columns, grid.rows), (3, 2));
+        assert_eq!(grid.tile_bounds(4), bounds(10.0, 10.0, 20.0, 20.0));
+
+        let mut tree = BoundsTree::new();
+        tree.insert(bounds(0.0, 0.0, 30.0, 20.0), "background");
+        tree.insert(bounds(5.0, 5.0, 10.0, 10.0), "icon");
+        tree.insert(bounds(2.0, 2.0, 12.0, 8.0), "tooltip");
+        tree.insert(bounds(-100.0, 15.0, 100.0, 20.0), "scrollbar");
+        tree.insert(bounds(200.0, 0.0, 210.0, 10.0), "offscreen");
+        assert_eq!(tree.max_order_per_tile(&grid), [3, 3, 1, 2, 2, 2]);
+    }
+
+    #[test]
+    fn test_bin_into_tiles() {
+        let mut tree = BoundsTree::new();
+        tree.insert(bounds(12.0, 0.0, 28.0, 8.0), "tab");
+        tree.insert(bounds(15.0, 2.0, 25.0, 6.0), "title");
+        tree.insert(bounds(0.0, 10.0, 40.0, 20.0), "editor");
+
+        // Primitives outside the grid are skipped rather than growing it.
+        tree.insert(bounds(1.0e30, 0.0, 1.0e30, 10.0), "far away");
+
+        let grid = TileGrid::covering(bounds(0.0, 0.0, 40.0, 20.0), 10.0).unwrap();
+        assert_eq!(grid.origin, Point { x: 0.0, y: 0.0 });
+        assert_eq!((grid.columns, grid.rows), (4, 2));
+        let bins = tree.bin_into_tiles(&grid);
+        assert_eq!(bins.len(), 8);
+        let bins = bins
+            .into_iter()
+            .map(|bin| {
+                bin.into_iter()
+                    .map(|primitive| *primitive.data)
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(bins[0], Vec::<&str>::new());
+        assert_eq!(bins[1], ["tab", "title"]);
+        assert_eq!(bins[2], ["tab", "title"]);
+        assert_eq!(bins[3], Vec::<&str>::new());
+        assert!(bins[4..].iter().all(|bin| bin == &["editor"]));
+
+        tree.clear();
+        assert!(tree.bin_into_tiles(&grid).iter().all(Vec::is_empty));
+    }
+
+    #[test]
+    fn test_covering_rejects_unbounded_grids() {
+        let nan = bounds(f32::NAN, 0.0, 10.0, 10.0);
+        assert!(matches!(
+            TileGrid::covering(nan, 10.0),
+            Err(Error::InvalidBounds { .. })
+        ));
+        let infinite = bounds(0.0, 0.0, f32::INFINITY, 10.0);
+        assert!(matches!(
+            TileGrid::covering(infinite, 10.0),
+            Err(Error::InvalidBounds { .. })
+        ));
+        let huge = bounds(-1.0e30, -1.0e30, 1.0e30, 1.0e30);
+        assert!(matches!(
+            TileGrid::covering(huge, 1.0),
+            Err(Error::CapacityExceeded { .. })
+        ));
+        let empty = TileGrid::covering(bounds(0.0, 0.0, 0.0, 0.0), 10.0).unwrap();
+        assert!(empty.is_empty());
+    }
+}
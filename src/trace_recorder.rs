@@ -0,0 +1,185 @@
+use crate::{
+    svg::{escape, svg_document},
+    Bounds, BoundsTree, PrimitiveHandle, SvgStyle,
+};
+use std::fmt::{self, Write};
+
+// Wraps a `BoundsTree`, recording a frame after every mutation so the evolution of the tree
+// can be replayed as an HTML animation. Every frame stores a rendering of the whole tree, so
+// this is only meant for debugging small trees.
+pub struct TraceRecorder<T> {
+    tree: BoundsTree<T>,
+    style: SvgStyle,
+    frames: Vec<Frame>,
+}
+
+struct Frame {
+    description: String,
+    content: String,
+    bounds: Option<Bounds>,
+}
+
+impl<T: Clone + fmt::Debug> TraceRecorder<T> {
+    pub fn new(style: SvgStyle) -> Self {
+        TraceRecorder {
+            tree: BoundsTree::new(),
+            style,
+            frames: Vec::new(),
+        }
+    }
+
+    pub fn insert(&mut self, bounds: Bounds, data: T) -> u32 {
+        let description = format!("insert {data:?} at {bounds:?}");
+        let order = self.tree.insert(bounds, data);
+        self.record(format!("{description}: order {order}"));
+        order
+    }
+
+    pub fn insert_with_handle(&mut self, bounds: Bounds, data: T) -> (PrimitiveHandle, u32) {
+        let description = format!("insert {data:?} at {bounds:?}");
+        let (handle, order) = self.tree.insert_with_handle(bounds, data);
+        self.record(format!("{description}: order {order}"));
+        (handle, order)
+    }
+
+    // Moves a primitive like `BoundsTree::update`. Nothing is recorded for stale handles,
+    // since the tree doesn't change.
+    pub fn update(&mut self, handle: PrimitiveHandle, bounds: Bounds) -> Option<u32> {
+        let description = format!("move {:?} to {bounds:?}", self.tree.get(handle)?);
+        let order = self.tree.update(handle, bounds)?;
+        self.record(format!("{description}: order {order}"));
+        Some(order)
+    }
+
+    pub fn remove(&mut self, handle: PrimitiveHandle) -> Option<T> {
+        let data = self.tree.remove(handle)?;
+        self.record(format!("remove {data:?}"));
+        Some(data)
+    }
+
+    pub fn tree(&self) -> &BoundsTree<T> {
+        &self.tree
+    }
+
+    pub fn into_tree(self) -> BoundsTree<T> {
+        self.tree
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    fn record(&mut self, description: String) {
+        self.frames.push(Frame {
+            description,
+            content: self.tree.render_svg_content(&self.style),
+            bounds: self.tree.root_bounds(),
+        });
+    }
+
+    // Exports a standalone HTML page with a slider and a play button for stepping through the
+    // recorded frames. Unless the style specifies a view box, every frame shows the region
+    // covered by all frames, so the view stays put while the tree grows.
+    pub fn to_html(&self) -> String {
+        let view_box = self.style.view_box.or_else(|| {
+            self.frames
+                .iter()
+                .filter_map(|frame| frame.bounds)
+                .reduce(Bounds::merge)
+        });
+
+        let mut html = String::from(
+            "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>Tree trace</title></head>\n<body>\n",
+        );
+        writeln!(
+            html,
+            r#"<input id="step" type="range" min="0" max="{}" value="0"> <button id="play">Play</button> <span id="description"></span>"#,
+            self.frames.len().saturating_sub(1)
+        )
+        .unwrap();
+        for (ix, frame) in self.frames.iter().enumerate() {
+            writeln!(
+                html,
+                r#"<div class="frame" data-description="{}"{}>{}</div>"#,
+                escape(&format!("{}: {}", ix + 1, frame.description)),
+                if ix == 0 { "" } else { " hidden" },
+                svg_document(view_box, &frame.content)
+            )
+            .unwrap();
+        }
+        html.push_str(
+            r#"<script>
+const frames = document.querySelectorAll(".frame");
+const step = document.getElementById("step");
+const description = document.getElementById("description");
+function show(index) {
+  frames.forEach((frame, ix) => frame.hidden = ix != index);
+  step.value = index;
+  description.textContent = frames[index] ? frames[index].dataset.description : "";
+}
+step.oninput = () => show(Number(step.value));
+document.getElementById("play").onclick = () => {
+  let index = 0;
+  const timer = setInterval(() => {
+    show(index++);
+    if (index >= frames.length) clearInterval(timer);
+  }, 500);
+};
+show(0);
+</script>
+</body>
+</html>
+"#,
+        );
+        html
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_fixtures::strip, Point};
+
+    #[test]
+    fn test_trace_recorder() {
+        let mut recorder = TraceRecorder::new(SvgStyle::default());
+        for (ix, x) in [0.0, 5.0, 20.0].into_iter().enumerate() {
+            let bounds = Bounds {
+                min: Point { x, y: 0.0 },
+                max: Point {
+                    x: x + 10.0,
+                    y: 10.0,
+                },
+            };
+            recorder.insert(bounds, ix);
+        }
+        assert_eq!(recorder.frame_count(), 3);
+
+        let html = recorder.to_html();
+        assert_eq!(html.matches("<svg").count(), 3);
+        // Every frame shares the view box covering the final tree.
+        assert_eq!(html.matches(r#"viewBox="0 0 30 10""#).count(), 3);
+        assert!(html.contains("3: insert 2 at"));
+        assert_eq!(recorder.into_tree().iter().count(), 3);
+    }
+
+    #[test]
+    fn test_recording_handle_mutations() {
+        let mut recorder = TraceRecorder::new(SvgStyle::default());
+        let (a, _) = recorder.insert_with_handle(strip(0.0, 10.0), "a");
+        let (b, _) = recorder.insert_with_handle(strip(20.0, 10.0), "b");
+        assert_eq!(recorder.update(b, strip(5.0, 10.0)), Some(2));
+        assert_eq!(recorder.remove(a), Some("a"));
+        assert_eq!(recorder.frame_count(), 4);
+
+        // Stale handles leave the tree untouched, so they don't add frames.
+        assert_eq!(recorder.update(a, strip(5.0, 10.0)), None);
+        assert_eq!(recorder.remove(a), None);
+        assert_eq!(recorder.frame_count(), 4);
+
+        let html = recorder.to_html();
+        assert!(html.contains("3: move &quot;b&quot; to"));
+        assert!(html.contains("4: remove &quot;a&quot;"));
+        assert_eq!(recorder.tree().iter().count(), 1);
+    }
+}
@@ -0,0 +1,325 @@
+use crate::{Bounds, Point, SpatialIndex};
+use std::collections::HashMap;
+
+// The number of cells an item can touch before it's kept in a separate list instead.
+const MAX_ITEM_CELLS: i64 = 256;
+
+// A spatial index that buckets items into square cells, which beats a tree when items are
+// dense and about as large as a cell. Every item is stored in each cell its bounds touch, so
+// items much larger than a cell are expensive. Items touching more than `MAX_ITEM_CELLS`
+// cells are kept in a list that every query checks instead.
+#[derive(Debug, Clone)]
+pub struct UniformGrid<T> {
+    cell_size: f32,
+    cells: HashMap<(i32, i32), Vec<usize>>,
+    oversized: Vec<usize>,
+    slots: Vec<GridSlot<T>>,
+    free_slots: Vec<usize>,
+    // The range of cells that contained items at some point, which bounds queries and nearest
+    // neighbor searches. It isn't shrunk when items are removed.
+    occupied: Option<((i32, i32), (i32, i32))>,
+}
+
+// Identifies an item of a `UniformGrid`. Like `PrimitiveHandle`, it's made up of a slot and
+// the generation of that slot, so handles to removed items are rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct GridHandle {
+    index: usize,
+    generation: u32,
+}
+
+#[derive(Debug, Clone)]
+struct GridSlot<T> {
+    generation: u32,
+    item: Option<(Bounds, T)>,
+}
+
+impl<T> UniformGrid<T> {
+    pub fn new(cell_size: f32) -> Self {
+        UniformGrid {
+            cell_size,
+            cells: HashMap::new(),
+            oversized: Vec::new(),
+            slots: Vec::new(),
+            free_slots: Vec::new(),
+            occupied: None,
+        }
+    }
+
+    pub fn cell_size(&self) -> f32 {
+        self.cell_size
+    }
+
+    fn cell(&self, point: Point) -> (i32, i32) {
+        (
+            (point.x / self.cell_size).floor() as i32,
+            (point.y / self.cell_size).floor() as i32,
+        )
+    }
+
+    // The cells containing any point of the bounds, including their maximum edges.
+    fn cells_touching(&self, bounds: Bounds) -> ((i32, i32), (i32, i32)) {
+        (self.cell(bounds.min), self.cell(bounds.max))
+    }
+
+    // The cells touching the bounds, or `None` if the bounds touch too many cells to be
+    // stored in each of them.
+    fn item_cells(&self, bounds: Bounds) -> Option<((i32, i32), (i32, i32))> {
+        let (min, max) = self.cells_touching(bounds);
+        let cells =
+            (max.0 as i64 - min.0 as i64 + 1).saturating_mul(max.1 as i64 - min.1 as i64 + 1);
+        (cells <= MAX_ITEM_CELLS).then_some((min, max))
+    }
+
+    // The cells touching the bounds that could contain items, or `None` if there are none.
+    fn occupied_cells_touching(&self, bounds: Bounds) -> Option<((i32, i32), (i32, i32))> {
+        let (occupied_min, occupied_max) = self.occupied?;
+        let (min, max) = self.cells_touching(bounds);
+        let min = (min.0.max(occupied_min.0), min.1.max(occupied_min.1));
+        let max = (max.0.min(occupied_max.0), max.1.min(occupied_max.1));
+        (min.0 <= max.0 && min.1 <= max.1).then_some((min, max))
+    }
+
+    fn item(&self, index: usize) -> (Bounds, &T) {
+        let (bounds, data) = self.slots[index].item.as_ref().unwrap();
+        (*bounds, data)
+    }
+}
+
+impl<T> SpatialIndex<T> for UniformGrid<T> {
+    type Handle = GridHandle;
+
+    fn insert(&mut self, bounds: Bounds, data: T) -> GridHandle {
+        let index = self.free_slots.pop().unwrap_or_else(|| {
+            self.slots.push(GridSlot {
+                generation: 0,
+                item: None,
+            });
+            self.slots.len() - 1
+        });
+        let slot = &mut self.slots[index];
+        slot.item = Some((bounds, data));
+        let handle = GridHandle {
+            index,
+            generation: slot.generation,
+        };
+
+        let Some((min, max)) = self.item_cells(bounds) else {
+            self.oversized.push(index);
+            return handle;
+        };
+        for x in min.0..=max.0 {
+            for y in min.1..=max.1 {
+                self.cells.entry((x, y)).or_default().push(index);
+            }
+        }
+        self.occupied = Some(match self.occupied {
+            Some((occupied_min, occupied_max)) => (
+                (occupied_min.0.min(min.0), occupied_min.1.min(min.1)),
+                (occupied_max.0.max(max.0), occupied_max.1.max(max.1)),
+            ),
+            None => (min, max),
+        });
+        handle
+    }
+
+    fn remove(&mut self, handle: GridHandle) -> Option<T> {
+        let slot = self
+            .slots
+            .get_mut(handle.index)
+            .filter(|slot| slot.generation == handle.generation)?;
+        let (bounds, data) = slot.item.take()?;
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free_slots.push(handle.index);
+
+        let Some((min, max)) = self.item_cells(bounds) else {
+            let ix = self
+                .oversized
+                .iter()
+                .position(|index| *index == handle.index)
+                .unwrap();
+            self.oversized.swap_remove(ix);
+            return Some(data);
+        };
+        for x in min.0..=max.0 {
+            for y in min.1..=max.1 {
+                let cell = self.cells.get_mut(&(x, y)).unwrap();
+                let ix = cell
+                    .iter()
+                    .position(|index| *index == handle.index)
+                    .unwrap();
+                cell.swap_remove(ix);
+                if cell.is_empty() {
+                    self.cells.remove(&(x, y));
+                }
+            }
+        }
+        Some(data)
+    }
+
+    fn query<'a>(&'a self, bounds: Bounds) -> impl Iterator<Item = (Bounds, &'a T)>
+    where
+        T: 'a,
+    {
+        // Only cells that ever held items are visited, so huge query bounds stay cheap.
+        let (min, max) = self
+            .occupied_cells_touching(bounds)
+            .unwrap_or(((0, 0), (-1, -1)));
+        let oversized = self.oversized.iter().filter_map(move |index| {
+            let (item_bounds, data) = self.item(*index);
+            item_bounds
+                .intersects(bounds)
+                .then_some((item_bounds, data))
+        });
+        (min.0..=max.0)
+            .flat_map(move |x| (min.1..=max.1).map(move |y| (x, y)))
+            .filter_map(|cell| Some((cell, self.cells.get(&cell)?)))
+            .flat_map(move |((x, y), indices)| {
+                indices.iter().filter_map(move |index| {
+                    let (item_bounds, data) = self.item(*index);
+                    // Items spanning several cells are only reported by the first visited cell
+                    // that both they and the query touch.
+                    let (item_min, _) = self.cells_touching(item_bounds);
+                    let first_cell = (item_min.0.max(min.0), item_min.1.max(min.1));
+                    (first_cell == (x, y) && item_bounds.intersects(bounds))
+                        .then_some((item_bounds, data))
+                })
+            })
+            .chain(oversized)
+    }
+
+    // Searches rings of cells around the point's cell, moving outwards until no item in the
+    // remaining rings can be closer than the nearest one found so far. Rings start at the
+    // first one reaching the occupied cells, and only their cells within the occupied range
+    // are visited, so points far from every item stay cheap.
+    fn nearest(&self, point: Point) -> Option<(Bounds, &T)> {
+        let mut nearest = None;
+        let mut nearest_distance = f32::INFINITY;
+        let mut visit = |indices: &[usize]| {
+            for index in indices {
+                let (bounds, data) = self.item(*index);
+                let distance = bounds.distance_squared_to_point(point);
+                if distance < nearest_distance {
+                    nearest_distance = distance;
+                    nearest = Some((bounds, data));
+                }
+            }
+            nearest_distance
+        };
+        let mut distance = visit(&self.oversized);
+
+        let Some((min, max)) = self.occupied else {
+            return nearest;
+        };
+        let (min_x, min_y) = (min.0 as i64, min.1 as i64);
+        let (max_x, max_y) = (max.0 as i64, max.1 as i64);
+        let center = self.cell(point);
+        let (center_x, center_y) = (center.0 as i64, center.1 as i64);
+        let first_ring = (min_x - center_x)
+            .max(center_x - max_x)
+            .max(min_y - center_y)
+            .max(center_y - max_y)
+            .max(0);
+        let last_ring = (center_x - min_x)
+            .max(max_x - center_x)
+            .max(center_y - min_y)
+            .max(max_y - center_y);
+
+        for ring in first_ring..=last_ring {
+            let (left, right) = (center_x - ring, center_x + ring);
+            let (top, bottom) = (center_y - ring, center_y + ring);
+            // The ring's top and bottom rows, then its left and right columns without the
+            // corners, each clamped to the occupied cells.
+            let rows = [top, bottom]
+                .into_iter()
+                .take(if ring == 0 { 1 } else { 2 })
+                .filter(|y| (min_y..=max_y).contains(y))
+                .flat_map(|y| (left.max(min_x)..=right.min(max_x)).map(move |x| (x, y)));
+            let columns = [left, right]
+                .into_iter()
+                .take(if ring == 0 { 0 } else { 2 })
+                .filter(|x| (min_x..=max_x).contains(x))
+                .flat_map(|x| {
+                    ((top + 1).max(min_y)..=(bottom - 1).min(max_y)).map(move |y| (x, y))
+                });
+            for (x, y) in rows.chain(columns) {
+                if let Some(indices) = self.cells.get(&(x as i32, y as i32)) {
+                    distance = visit(indices);
+                }
+            }
+            // Items that weren't visited yet don't touch any cell within this ring, so they're
+            // at least `ring` cells away.
+            let min_remaining_distance = ring as f32 * self.cell_size;
+            if distance <= min_remaining_distance * min_remaining_distance {
+                break;
+            }
+        }
+        nearest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spatial_index::tests::check_spatial_index;
+
+    #[test]
+    fn test_uniform_grid() {
+        check_spatial_index(UniformGrid::new(10.0));
+        check_spatial_index(UniformGrid::new(3.0));
+        check_spatial_index(UniformGrid::new(0.5));
+    }
+
+    #[test]
+    fn test_huge_bounds() {
+        let mut grid = UniformGrid::new(1.0);
+        let small = grid.insert(Bounds::from_xywh(0.5, 0.5, 2.0, 2.0), "small");
+        let everything = Bounds::new(
+            Point {
+                x: f32::NEG_INFINITY,
+                y: f32::NEG_INFINITY,
+            },
+            Point {
+                x: f32::INFINITY,
+                y: f32::INFINITY,
+            },
+        );
+        let huge = grid.insert(everything, "huge");
+
+        let mut results = grid
+            .query(everything)
+            .map(|(_, data)| *data)
+            .collect::<Vec<_>>();
+        results.sort();
+        assert_eq!(results, ["huge", "small"]);
+        assert_eq!(
+            grid.nearest(Point { x: 1.0e9, y: 0.0 })
+                .map(|(_, data)| *data),
+            Some("huge")
+        );
+
+        assert_eq!(grid.remove(huge), Some("huge"));
+        assert_eq!(grid.query(everything).count(), 1);
+        assert_eq!(grid.remove(small), Some("small"));
+        assert_eq!(grid.query(everything).count(), 0);
+    }
+
+    #[test]
+    fn test_nearest_far_from_items() {
+        let mut grid = UniformGrid::new(1.0);
+        grid.insert(Bounds::from_xywh(0.0, 0.0, 2.0, 2.0), "a");
+        grid.insert(Bounds::from_xywh(10.0, 5.0, 2.0, 2.0), "b");
+
+        // Searching every cell of every ring up to the occupied range would visit ~10^12
+        // cells here.
+        let far = Point { x: 1.0e6, y: 6.0 };
+        assert_eq!(grid.nearest(far).map(|(_, data)| *data), Some("b"));
+        let far = Point {
+            x: -1.0e6,
+            y: -1.0e6,
+        };
+        assert_eq!(grid.nearest(far).map(|(_, data)| *data), Some("a"));
+        let inside = Point { x: 9.0, y: 6.0 };
+        assert_eq!(grid.nearest(inside).map(|(_, data)| *data), Some("b"));
+    }
+}
@@ -0,0 +1,171 @@
+use crate::{bounds_tree::Node, BoundingBox, BoundsTree};
+use std::{error::Error, fmt};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    RootOutOfRange {
+        root: usize,
+    },
+    // There are nodes, but no root to reach them from.
+    MissingRoot,
+    ChildOutOfRange {
+        node: usize,
+        child: usize,
+    },
+    // A node is the child of more than one parent, or of one of its own descendants.
+    NodeReachableTwice {
+        node: usize,
+    },
+    UnreachableNodes {
+        count: usize,
+    },
+    ChildNotEnclosed {
+        node: usize,
+        child: usize,
+    },
+    InconsistentMaxOrder {
+        node: usize,
+        expected: u32,
+        actual: u32,
+    },
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::RootOutOfRange { root } => write!(f, "root {root} is out of range"),
+            ValidationError::MissingRoot => write!(f, "tree has nodes but no root"),
+            ValidationError::ChildOutOfRange { node, child } => {
+                write!(f, "child {child} of node {node} is out of range")
+            }
+            ValidationError::NodeReachableTwice { node } => {
+                write!(f, "node {node} is reachable more than once")
+            }
+            ValidationError::UnreachableNodes { count } => {
+                write!(f, "{count} nodes are unreachable from the root")
+            }
+            ValidationError::ChildNotEnclosed { node, child } => {
+                write!(f, "bounds of node {node} don't enclose its child {child}")
+            }
+            ValidationError::InconsistentMaxOrder {
+                node,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "node {node} has a max order of {actual}, but its children's is {expected}"
+            ),
+        }
+    }
+}
+
+impl Error for ValidationError {}
+
+impl<T: Clone, B: BoundingBox> BoundsTree<T, B> {
+    // Checks the structural invariants of the tree: every node is reachable from the root
+    // exactly once, internal nodes enclose their children, and every internal node stores the
+    // maximum order within its subtree. A tree only built through this crate's API always
+    // passes; this is meant for debugging and for trees loaded from untrusted data.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        let nodes = &self.tree.nodes;
+        let Some(root) = self.tree.root else {
+            return if nodes.is_empty() {
+                Ok(())
+            } else {
+                Err(ValidationError::MissingRoot)
+            };
+        };
+        if root >= nodes.len() {
+            return Err(ValidationError::RootOutOfRange { root });
+        }
+
+        let mut visited = vec![false; nodes.len()];
+        let mut stack = vec![root];
+        while let Some(index) = stack.pop() {
+            if visited[index] {
+                return Err(ValidationError::NodeReachableTwice { node: index });
+            }
+            visited[index] = true;
+
+            let Node::Internal {
+                left,
+                right,
+                bounds,
+                summary,
+            } = nodes[index]
+            else {
+                continue;
+            };
+            for child in [left, right] {
+                let Some(child_node) = nodes.get(child) else {
+                    return Err(ValidationError::ChildOutOfRange { node: index, child });
+                };
+                if !bounds.contains(child_node.bounds()) {
+                    return Err(ValidationError::ChildNotEnclosed { node: index, child });
+                }
+            }
+            let expected = nodes[left].summary().max(nodes[right].summary());
+            if summary != expected {
+                return Err(ValidationError::InconsistentMaxOrder {
+                    node: index,
+                    expected,
+                    actual: summary,
+                });
+            }
+            stack.extend([left, right]);
+        }
+
+        let count = visited.iter().filter(|visited| !**visited).count();
+        if count > 0 {
+            return Err(ValidationError::UnreachableNodes { count });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Bounds, Point};
+
+    #[test]
+    fn test_validate() {
+        let mut tree = BoundsTree::new();
+        assert_eq!(tree.validate(), Ok(()));
+        for (ix, x) in [0.0, 5.0, 20.0, 22.0].into_iter().enumerate() {
+            let bounds = Bounds {
+                min: Point { x, y: 0.0 },
+                max: Point {
+                    x: x + 10.0,
+                    y: 10.0,
+                },
+            };
+            tree.insert(bounds, ix);
+        }
+        assert_eq!(tree.validate(), Ok(()));
+
+        let root = tree.tree.root.unwrap();
+        let Node::Internal { summary, .. } = &mut tree.tree.nodes[root] else {
+            unreachable!()
+        };
+        *summary = 7;
+        assert_eq!(
+            tree.validate(),
+            Err(ValidationError::InconsistentMaxOrder {
+                node: root,
+                expected: 2,
+                actual: 7
+            })
+        );
+
+        let Node::Internal { left, summary, .. } = &mut tree.tree.nodes[root] else {
+            unreachable!()
+        };
+        *summary = 2;
+        *left = root;
+        assert_eq!(
+            tree.validate(),
+            Err(ValidationError::NodeReachableTwice { node: root })
+        );
+    }
+}
@@ -0,0 +1,146 @@
+use crate::{Bounds, Error, PersistentBoundsTree, Point};
+use std::collections::HashMap;
+use wasm_bindgen::prelude::*;
+
+// A JS-friendly tree of `f32` bounds identified by `u32` ids. Bulk methods take and return
+// typed arrays, with bounds packed as `[min_x, min_y, max_x, max_y]` quadruples, to avoid
+// crossing the JS boundary once per primitive.
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct WasmBoundsTree {
+    tree: PersistentBoundsTree<u32>,
+    bounds: HashMap<u32, Bounds>,
+}
+
+#[wasm_bindgen]
+impl WasmBoundsTree {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn size(&self) -> usize {
+        self.bounds.len()
+    }
+
+    // Inserts a primitive and returns its order. Inserting an id that is already present
+    // replaces its bounds. Fails if the coordinates are NaN or the minimum exceeds the maximum.
+    pub fn insert(
+        &mut self,
+        id: u32,
+        min_x: f32,
+        min_y: f32,
+        max_x: f32,
+        max_y: f32,
+    ) -> Result<u32, JsError> {
+        let bounds = valid_bounds(min_x, min_y, max_x, max_y)?;
+        Ok(self.insert_bounds(id, bounds))
+    }
+
+    // Inserts one primitive per id, returning their orders. Nothing is inserted if any of the
+    // bounds are invalid.
+    #[wasm_bindgen(js_name = insertMany)]
+    pub fn insert_many(&mut self, ids: &[u32], bounds: &[f32]) -> Result<Vec<u32>, JsError> {
+        if bounds.len() != ids.len() * 4 {
+            return Err(JsError::new("expected four coordinates per id"));
+        }
+        let bounds = bounds
+            .chunks_exact(4)
+            .map(|bounds| valid_bounds(bounds[0], bounds[1], bounds[2], bounds[3]))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(ids
+            .iter()
+            .zip(bounds)
+            .map(|(id, bounds)| self.insert_bounds(*id, bounds))
+            .collect())
+    }
+
+    // Returns whether a primitive with the given id was present.
+    pub fn remove(&mut self, id: u32) -> bool {
+        let Some(bounds) = self.bounds.remove(&id) else {
+            return false;
+        };
+        // Only valid bounds are inserted, so every tracked id can be found in the tree.
+        self.tree = self
+            .tree
+            .remove(bounds, &id)
+            .expect("every tracked id is in the tree");
+        true
+    }
+
+    // Returns the ids of all primitives intersecting the given bounds.
+    pub fn query(&self, min_x: f32, min_y: f32, max_x: f32, max_y: f32) -> Vec<u32> {
+        let bounds = Bounds {
+            min: Point { x: min_x, y: min_y },
+            max: Point { x: max_x, y: max_y },
+        };
+        self.tree
+            .query(bounds)
+            .map(|primitive| *primitive.data)
+            .collect()
+    }
+}
+
+impl WasmBoundsTree {
+    fn insert_bounds(&mut self, id: u32, bounds: Bounds) -> u32 {
+        self.remove(id);
+        let (tree, order) = self.tree.insert(bounds, id);
+        self.tree = tree;
+        self.bounds.insert(id, bounds);
+        order
+    }
+}
+
+// Bounds that can't be found in the tree again, like ones with NaN coordinates, would make
+// removing their id trap, so they're rejected on the way in.
+fn valid_bounds(min_x: f32, min_y: f32, max_x: f32, max_y: f32) -> Result<Bounds, Error> {
+    let bounds = Bounds {
+        min: Point { x: min_x, y: min_y },
+        max: Point { x: max_x, y: max_y },
+    };
+    if !bounds.is_valid() {
+        return Err(Error::InvalidBounds {
+            bounds: format!("{bounds:?}"),
+        });
+    }
+    Ok(bounds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wasm_tree() {
+        let mut tree = WasmBoundsTree::new();
+        let orders = tree
+            .insert_many(
+                &[1, 2, 3],
+                &[
+                    0.0, 0.0, 10.0, 10.0, 5.0, 5.0, 15.0, 15.0, 20.0, 20.0, 30.0, 30.0,
+                ],
+            )
+            .unwrap();
+        assert_eq!(orders, [1, 2, 1]);
+        assert_eq!(tree.size(), 3);
+
+        let mut hits = tree.query(8.0, 8.0, 9.0, 9.0);
+        hits.sort();
+        assert_eq!(hits, [1, 2]);
+
+        assert!(tree.remove(2));
+        assert!(!tree.remove(2));
+        assert_eq!(tree.query(8.0, 8.0, 9.0, 9.0), [1]);
+        assert_eq!(tree.insert(3, 8.0, 8.0, 9.0, 9.0).unwrap(), 2);
+        assert_eq!(tree.size(), 2);
+    }
+
+    #[test]
+    fn test_invalid_bounds() {
+        // Building a `JsError` needs a JS host, so the validation is checked directly.
+        assert!(valid_bounds(f32::NAN, 0.0, 10.0, 10.0).is_err());
+        assert!(valid_bounds(10.0, 0.0, 0.0, 10.0).is_err());
+        assert!(valid_bounds(0.0, 0.0, 10.0, 10.0).is_ok());
+    }
+}